@@ -1,10 +1,36 @@
 use penguin_app::{config::AppConfig, App};
 use penguin_config::PenguinConfig;
+use penguin_renderer::renderer::device_report::DeviceReport;
+
+const USAGE: &str = "usage: penguin-engine-inner [device-report [--json]]";
 
 fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        [] => run_app(),
+        ["device-report"] => print_device_report(false),
+        ["device-report", "--json"] => print_device_report(true),
+        _ => {
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    }
+}
+
+fn run_app() {
     App::builder(AppConfig::read_config())
         .add_plugin(penguin_app::time_plugin::TimePlugin)
         .add_plugin(penguin_renderer::renderer::RendererPlugin)
         .run()
         .expect("app run loop failed");
 }
+
+/// Prints what the machine's GPUs support, to attach to rendering bug reports.
+fn print_device_report(json: bool) {
+    let report = DeviceReport::collect().expect("couldn't query the physical devices");
+    if json {
+        println!("{}", report.to_json());
+    } else {
+        print!("{}", report);
+    }
+}
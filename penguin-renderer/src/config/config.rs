@@ -1,6 +1,22 @@
+use crate::renderer::vk_types::DeviceFeature;
+use ash::vk;
+
 /// The required extensions for the physical device that we will be selecting.
 pub const REQUIRED_DEVICE_EXTENSIONS: [&'static str; 1] = ["VK_KHR_swapchain"];
 
+/// Features devices without are rejected. Subsystems that can do without a feature request it
+/// themselves.
+pub const REQUIRED_DEVICE_FEATURES: [DeviceFeature; 4] = [
+    DeviceFeature::FillModeNonSolid, // for wireframe mode
+    DeviceFeature::GeometryShader,
+    DeviceFeature::TessellationShader,
+    DeviceFeature::ShaderFloat64,
+];
+
+/// Environment variable that selects the physical device by index or part of its name,
+/// overriding `RendererConfig::physical_device`.
+pub const PHYSICAL_DEVICE_ENV_VAR: &str = "PENGUIN_PHYSICAL_DEVICE";
+
 #[cfg(all(debug_assertions))]
 const DEBUG_ENABLED: bool = true;
 #[cfg(not(debug_assertions))]
@@ -17,10 +33,65 @@ pub struct ValidationInfo {
     pub required_validation_layers: [&'static str; 1],
 }
 
+/// Names Vulkan objects after their resources and labels command buffer regions, for
+/// validation messages and graphics debuggers like RenderDoc.
+pub const VK_DEBUG_NAMES_ENABLE: bool = DEBUG_ENABLED;
+
 /// Weather to use verbose vulkan validation layer logging
 pub const VK_VERBOSE_LOGGING_ENABLE: bool = false;
 
-// 2 == double buffering
-pub const MAX_FRAMES_COUNT: usize = 2;
+/// Frames in flight used when the renderer config doesn't set any. 2 == double buffering
+pub const DEFAULT_FRAMES_IN_FLIGHT: usize = 2;
+
+/// Upper bound for the frames in flight set in the renderer config.
+pub const MAX_FRAMES_IN_FLIGHT: usize = 4;
+
+/// MSAA sample count used when the renderer config doesn't set any. Lowered to what the device
+/// supports. 1 == no MSAA
+pub const DEFAULT_MSAA_SAMPLES: u32 = 4;
+
+/// Format of the offscreen image the scene is rendered into before being tonemapped into the
+/// swapchain image.
+pub const HDR_COLOR_FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+
+/// Exposure used when the renderer config doesn't set any.
+pub const DEFAULT_EXPOSURE: f32 = 1.0;
+
+/// Upper bound for the number of bloom downsample steps.
+pub const MAX_BLOOM_MIP_COUNT: u32 = 8;
+
+/// Size of the lookup table color grading uses when the renderer config doesn't name one.
+pub const IDENTITY_COLOR_LUT_SIZE: u32 = 16;
+
+/// Depth format of the shadow map. Has to support sampling with a comparison sampler.
+pub const SHADOW_MAP_FORMAT: vk::Format = vk::Format::D32_SFLOAT;
+
+/// Upper bound for the number of shadow cascades set in the renderer config.
+pub const MAX_SHADOW_CASCADES: usize = 4;
+
+/// Upper bound for the shadow filter radius set in the renderer config, in shadow map texels.
+pub const MAX_SHADOW_PCF_RADIUS: u32 = 3;
 
 pub const MAX_OBJECTS: usize = 10_000;
+
+/// Size of the light buffer. Lights past this are ignored.
+pub const MAX_LIGHTS: usize = 256;
+
+/// Ambient light used when the renderer config doesn't set any.
+pub const DEFAULT_AMBIENT_COLOR: [f32; 3] = [0.03, 0.03, 0.03];
+
+/// Whether to use one global texture array indexed per object (bindless) when the device
+/// supports descriptor indexing. Falls back to one descriptor set per texture otherwise.
+pub const BINDLESS_TEXTURES_ENABLE: bool = true;
+
+/// Size of the global bindless texture array.
+pub const MAX_BINDLESS_TEXTURES: u32 = 1024;
+
+/// Size in bytes of the ring buffer that per-frame uniform and storage data is pushed into.
+pub const UNIFORM_RING_BUFFER_SIZE: u64 = 1024 * 1024;
+
+/// Profile scopes each frame can record GPU timestamps for, two queries each.
+pub const MAX_GPU_PROFILE_SCOPES: u32 = 32;
+
+/// Occlusion queries each frame can record.
+pub const MAX_OCCLUSION_QUERIES: u32 = 256;
@@ -1,2 +1,5 @@
 pub mod config;
 pub use config::*;
+
+mod renderer_config;
+pub use renderer_config::*;
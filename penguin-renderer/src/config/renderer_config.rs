@@ -0,0 +1,139 @@
+use crate::renderer::post_process::{PostProcessEffectConfig, TonemapOperator, TonemapSettings};
+use crate::renderer::debug::ValidationSettings;
+use crate::renderer::profiling::ProfilingSettings;
+use crate::renderer::shadows::ShadowSettings;
+use crate::renderer::vk_types::DepthSettings;
+use penguin_config::*;
+use serde::Deserialize;
+
+/// Renderer settings that can be changed without recompiling, read from `renderer-config.json`.
+#[derive(Debug, Clone, Deserialize, PenguinConfig)]
+#[penguin_config(path = "renderer-config.json")]
+pub struct RendererConfig {
+    /// How many frames the CPU may record ahead of the GPU.
+    #[serde(default = "default_frames_in_flight")]
+    pub frames_in_flight: usize,
+    /// Samples per pixel for multisample anti-aliasing, a power of two. 1 disables MSAA.
+    #[serde(default = "default_msaa_samples")]
+    pub msaa_samples: u32,
+    /// Curve used to map the HDR scene color to the screen: "aces", "reinhard" or "filmic".
+    #[serde(default)]
+    pub tonemap_operator: TonemapOperator,
+    /// Multiplier applied to the scene color before tonemapping.
+    #[serde(default = "default_exposure")]
+    pub exposure: f32,
+    /// Effects applied to the scene color, in order. Each entry names its `effect` ("bloom",
+    /// "color_grading", "vignette" or "fxaa"), can set `enabled` and the effect's settings.
+    #[serde(default = "default_post_process")]
+    pub post_process: Vec<PostProcessEffectConfig>,
+    /// Linear rgb light that reaches every surface, also where no light shines.
+    #[serde(default = "default_ambient_color")]
+    pub ambient_color: [f32; 3],
+    /// Cascaded shadow map of the directional light.
+    #[serde(default)]
+    pub shadows: ShadowSettings,
+    /// Depth buffer of the scene. Only read at startup.
+    #[serde(default)]
+    pub depth: DepthSettings,
+    /// Index or part of the name of the GPU to use instead of the best scoring one, like
+    /// "nvidia". The `PENGUIN_PHYSICAL_DEVICE` environment variable overrides it.
+    #[serde(default)]
+    pub physical_device: Option<String>,
+    /// GPU timings of the render graph passes.
+    #[serde(default)]
+    pub profiling: ProfilingSettings,
+    /// What to do with validation layer messages, when validation is enabled.
+    #[serde(default)]
+    pub validation: ValidationSettings,
+}
+
+fn default_frames_in_flight() -> usize {
+    super::DEFAULT_FRAMES_IN_FLIGHT
+}
+
+fn default_msaa_samples() -> u32 {
+    super::DEFAULT_MSAA_SAMPLES
+}
+
+fn default_exposure() -> f32 {
+    super::DEFAULT_EXPOSURE
+}
+
+fn default_ambient_color() -> [f32; 3] {
+    super::DEFAULT_AMBIENT_COLOR
+}
+
+fn default_post_process() -> Vec<PostProcessEffectConfig> {
+    PostProcessEffectConfig::default_chain()
+}
+
+impl Default for RendererConfig {
+    fn default() -> Self {
+        Self {
+            frames_in_flight: default_frames_in_flight(),
+            msaa_samples: default_msaa_samples(),
+            tonemap_operator: TonemapOperator::default(),
+            exposure: default_exposure(),
+            post_process: default_post_process(),
+            ambient_color: default_ambient_color(),
+            shadows: ShadowSettings::default(),
+            depth: DepthSettings::default(),
+            physical_device: None,
+            profiling: ProfilingSettings::default(),
+            validation: ValidationSettings::default(),
+        }
+    }
+}
+
+impl RendererConfig {
+    /// Clamps the settings to values the renderer supports.
+    pub fn validated(mut self) -> Self {
+        let frames_in_flight = self
+            .frames_in_flight
+            .clamp(1, super::MAX_FRAMES_IN_FLIGHT);
+
+        if frames_in_flight != self.frames_in_flight {
+            log::warn!(
+                "frames_in_flight {} is out of range, using {}",
+                self.frames_in_flight,
+                frames_in_flight
+            );
+            self.frames_in_flight = frames_in_flight;
+        }
+
+        // round down to a power of two, the device's limit is applied at startup
+        let msaa_samples = match self.msaa_samples {
+            0 => 1,
+            samples => 1 << (31 - samples.leading_zeros()),
+        };
+
+        if msaa_samples != self.msaa_samples {
+            log::warn!(
+                "msaa_samples {} isn't a power of two, using {}",
+                self.msaa_samples,
+                msaa_samples
+            );
+            self.msaa_samples = msaa_samples;
+        }
+
+        if !(self.exposure.is_finite() && self.exposure > 0.0) {
+            log::warn!(
+                "exposure {} isn't a positive number, using {}",
+                self.exposure,
+                default_exposure()
+            );
+            self.exposure = default_exposure();
+        }
+
+        self.shadows = self.shadows.validated();
+
+        self
+    }
+
+    pub fn tonemap_settings(&self) -> TonemapSettings {
+        TonemapSettings {
+            operator: self.tonemap_operator,
+            exposure: self.exposure,
+        }
+    }
+}
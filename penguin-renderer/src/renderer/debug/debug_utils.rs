@@ -0,0 +1,96 @@
+use crate::renderer::vk_types::VkContext;
+use ash::vk;
+use std::ffi::CString;
+
+/// Whether the debug utils extension is enabled, which the validation messages, object names
+/// and command buffer labels go through.
+pub(crate) const fn debug_utils_enabled() -> bool {
+    crate::config::VK_VALIDATION.is_enabled || crate::config::VK_DEBUG_NAMES_ENABLE
+}
+
+/// Label names can't contain nul bytes, those are cut off.
+fn label_name(name: &str) -> CString {
+    let name = name.split('\0').next().unwrap_or_default();
+    CString::new(name).expect("name without nul bytes")
+}
+
+impl VkContext {
+    /// Names the object in validation messages and graphics debuggers, like "mesh:monkey".
+    pub fn set_object_name<H: vk::Handle>(&self, handle: H, name: &str) {
+        if !debug_utils_enabled() {
+            return;
+        }
+
+        let name = label_name(name);
+        let name_info = vk::DebugUtilsObjectNameInfoEXT::builder()
+            .object_type(H::TYPE)
+            .object_handle(handle.as_raw())
+            .object_name(&name);
+
+        let result = unsafe {
+            self.debug_messenger
+                .debug_utils_loader
+                .debug_utils_set_object_name(self.device.handle.handle(), &name_info)
+        };
+        // names only help debugging, an object without one works the same
+        if let Err(error) = result {
+            log::warn!("Couldn't set debug object name {:?}: {}", name, error);
+        }
+    }
+
+    /// Begins a labeled region of the command buffer, ended by `cmd_end_label`. Regions can
+    /// nest.
+    pub fn cmd_begin_label(&self, command_buffer: vk::CommandBuffer, name: &str) {
+        if !debug_utils_enabled() {
+            return;
+        }
+
+        let name = label_name(name);
+        let label = vk::DebugUtilsLabelEXT::builder().label_name(&name);
+
+        unsafe {
+            self.debug_messenger
+                .debug_utils_loader
+                .cmd_begin_debug_utils_label(command_buffer, &label)
+        };
+    }
+
+    pub fn cmd_end_label(&self, command_buffer: vk::CommandBuffer) {
+        if !debug_utils_enabled() {
+            return;
+        }
+
+        unsafe {
+            self.debug_messenger
+                .debug_utils_loader
+                .cmd_end_debug_utils_label(command_buffer)
+        };
+    }
+
+    /// Marks a single point of the command buffer.
+    pub fn cmd_insert_label(&self, command_buffer: vk::CommandBuffer, name: &str) {
+        if !debug_utils_enabled() {
+            return;
+        }
+
+        let name = label_name(name);
+        let label = vk::DebugUtilsLabelEXT::builder().label_name(&name);
+
+        unsafe {
+            self.debug_messenger
+                .debug_utils_loader
+                .cmd_insert_debug_utils_label(command_buffer, &label)
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn label_names_are_cut_off_at_nul_bytes() {
+        assert_eq!(label_name("mesh:monkey").to_str(), Ok("mesh:monkey"));
+        assert_eq!(label_name("mesh\0monkey").to_str(), Ok("mesh"));
+    }
+}
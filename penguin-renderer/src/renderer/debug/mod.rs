@@ -1 +1,10 @@
+mod debug_utils;
+pub(crate) use debug_utils::debug_utils_enabled;
+
+mod validation_parser;
+pub use validation_parser::*;
+
+mod validation_report;
+pub use validation_report::*;
+
 pub mod validation_layers;
@@ -0,0 +1,448 @@
+use serde::Serialize;
+use std::fmt;
+
+/// An object a validation message is about.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ContextObj {
+    pub index: String,
+    pub vk_type: String,
+    pub handle: String,
+    /// set with `VkContext::set_object_name`
+    pub name: Option<String>,
+}
+
+/// A validation layer message, split into its parts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ValidationError {
+    /// the message's full id, like "VUID-vkCmdDraw-None-02699"
+    pub vuid: String,
+    /// readable part of the id, like "vkCmdDraw-None"
+    pub vulkan_id: String,
+    /// hash of the id, like "0x2c5c1d5f", empty if the message doesn't have one
+    pub message_id: String,
+    pub context_objects: Vec<ContextObj>,
+    /// what went wrong, with the objects' handles replaced by their names or indices
+    pub message: String,
+    /// what "The Vulkan spec states", empty if the message doesn't say
+    pub spec_info: String,
+    pub spec_link: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseError {
+    Empty,
+    /// the message starts with an id that's missing its "]"
+    UnclosedId,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Empty => write!(f, "the validation message is empty"),
+            ParseError::UnclosedId => write!(f, "the validation message's id isn't closed"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl ValidationError {
+    /// The whole message, for messages that couldn't be parsed.
+    pub fn unparsed(input: &str) -> Self {
+        Self {
+            vuid: String::new(),
+            vulkan_id: String::new(),
+            message_id: String::new(),
+            context_objects: Vec::new(),
+            message: input.trim().to_owned(),
+            spec_info: String::new(),
+            spec_link: String::new(),
+        }
+    }
+
+    /// Sets the id, for messages that only have it on the side.
+    pub fn set_vuid(&mut self, vuid: &str) {
+        self.vuid = vuid.to_owned();
+        self.vulkan_id = readable_id(vuid).to_owned();
+    }
+
+    /// The parsed fields as a JSON object, on a single line.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("Couldn't serialize validation message")
+    }
+}
+
+pub fn parse_vk_general_message(input: &str) -> &str {
+    input
+}
+
+/// Splits a validation layer message into its parts.
+///
+///     Understands the layouts of current and older layers:
+///     "Validation Error: [ VUID ] Object 0: handle = 0x1, type = VK_OBJECT_TYPE_BUFFER; | MessageID = 0x2 | message",
+///     "[ VUID ] Object: 0x1 (Type = 9) | message",
+///     and messages without an id, which newer layers pass separately. Those still link to it in
+///     the spec. An "Objects:" list after the message, like the layers print when they log on
+///     their own, is parsed too.
+pub fn parse_vk_validation_error_message(input: &str) -> Result<ValidationError, ParseError> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err(ParseError::Empty);
+    }
+
+    let (vuid, objects, message_id, message) = match split_id(input)? {
+        Some((vuid, rest)) => {
+            let (objects, message_id, message) = split_sections(rest);
+            (vuid, objects, message_id, message)
+        }
+        None => ("", "", "", input),
+    };
+
+    let mut context_objects = parse_objects(objects);
+    let (message, listed_objects) = split_object_list(message);
+    context_objects.extend(listed_objects);
+
+    let (message, spec) = message
+        .split_once("The Vulkan spec states:")
+        .unwrap_or((message, ""));
+    let (spec_info, spec_link) = split_spec_link(spec);
+
+    // messages without an id still link to it
+    let vuid = if vuid.is_empty() {
+        spec_link.rsplit_once('#').map_or("", |(_, anchor)| anchor)
+    } else {
+        vuid
+    };
+
+    Ok(ValidationError {
+        vuid: vuid.to_owned(),
+        vulkan_id: readable_id(vuid).to_owned(),
+        message_id: message_id.to_owned(),
+        message: replace_handles(message.trim(), &context_objects),
+        context_objects,
+        spec_info: spec_info.to_owned(),
+        spec_link: spec_link.to_owned(),
+    })
+}
+
+// Helper functions --------------------------
+
+/// Splits "Validation Error: [ VUID ] rest" into the id and the rest, if the message starts
+/// with an id.
+fn split_id(input: &str) -> Result<Option<(&str, &str)>, ParseError> {
+    let open = match input.find('[') {
+        Some(open) => open,
+        None => return Ok(None),
+    };
+
+    // otherwise the bracket is part of the message, like in "pQueueCreateInfos[0]"
+    let prefix = input[..open].trim();
+    if !(prefix.is_empty() || prefix.starts_with("Validation") && prefix.ends_with(':')) {
+        return Ok(None);
+    }
+
+    let rest = &input[open + 1..];
+    let close = rest.find(']').ok_or(ParseError::UnclosedId)?;
+    Ok(Some((rest[..close].trim(), &rest[close + 1..])))
+}
+
+/// Splits "Object 0: ...; | MessageID = 0x1 | message" into its sections, any of which may be
+/// missing. Only the separators in front of the message count, the message itself can contain
+/// flags like "A|B".
+fn split_sections(rest: &str) -> (&str, &str, &str) {
+    let mut rest = rest.trim_start();
+
+    let mut objects = "";
+    if rest.starts_with("Object") {
+        let (section, after) = rest.split_once('|').unwrap_or((rest, ""));
+        objects = section;
+        rest = after;
+    } else {
+        rest = rest.strip_prefix('|').unwrap_or(rest);
+    }
+    rest = rest.trim_start();
+
+    let mut message_id = "";
+    if let Some(after_key) = rest.strip_prefix("MessageID") {
+        let (section, after) = after_key.split_once('|').unwrap_or((after_key, ""));
+        message_id = section.trim_start_matches(|c: char| c == '=' || c.is_whitespace());
+        message_id = message_id.trim();
+        rest = after;
+    }
+
+    (objects, message_id, rest.trim())
+}
+
+/// Objects of "Object 0: handle = 0x1, name = mesh:monkey, type = VK_OBJECT_TYPE_BUFFER;
+/// Object 1: ...", where only named objects have a name, or of the older
+/// "Object: 0x1 (Type = 9)".
+fn parse_objects(section: &str) -> Vec<ContextObj> {
+    section
+        .split(';')
+        .filter_map(|object| {
+            let (index, fields) = object.trim().strip_prefix("Object")?.split_once(':')?;
+            let index = index.trim();
+
+            if let Some((handle, vk_type)) = fields.split_once("(Type =") {
+                return Some(ContextObj {
+                    index: if index.is_empty() { "0" } else { index }.to_owned(),
+                    vk_type: vk_type.trim().trim_end_matches(')').trim().to_owned(),
+                    handle: handle.trim().to_owned(),
+                    name: None,
+                });
+            }
+
+            let field = |key: &str| {
+                fields.split(", ").find_map(|field| {
+                    let (field_key, value) = field.split_once('=')?;
+                    if field_key.trim() == key {
+                        Some(value.trim().to_owned())
+                    } else {
+                        None
+                    }
+                })
+            };
+
+            Some(ContextObj {
+                index: index.to_owned(),
+                vk_type: field("type").unwrap_or_else(|| "Unknown type".to_owned()),
+                handle: field("handle").unwrap_or_else(|| "Unknown handle".to_owned()),
+                name: field("name"),
+            })
+        })
+        .collect()
+}
+
+/// Splits off the list the layers put after the message when they log it on their own:
+/// "Objects: 1\n    [0] 0x1, type: 9, name: NULL".
+fn split_object_list(message: &str) -> (&str, Vec<ContextObj>) {
+    let (message, list) = match message.split_once("\nObjects:") {
+        Some(split) => split,
+        None => return (message, Vec::new()),
+    };
+
+    let objects = list
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let (index, fields) = line.trim().strip_prefix('[')?.split_once(']')?;
+            let mut fields = fields.split(", ");
+
+            let mut object = ContextObj {
+                index: index.trim().to_owned(),
+                vk_type: "Unknown type".to_owned(),
+                handle: fields.next()?.trim().to_owned(),
+                name: None,
+            };
+            for field in fields {
+                match field
+                    .split_once(':')
+                    .map(|(key, value)| (key.trim(), value.trim()))
+                {
+                    Some(("type", vk_type)) => object.vk_type = vk_type.to_owned(),
+                    Some(("name", name)) if name != "NULL" => object.name = Some(name.to_owned()),
+                    _ => {}
+                }
+            }
+            Some(object)
+        })
+        .collect();
+
+    (message, objects)
+}
+
+/// Splits "the spec text. (https://link)" into the text and the link.
+fn split_spec_link(spec: &str) -> (&str, &str) {
+    let spec = spec.trim();
+    match spec.rfind("(https://") {
+        Some(start) => (
+            spec[..start].trim_end(),
+            spec[start + 1..].trim_end_matches(')'),
+        ),
+        None => (spec, ""),
+    }
+}
+
+/// "VUID-vkCmdDraw-None-02699" -> "vkCmdDraw-None". Other ids, like the "UNASSIGNED-" ones,
+/// are readable already.
+fn readable_id(vuid: &str) -> &str {
+    let id = match vuid.strip_prefix("VUID-") {
+        Some(id) => id,
+        None => return vuid,
+    };
+    match id.rsplit_once('-') {
+        Some((readable, number))
+            if !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit()) =>
+        {
+            readable
+        }
+        _ => id,
+    }
+}
+
+/// Replaces the objects' handles in the message with "|name|", or "|index|" for unnamed
+/// objects. The layers write handles like "0x1[name]", or "0x1[]" for unnamed objects.
+fn replace_handles(message: &str, objects: &[ContextObj]) -> String {
+    let mut message = message.to_owned();
+    for object in objects {
+        // a null handle isn't an object that can be told apart, neither is a missing one
+        let handle = object
+            .handle
+            .strip_prefix("0x")
+            .and_then(|hex| u64::from_str_radix(hex, 16).ok());
+        if matches!(handle, None | Some(0)) {
+            continue;
+        }
+        let placeholder = format!("|{}|", object.name.as_ref().unwrap_or(&object.index));
+        message = replace_handle(&message, object, &placeholder);
+    }
+    message
+}
+
+fn replace_handle(message: &str, object: &ContextObj, placeholder: &str) -> String {
+    let named_suffix = object.name.as_ref().map(|name| format!("[{}]", name));
+
+    let mut replaced = String::with_capacity(message.len());
+    let mut rest = message;
+    while let Some(start) = rest.find(&object.handle) {
+        replaced.push_str(&rest[..start]);
+        let after = &rest[start + object.handle.len()..];
+
+        // "0x1" is only the start of "0x1f"
+        if after.starts_with(|c: char| c.is_ascii_hexdigit()) {
+            replaced.push_str(&object.handle);
+            rest = after;
+            continue;
+        }
+
+        replaced.push_str(placeholder);
+        rest = match &named_suffix {
+            Some(suffix) if after.starts_with(suffix.as_str()) => &after[suffix.len()..],
+            _ => after.strip_prefix("[]").unwrap_or(after),
+        };
+    }
+    replaced.push_str(rest);
+    replaced
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Messages real layers sent, and what they should be parsed into.
+    const CORPUS: &[(&str, &str, &str)] = &[
+        (
+            "descriptor_set_not_updated",
+            include_str!("validation_corpus/descriptor_set_not_updated.txt"),
+            include_str!("validation_corpus/descriptor_set_not_updated.json"),
+        ),
+        (
+            "buffer_in_use",
+            include_str!("validation_corpus/buffer_in_use.txt"),
+            include_str!("validation_corpus/buffer_in_use.json"),
+        ),
+        (
+            "legacy_object_layout",
+            include_str!("validation_corpus/legacy_object_layout.txt"),
+            include_str!("validation_corpus/legacy_object_layout.json"),
+        ),
+        (
+            "best_practices_flags",
+            include_str!("validation_corpus/best_practices_flags.txt"),
+            include_str!("validation_corpus/best_practices_flags.json"),
+        ),
+        (
+            "layer_log_object_list",
+            include_str!("validation_corpus/layer_log_object_list.txt"),
+            include_str!("validation_corpus/layer_log_object_list.json"),
+        ),
+    ];
+
+    #[test]
+    fn corpus_parses_into_its_golden_fields() {
+        for (name, input, golden) in CORPUS {
+            let parsed = parse_vk_validation_error_message(input)
+                .unwrap_or_else(|error| panic!("{}: {}", name, error));
+            let parsed: serde_json::Value = serde_json::from_str(&parsed.to_json()).unwrap();
+            let golden: serde_json::Value = serde_json::from_str(golden).unwrap();
+
+            assert_eq!(parsed, golden, "{}", name);
+        }
+    }
+
+    #[test]
+    fn truncated_corpus_messages_dont_panic() {
+        for (_, input, _) in CORPUS {
+            for (split, _) in input.char_indices() {
+                let _ = parse_vk_validation_error_message(&input[..split]);
+                let _ = parse_vk_validation_error_message(&input[split..]);
+            }
+        }
+    }
+
+    #[test]
+    fn odd_brackets_dont_panic() {
+        for input in [
+            "] [",
+            "[",
+            "]",
+            "[ ]",
+            "Validation Error: ] VUID [",
+            "| [ |",
+        ] {
+            let _ = parse_vk_validation_error_message(input);
+        }
+    }
+
+    #[test]
+    fn empty_and_unclosed_messages_are_errors() {
+        assert_eq!(
+            parse_vk_validation_error_message("  \n"),
+            Err(ParseError::Empty)
+        );
+        assert_eq!(
+            parse_vk_validation_error_message("Validation Error: [ VUID-vkCmdDraw-None-02699"),
+            Err(ParseError::UnclosedId)
+        );
+    }
+
+    #[test]
+    fn brackets_in_the_message_arent_an_id() {
+        let input = "vkCreateDevice(): pCreateInfo->pQueueCreateInfos[0].queueCount is 0.";
+        let parsed = parse_vk_validation_error_message(input).unwrap();
+
+        assert_eq!(parsed.vuid, "");
+        assert_eq!(parsed.message, input);
+    }
+
+    #[test]
+    fn only_vuids_are_shortened() {
+        assert_eq!(readable_id("VUID-vkCmdDraw-None-02699"), "vkCmdDraw-None");
+        assert_eq!(
+            readable_id("UNASSIGNED-CoreValidation-DrawState-InvalidImageLayout"),
+            "UNASSIGNED-CoreValidation-DrawState-InvalidImageLayout"
+        );
+        assert_eq!(readable_id(""), "");
+    }
+
+    #[test]
+    fn handles_are_only_replaced_whole() {
+        let object = |handle: &str| ContextObj {
+            index: "0".to_owned(),
+            vk_type: "VK_OBJECT_TYPE_BUFFER".to_owned(),
+            handle: handle.to_owned(),
+            name: None,
+        };
+
+        assert_eq!(
+            replace_handles("VkBuffer 0x1[] and VkImage 0x1f", &[object("0x1")]),
+            "VkBuffer |0| and VkImage 0x1f"
+        );
+        for null in ["0x0", "0x0000000000000000"] {
+            assert_eq!(
+                replace_handles("VkBuffer 0x0000000000000000[]", &[object(null)]),
+                "VkBuffer 0x0000000000000000[]"
+            );
+        }
+    }
+}
@@ -0,0 +1,335 @@
+use crate::renderer::debug::ValidationError;
+use crate::renderer::vk_types::VkContext;
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ValidationSettings {
+    /// Ids of validation messages that are neither logged nor counted, like
+    /// "VUID-vkCmdDraw-None-02699".
+    pub suppressed: Vec<String>,
+    /// Panics at the end of a frame that caused validation errors, for tests.
+    pub fail_on_error: bool,
+    pub log_format: ValidationLogFormat,
+}
+
+/// How validation warnings and errors are logged.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ValidationLogFormat {
+    /// readable, with repeated messages cut short
+    #[default]
+    Text,
+    /// the parsed fields as one JSON object per message, for tools
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ValidationSeverity {
+    Warning,
+    Error,
+}
+
+/// A validation message and how often it came up.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationReportEntry {
+    pub severity: ValidationSeverity,
+    /// the first time the message came up
+    pub message: ValidationError,
+    pub count: usize,
+}
+
+pub type ValidationCallback = Arc<dyn Fn(ValidationSeverity, &ValidationError) + Send + Sync>;
+
+/// Collects the validation warnings and errors, shared with the debug messenger callback.
+///
+///     Messages are de-duplicated by their id, so every entry counts how often its message came
+///     up. Messages without an id are told apart by their text.
+#[derive(Default)]
+pub struct ValidationReport {
+    state: Mutex<ReportState>,
+}
+
+#[derive(Default)]
+struct ReportState {
+    entries: BTreeMap<String, ValidationReportEntry>,
+    suppressed: BTreeSet<String>,
+    suppressed_count: usize,
+    callback: Option<ValidationCallback>,
+    fail_on_error: bool,
+    log_format: ValidationLogFormat,
+}
+
+impl ValidationReport {
+    pub fn configure(&self, settings: &ValidationSettings) {
+        let mut state = self.state();
+        state.suppressed.extend(settings.suppressed.iter().cloned());
+        state.fail_on_error = settings.fail_on_error;
+        state.log_format = settings.log_format;
+    }
+
+    pub fn log_format(&self) -> ValidationLogFormat {
+        self.state().log_format
+    }
+
+    /// Messages with this id are neither logged nor counted from now on.
+    pub fn suppress(&self, vuid: &str) {
+        self.state().suppressed.insert(vuid.to_owned());
+    }
+
+    /// Called with every message that isn't suppressed, from the thread the Vulkan call that
+    /// caused it was made on. A panic in the callback is caught and logged, it can't unwind into
+    /// the driver.
+    pub fn set_callback(&self, callback: ValidationCallback) {
+        self.state().callback = Some(callback);
+    }
+
+    pub fn set_fail_on_error(&self, fail_on_error: bool) {
+        self.state().fail_on_error = fail_on_error;
+    }
+
+    /// Counts the message. Returns false if it's suppressed.
+    pub fn record(&self, severity: ValidationSeverity, message: &ValidationError) -> bool {
+        let callback = {
+            let mut state = self.state();
+            if state.suppressed.contains(&message.vuid) {
+                state.suppressed_count += 1;
+                return false;
+            }
+            state.callback.clone()
+        };
+
+        // without the lock, so the callback can use the report and make Vulkan calls that
+        // validate themselves
+        if let Some(callback) = callback {
+            if panic::catch_unwind(AssertUnwindSafe(|| callback(severity, message))).is_err() {
+                log::error!("Validation callback panicked on {}", message.vuid);
+            }
+        }
+
+        let mut state = self.state();
+        let key = if message.vuid.is_empty() {
+            message.message.clone()
+        } else {
+            message.vuid.clone()
+        };
+        state
+            .entries
+            .entry(key)
+            .or_insert_with(|| ValidationReportEntry {
+                severity,
+                message: message.clone(),
+                count: 0,
+            })
+            .count += 1;
+        true
+    }
+
+    /// Every distinct message so far.
+    pub fn entries(&self) -> Vec<ValidationReportEntry> {
+        self.state().entries.values().cloned().collect()
+    }
+
+    /// How many messages of the severity came up, duplicates included.
+    pub fn count(&self, severity: ValidationSeverity) -> usize {
+        self.state()
+            .entries
+            .values()
+            .filter(|entry| entry.severity == severity)
+            .map(|entry| entry.count)
+            .sum()
+    }
+
+    pub fn error_count(&self) -> usize {
+        self.count(ValidationSeverity::Error)
+    }
+
+    pub fn warning_count(&self) -> usize {
+        self.count(ValidationSeverity::Warning)
+    }
+
+    pub fn suppressed_count(&self) -> usize {
+        self.state().suppressed_count
+    }
+
+    /// Whether no validation errors came up. Warnings don't count.
+    pub fn is_clean(&self) -> bool {
+        self.error_count() == 0
+    }
+
+    /// Forgets the messages so far, to check a single frame.
+    pub fn clear(&self) {
+        let mut state = self.state();
+        state.entries.clear();
+        state.suppressed_count = 0;
+    }
+
+    /// Panics listing the validation errors if there are any.
+    pub fn assert_clean(&self) {
+        if !self.is_clean() {
+            panic!("validation errors came up:\n{}", self.summary());
+        }
+    }
+
+    /// Panics like `assert_clean` if failing on validation errors is enabled.
+    pub fn check(&self) {
+        if self.state().fail_on_error {
+            self.assert_clean();
+        }
+    }
+
+    /// One line per distinct message.
+    pub fn summary(&self) -> String {
+        self.state()
+            .entries
+            .iter()
+            .map(|(key, entry)| format!("{:?} {} ({}x)", entry.severity, key, entry.count))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, ReportState> {
+        // a panicking callback doesn't make the collected messages wrong
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl VkContext {
+    /// Validation messages of this context. Empty if validation is disabled.
+    pub fn validation_report(&self) -> &Arc<ValidationReport> {
+        &self.debug_messenger.report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn message(vuid: &str) -> ValidationError {
+        ValidationError {
+            vuid: vuid.to_owned(),
+            vulkan_id: String::new(),
+            message_id: String::new(),
+            context_objects: Vec::new(),
+            message: format!("{} message", vuid),
+            spec_info: String::new(),
+            spec_link: String::new(),
+        }
+    }
+
+    #[test]
+    fn messages_are_deduplicated_by_vuid() {
+        let report = ValidationReport::default();
+        report.record(ValidationSeverity::Error, &message("VUID-a"));
+        report.record(ValidationSeverity::Error, &message("VUID-a"));
+        report.record(ValidationSeverity::Warning, &message("VUID-b"));
+
+        let entries = report.entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].count, 2);
+        assert_eq!(report.error_count(), 2);
+        assert_eq!(report.warning_count(), 1);
+    }
+
+    #[test]
+    fn messages_without_vuid_are_told_apart_by_text() {
+        let report = ValidationReport::default();
+        report.record(ValidationSeverity::Error, &message(""));
+        report.record(
+            ValidationSeverity::Error,
+            &ValidationError {
+                message: "other".to_owned(),
+                ..message("")
+            },
+        );
+
+        assert_eq!(report.entries().len(), 2);
+    }
+
+    #[test]
+    fn suppressed_messages_are_only_counted_as_suppressed() {
+        let report = ValidationReport::default();
+        report.configure(&ValidationSettings {
+            suppressed: vec!["VUID-a".to_owned()],
+            ..Default::default()
+        });
+
+        assert!(!report.record(ValidationSeverity::Error, &message("VUID-a")));
+        assert!(report.is_clean());
+        assert_eq!(report.suppressed_count(), 1);
+    }
+
+    #[test]
+    fn callback_sees_every_unsuppressed_message() {
+        let report = ValidationReport::default();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let callback_calls = calls.clone();
+        report.set_callback(Arc::new(move |_, _| {
+            callback_calls.fetch_add(1, Ordering::Relaxed);
+        }));
+        report.suppress("VUID-b");
+
+        report.record(ValidationSeverity::Error, &message("VUID-a"));
+        report.record(ValidationSeverity::Error, &message("VUID-a"));
+        report.record(ValidationSeverity::Error, &message("VUID-b"));
+
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn callback_can_use_the_report() {
+        let report = Arc::new(ValidationReport::default());
+        let callback_report = Arc::downgrade(&report);
+        report.set_callback(Arc::new(move |_, _| {
+            if let Some(report) = callback_report.upgrade() {
+                report.error_count();
+            }
+        }));
+
+        assert!(report.record(ValidationSeverity::Error, &message("VUID-a")));
+    }
+
+    #[test]
+    fn panicking_callback_is_contained() {
+        let report = ValidationReport::default();
+        report.set_callback(Arc::new(|_, _| panic!("callback panicked")));
+
+        assert!(report.record(ValidationSeverity::Error, &message("VUID-a")));
+        assert_eq!(report.error_count(), 1);
+    }
+
+    #[test]
+    fn warnings_keep_a_frame_clean() {
+        let report = ValidationReport::default();
+        report.set_fail_on_error(true);
+        report.record(ValidationSeverity::Warning, &message("VUID-a"));
+
+        report.check();
+        assert!(report.is_clean());
+    }
+
+    #[test]
+    #[should_panic(expected = "VUID-a")]
+    fn errors_fail_the_check_in_fail_on_error_mode() {
+        let report = ValidationReport::default();
+        report.set_fail_on_error(true);
+        report.record(ValidationSeverity::Error, &message("VUID-a"));
+
+        report.check();
+    }
+
+    #[test]
+    fn cleared_report_is_clean() {
+        let report = ValidationReport::default();
+        report.record(ValidationSeverity::Error, &message("VUID-a"));
+        report.clear();
+
+        report.assert_clean();
+    }
+}
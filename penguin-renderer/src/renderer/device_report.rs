@@ -0,0 +1,502 @@
+use crate::renderer::vk_types::{
+    depth_format_candidates, renderer_feature_requests, DeviceFeature, Instance, SupportedFeatures,
+};
+use anyhow::Result;
+use ash::vk;
+use serde::Serialize;
+use std::fmt;
+
+/// What every physical device of the machine supports, for attaching to rendering bug reports.
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceReport {
+    pub instance_version: String,
+    pub devices: Vec<PhysicalDeviceReport>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PhysicalDeviceReport {
+    pub index: usize,
+    pub name: String,
+    pub device_type: String,
+    pub api_version: String,
+    pub driver_version: u32,
+    pub vendor_id: u32,
+    pub device_id: u32,
+    /// the limits the renderer depends on
+    pub limits: serde_json::Map<String, serde_json::Value>,
+    /// the Vulkan 1.0 features
+    pub features: Vec<FeatureReport>,
+    /// the features the renderer can enable, the chained 1.1 and 1.2 ones included
+    pub renderer_features: Vec<FeatureReport>,
+    /// what the renderer's subsystems would get when creating a device
+    pub subsystems: Vec<SubsystemReport>,
+    pub extensions: Vec<ExtensionReport>,
+    pub queue_families: Vec<QueueFamilyReport>,
+    pub memory_heaps: Vec<MemoryHeapReport>,
+    pub memory_types: Vec<MemoryTypeReport>,
+    pub formats: Vec<FormatReport>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FeatureReport {
+    pub name: &'static str,
+    pub supported: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SubsystemReport {
+    pub subsystem: &'static str,
+    pub required: bool,
+    pub enabled: bool,
+    /// the extensions and features the device lacks for it
+    pub missing: Vec<&'static str>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExtensionReport {
+    pub name: String,
+    pub spec_version: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QueueFamilyReport {
+    pub index: usize,
+    pub flags: String,
+    pub queue_count: u32,
+    pub timestamp_valid_bits: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MemoryHeapReport {
+    pub index: usize,
+    pub size: u64,
+    pub flags: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MemoryTypeReport {
+    pub index: usize,
+    pub heap_index: u32,
+    pub flags: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FormatReport {
+    pub format: String,
+    pub optimal_tiling: String,
+    pub linear_tiling: String,
+    pub buffer: String,
+}
+
+impl DeviceReport {
+    /// Creates an instance without a window and queries every physical device.
+    pub fn collect() -> Result<Self> {
+        let instance = Instance::init_headless()?;
+
+        let instance_version = version_string(instance.loader_version()?);
+
+        let physical_devices = unsafe { instance.enumerate_physical_devices() }?;
+        let devices = physical_devices
+            .into_iter()
+            .enumerate()
+            .map(|(index, physical_device)| {
+                PhysicalDeviceReport::collect(&instance, index, physical_device)
+            })
+            .collect::<Result<Vec<_>>>();
+
+        unsafe { instance.destroy_instance(None) };
+
+        Ok(Self {
+            instance_version,
+            devices: devices?,
+        })
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("couldn't serialize device report")
+    }
+}
+
+impl PhysicalDeviceReport {
+    fn collect(
+        instance: &Instance,
+        index: usize,
+        physical_device: vk::PhysicalDevice,
+    ) -> Result<Self> {
+        let properties = unsafe { instance.get_physical_device_properties(physical_device) };
+        let features = unsafe { instance.get_physical_device_features(physical_device) };
+        let memory_properties =
+            unsafe { instance.get_physical_device_memory_properties(physical_device) };
+        let queue_families =
+            unsafe { instance.get_physical_device_queue_family_properties(physical_device) };
+        let extensions =
+            unsafe { instance.enumerate_device_extension_properties(physical_device) }?;
+        let supported = SupportedFeatures::query(instance, physical_device);
+
+        let memory_heaps = memory_properties.memory_heaps
+            [..memory_properties.memory_heap_count as usize]
+            .iter()
+            .enumerate()
+            .map(|(index, heap)| MemoryHeapReport {
+                index,
+                size: heap.size,
+                flags: format!("{:?}", heap.flags),
+            })
+            .collect();
+
+        let memory_types = memory_properties.memory_types
+            [..memory_properties.memory_type_count as usize]
+            .iter()
+            .enumerate()
+            .map(|(index, memory_type)| MemoryTypeReport {
+                index,
+                heap_index: memory_type.heap_index,
+                flags: format!("{:?}", memory_type.property_flags),
+            })
+            .collect();
+
+        let formats = renderer_formats()
+            .into_iter()
+            .map(|format| {
+                let support = unsafe {
+                    instance.get_physical_device_format_properties(physical_device, format)
+                };
+                FormatReport {
+                    format: format!("{:?}", format),
+                    optimal_tiling: format!("{:?}", support.optimal_tiling_features),
+                    linear_tiling: format!("{:?}", support.linear_tiling_features),
+                    buffer: format!("{:?}", support.buffer_features),
+                }
+            })
+            .collect();
+
+        Ok(Self {
+            index,
+            name: crate::util::raw_c_string_to_string(&properties.device_name),
+            device_type: format!("{:?}", properties.device_type),
+            api_version: version_string(properties.api_version),
+            driver_version: properties.driver_version,
+            vendor_id: properties.vendor_id,
+            device_id: properties.device_id,
+            limits: limits(&properties.limits),
+            features: feature_list(&features),
+            renderer_features: DeviceFeature::ALL
+                .iter()
+                .map(|feature| FeatureReport {
+                    name: feature.name(),
+                    supported: supported.features.contains(feature),
+                })
+                .collect(),
+            subsystems: renderer_feature_requests()
+                .iter()
+                .map(|request| {
+                    let missing = request.missing(&supported);
+                    SubsystemReport {
+                        subsystem: request.subsystem,
+                        required: request.required,
+                        enabled: missing.is_empty(),
+                        missing,
+                    }
+                })
+                .collect(),
+            extensions: extensions
+                .iter()
+                .map(|extension| ExtensionReport {
+                    name: crate::util::raw_c_string_to_string(&extension.extension_name),
+                    spec_version: extension.spec_version,
+                })
+                .collect(),
+            queue_families: queue_families
+                .iter()
+                .enumerate()
+                .map(|(index, family)| QueueFamilyReport {
+                    index,
+                    flags: format!("{:?}", family.queue_flags),
+                    queue_count: family.queue_count,
+                    timestamp_valid_bits: family.timestamp_valid_bits,
+                })
+                .collect(),
+            memory_heaps,
+            memory_types,
+            formats,
+        })
+    }
+}
+
+fn version_string(version: u32) -> String {
+    format!(
+        "{}.{}.{}",
+        vk::api_version_major(version),
+        vk::api_version_minor(version),
+        vk::api_version_patch(version)
+    )
+}
+
+/// Formats the renderer creates images or vertex attributes with.
+fn renderer_formats() -> Vec<vk::Format> {
+    let mut formats = vec![
+        crate::config::HDR_COLOR_FORMAT,
+        crate::config::SHADOW_MAP_FORMAT,
+        vk::Format::B8G8R8A8_SRGB,
+        vk::Format::R8G8B8A8_SRGB,
+        vk::Format::R8G8B8A8_UNORM,
+        vk::Format::R32G32_SFLOAT,
+        vk::Format::R32G32B32_SFLOAT,
+        vk::Format::R32G32B32A32_SFLOAT,
+    ];
+    for &format in depth_format_candidates(false)
+        .iter()
+        .chain(depth_format_candidates(true))
+    {
+        if !formats.contains(&format) {
+            formats.push(format);
+        }
+    }
+    formats
+}
+
+macro_rules! named_values {
+    ($source:expr, $to_value:expr, [$($field:ident),* $(,)?]) => {
+        vec![$((stringify!($field), $to_value(&$source.$field))),*]
+    };
+}
+
+fn limits(limits: &vk::PhysicalDeviceLimits) -> serde_json::Map<String, serde_json::Value> {
+    let numbers = named_values!(
+        limits,
+        |value| serde_json::json!(value),
+        [
+            max_image_dimension2_d,
+            max_image_dimension_cube,
+            max_image_array_layers,
+            max_uniform_buffer_range,
+            max_storage_buffer_range,
+            max_push_constants_size,
+            max_memory_allocation_count,
+            max_sampler_allocation_count,
+            buffer_image_granularity,
+            max_bound_descriptor_sets,
+            max_per_stage_descriptor_samplers,
+            max_per_stage_descriptor_uniform_buffers,
+            max_per_stage_descriptor_storage_buffers,
+            max_per_stage_descriptor_sampled_images,
+            max_per_stage_resources,
+            max_descriptor_set_sampled_images,
+            max_descriptor_set_uniform_buffers_dynamic,
+            max_descriptor_set_storage_buffers_dynamic,
+            max_vertex_input_attributes,
+            max_vertex_input_bindings,
+            max_compute_shared_memory_size,
+            max_compute_work_group_count,
+            max_compute_work_group_invocations,
+            max_compute_work_group_size,
+            max_sampler_anisotropy,
+            max_viewports,
+            max_viewport_dimensions,
+            min_memory_map_alignment,
+            min_uniform_buffer_offset_alignment,
+            min_storage_buffer_offset_alignment,
+            max_framebuffer_width,
+            max_framebuffer_height,
+            max_color_attachments,
+            timestamp_compute_and_graphics,
+            timestamp_period,
+            non_coherent_atom_size,
+        ]
+    );
+    let sample_counts = named_values!(
+        limits,
+        |value| serde_json::json!(format!("{:?}", value)),
+        [
+            framebuffer_color_sample_counts,
+            framebuffer_depth_sample_counts,
+            sampled_image_color_sample_counts,
+            sampled_image_depth_sample_counts,
+        ]
+    );
+
+    numbers
+        .into_iter()
+        .chain(sample_counts)
+        .map(|(name, value)| (name.to_owned(), value))
+        .collect()
+}
+
+fn feature_list(features: &vk::PhysicalDeviceFeatures) -> Vec<FeatureReport> {
+    named_values!(
+        features,
+        |&supported: &vk::Bool32| supported == vk::TRUE,
+        [
+            robust_buffer_access,
+            full_draw_index_uint32,
+            image_cube_array,
+            independent_blend,
+            geometry_shader,
+            tessellation_shader,
+            sample_rate_shading,
+            dual_src_blend,
+            logic_op,
+            multi_draw_indirect,
+            draw_indirect_first_instance,
+            depth_clamp,
+            depth_bias_clamp,
+            fill_mode_non_solid,
+            depth_bounds,
+            wide_lines,
+            large_points,
+            alpha_to_one,
+            multi_viewport,
+            sampler_anisotropy,
+            texture_compression_etc2,
+            texture_compression_astc_ldr,
+            texture_compression_bc,
+            occlusion_query_precise,
+            pipeline_statistics_query,
+            vertex_pipeline_stores_and_atomics,
+            fragment_stores_and_atomics,
+            shader_tessellation_and_geometry_point_size,
+            shader_image_gather_extended,
+            shader_storage_image_extended_formats,
+            shader_storage_image_multisample,
+            shader_storage_image_read_without_format,
+            shader_storage_image_write_without_format,
+            shader_uniform_buffer_array_dynamic_indexing,
+            shader_sampled_image_array_dynamic_indexing,
+            shader_storage_buffer_array_dynamic_indexing,
+            shader_storage_image_array_dynamic_indexing,
+            shader_clip_distance,
+            shader_cull_distance,
+            shader_float64,
+            shader_int64,
+            shader_int16,
+            shader_resource_residency,
+            shader_resource_min_lod,
+            sparse_binding,
+            sparse_residency_buffer,
+            sparse_residency_image2_d,
+            sparse_residency_image3_d,
+            sparse_residency2_samples,
+            sparse_residency4_samples,
+            sparse_residency8_samples,
+            sparse_residency16_samples,
+            sparse_residency_aliased,
+            variable_multisample_rate,
+            inherited_queries,
+        ]
+    )
+    .into_iter()
+    .map(|(name, supported)| FeatureReport { name, supported })
+    .collect()
+}
+
+impl fmt::Display for DeviceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Vulkan instance version {}", self.instance_version)?;
+        writeln!(f, "{} physical device(s)", self.devices.len())?;
+        for device in self.devices.iter() {
+            writeln!(f)?;
+            write!(f, "{}", device)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for PhysicalDeviceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "[{}] {} ({})", self.index, self.name, self.device_type)?;
+        writeln!(f, "  api version {}", self.api_version)?;
+        writeln!(
+            f,
+            "  driver version {}, vendor id {:#06x}, device id {:#06x}",
+            self.driver_version, self.vendor_id, self.device_id
+        )?;
+
+        writeln!(f, "  limits:")?;
+        for (name, value) in self.limits.iter() {
+            writeln!(f, "    {}: {}", name, value)?;
+        }
+
+        let names = |features: &[FeatureReport], supported: bool| {
+            features
+                .iter()
+                .filter(|feature| feature.supported == supported)
+                .map(|feature| feature.name)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        writeln!(f, "  features: {}", names(&self.features, true))?;
+        writeln!(f, "  missing features: {}", names(&self.features, false))?;
+        writeln!(
+            f,
+            "  renderer features: {}",
+            names(&self.renderer_features, true)
+        )?;
+        writeln!(
+            f,
+            "  missing renderer features: {}",
+            names(&self.renderer_features, false)
+        )?;
+
+        writeln!(f, "  subsystems:")?;
+        for subsystem in self.subsystems.iter() {
+            let required = if subsystem.required {
+                " (required)"
+            } else {
+                ""
+            };
+            if subsystem.enabled {
+                writeln!(f, "    {}{}: enabled", subsystem.subsystem, required)?;
+            } else {
+                writeln!(
+                    f,
+                    "    {}{}: disabled, missing {}",
+                    subsystem.subsystem,
+                    required,
+                    subsystem.missing.join(", ")
+                )?;
+            }
+        }
+
+        writeln!(f, "  extensions:")?;
+        for extension in self.extensions.iter() {
+            writeln!(f, "    {} v{}", extension.name, extension.spec_version)?;
+        }
+
+        writeln!(f, "  queue families:")?;
+        for family in self.queue_families.iter() {
+            writeln!(
+                f,
+                "    {}: {} queue(s), {}, {} timestamp bits",
+                family.index, family.queue_count, family.flags, family.timestamp_valid_bits
+            )?;
+        }
+
+        writeln!(f, "  memory heaps:")?;
+        for heap in self.memory_heaps.iter() {
+            writeln!(
+                f,
+                "    {}: {} MiB, {}",
+                heap.index,
+                heap.size / (1024 * 1024),
+                heap.flags
+            )?;
+        }
+        writeln!(f, "  memory types:")?;
+        for memory_type in self.memory_types.iter() {
+            writeln!(
+                f,
+                "    {}: heap {}, {}",
+                memory_type.index, memory_type.heap_index, memory_type.flags
+            )?;
+        }
+
+        writeln!(f, "  formats:")?;
+        for format in self.formats.iter() {
+            writeln!(f, "    {}", format.format)?;
+            writeln!(f, "      optimal tiling: {}", format.optimal_tiling)?;
+            writeln!(f, "      linear tiling: {}", format.linear_tiling)?;
+            writeln!(f, "      buffer: {}", format.buffer)?;
+        }
+        Ok(())
+    }
+}
@@ -1,18 +1,33 @@
 use penguin_app::ecs::*;
+use penguin_config::PenguinConfig;
 
+use crate::config::RendererConfig;
+use crate::math_vk_format::Vec3;
+use crate::renderer::lights::{self, LightsResource};
+use crate::renderer::post_process::PostProcessResource;
+use crate::renderer::profiling::{GpuProfilerResource, GpuStatsResource};
 use crate::renderer::resources::TexturesResource;
-use crate::renderer::vk_types::resource::DescriptorSetsResource;
+use crate::renderer::shadows::ShadowResource;
+use crate::renderer::vk_types::resource::{BindlessTexturesResource, DescriptorSetsResource};
 use crate::renderer::{render_loop, resources::{MaterialsResource, MeshesResource, RenderObjectsResource}, startup_shutdown};
 
 pub struct RendererPlugin;
 
 impl Plugin for RendererPlugin {
     fn startup(&mut self, resources: &mut Resources) -> Vec<Step> {
+        let renderer_config = RendererConfig::read_config().validated();
+        resources.insert(LightsResource::new(Vec3::from(renderer_config.ambient_color)));
+        resources.insert(GpuStatsResource::new(renderer_config.profiling.average_frames as usize));
+        resources.insert(renderer_config);
         resources.insert(MeshesResource::default());
         resources.insert(MaterialsResource::default());
         resources.insert(TexturesResource::default());
         resources.insert(RenderObjectsResource::default());
         resources.insert(DescriptorSetsResource::default());
+        resources.insert(BindlessTexturesResource::default());
+        resources.insert(PostProcessResource::default());
+        resources.insert(ShadowResource::default());
+        resources.insert(GpuProfilerResource::default());
 
         Schedule::builder()
             .add_thread_local(startup_shutdown::renderer_startup_system())
@@ -22,6 +37,7 @@ impl Plugin for RendererPlugin {
 
     fn run() -> Vec<Step> {
         Schedule::builder()
+            .add_system(lights::gather_lights_system())
             .add_thread_local(render_loop::render_system())
             .build()
             .into_vec()
@@ -1,19 +1,28 @@
-use crate::renderer::vk_types::VkContext;
+use crate::renderer::memory::AllocatedBuffer;
+use crate::renderer::sync::TimelinePoint;
+use crate::renderer::vk_types::{DescriptorAllocator, DescriptorSet, VkContext};
 use ash::vk;
 
-#[derive(Clone)]
 pub struct FrameData {
     pub command_buffer: vk::CommandBuffer,
 
-    pub render_complete_fence: vk::Fence, // render commands finished execution
+    pub render_complete: TimelinePoint, // render commands finished execution
     pub rendering_complete_semaphore: vk::Semaphore,
     pub presenting_complete_semaphore: vk::Semaphore,
 
-    pub uniform_buffer_descriptor_set: vk::DescriptorSet,
+    /// camera data, read from the uniform ring buffer through a dynamic offset
+    pub camera_descriptor_set: DescriptorSet,
+    /// object data of this frame only, so that the CPU never writes data the GPU is reading
+    pub object_buffer: AllocatedBuffer,
+    pub object_descriptor_set: DescriptorSet,
+    /// descriptor sets that only live for one frame, reset when the frame data is reused
+    pub descriptor_allocator: DescriptorAllocator,
     pub frame_index: u64,
 }
 impl FrameData {
     pub fn destroy(&mut self, context: &VkContext) {
+        self.descriptor_allocator.destroy(context);
+        self.object_buffer.destroy(context);
         unsafe {
             context
                 .device
@@ -21,9 +30,6 @@ impl FrameData {
             context
                 .device
                 .destroy_semaphore(self.rendering_complete_semaphore, None);
-            context
-                .device
-                .destroy_fence(self.render_complete_fence, None);
         }
     }
 }
@@ -50,6 +56,14 @@ impl FrameDataContainer {
         &self.frame_datas[self.frame_index]
     }
 
+    pub fn get_current_mut(&mut self) -> &mut FrameData {
+        &mut self.frame_datas[self.frame_index]
+    }
+
+    pub fn frames_in_flight(&self) -> usize {
+        self.frame_datas.len()
+    }
+
     pub fn frame_count(&self) -> usize {
         self.frame_count
     }
@@ -60,7 +74,7 @@ impl FrameDataContainer {
     }
 
     fn update_frame_index(&mut self) {
-        self.frame_index = (self.frame_index + 1) % crate::config::MAX_FRAMES_COUNT;
+        self.frame_index = (self.frame_index + 1) % self.frames_in_flight();
     }
 
     pub fn destroy(&mut self, context: &VkContext) {
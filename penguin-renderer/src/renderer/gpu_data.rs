@@ -11,6 +11,28 @@ mod uniforms {
     }
 
 
+    /// Per frame data of the fragment shaders, `UniformFrameData` in the shaders.
+    #[derive(Default, Clone, Copy)]
+    #[repr(C)]
+    pub struct GPUFrameData {
+        /// rgb, added to every lit surface
+        pub ambient_color: Vec4,
+        /// number of lights in the light buffer
+        pub light_count: u32,
+        pub _padding: [u32; 3],
+    }
+
+    /// Cascaded shadow map of the directional light.
+    #[derive(Default, Clone, Copy)]
+    #[repr(C)]
+    pub struct GPUShadowData {
+        /// world space to clip space of each cascade's tile in the shadow map
+        pub cascade_view_proj: [Mat4; crate::config::MAX_SHADOW_CASCADES],
+        pub cascade_count: u32,
+        pub pcf_radius: u32,
+        pub _padding: [u32; 2],
+    }
+
     #[derive(Default, Clone, Copy)]
     #[repr(C)]
     pub struct SomeGPUData {
@@ -20,13 +42,62 @@ mod uniforms {
 
 pub use buffers::*;
 mod buffers {
-    use crate::math_vk_format::Mat4;
+    use crate::math_vk_format::{Mat4, Vec3};
 
     #[derive(Default, Clone, Copy)]
     #[repr(C)]
     pub struct GPUObjectData {
         pub transform: Mat4,
     }
+
+    pub const GPU_LIGHT_DIRECTIONAL: u32 = 0;
+    pub const GPU_LIGHT_POINT: u32 = 1;
+    pub const GPU_LIGHT_SPOT: u32 = 2;
+
+    /// A light in the light storage buffer. The vec3s line up with the std430 layout.
+    #[derive(Default, Clone, Copy)]
+    #[repr(C)]
+    pub struct GPULight {
+        pub position: Vec3,
+        /// distance at which point and spot lights fade out
+        pub range: f32,
+        /// direction the light shines in, for directional and spot lights
+        pub direction: Vec3,
+        /// one of the GPU_LIGHT_ constants
+        pub kind: u32,
+        pub color: Vec3,
+        pub intensity: f32,
+        /// cosines of the spot light's inner and outer cone angles
+        pub spot_cos_inner: f32,
+        pub spot_cos_outer: f32,
+        /// 1 if the light's shadows are in the shadow map
+        pub casts_shadows: u32,
+        pub _padding: u32,
+    }
 }
 
+pub use push_constants::*;
+/// Data pushed to the shaders as push constants
+mod push_constants {
+    use crate::math_vk_format::{Vec3, Vec4};
+    use crate::renderer::render_objects::PBR_MAP_COUNT;
 
+    /// Parameters of a pbr material, `MaterialParams` in the lit shaders.
+    #[derive(Default, Clone, Copy, PartialEq)]
+    #[repr(C)]
+    pub struct GPUMaterialData {
+        /// linear rgba
+        pub base_color: Vec4,
+        /// linear rgb
+        pub emissive: Vec3,
+        pub metallic: f32,
+        pub roughness: f32,
+        pub occlusion_strength: f32,
+        pub normal_scale: f32,
+        /// indices of the maps in the bindless texture array, in `PbrMap` order
+        pub texture_indices: [u32; PBR_MAP_COUNT],
+        /// texels with a lower alpha are discarded, 0 keeps all of them
+        pub alpha_cutoff: f32,
+        pub _padding: [u32; 3],
+    }
+}
@@ -0,0 +1,177 @@
+use crate::math_vk_format::Vec3;
+use crate::renderer::gpu_data::{GPULight, GPU_LIGHT_DIRECTIONAL, GPU_LIGHT_POINT, GPU_LIGHT_SPOT};
+use crate::renderer::shadows::ShadowResource;
+use penguin_app::ecs::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LightKind {
+    /// Infinitely far away, like the sun.
+    Directional { direction: Vec3 },
+    Point {
+        position: Vec3,
+        /// Distance at which the light has faded out completely.
+        range: f32,
+    },
+    Spot {
+        position: Vec3,
+        direction: Vec3,
+        range: f32,
+        /// Angle from the direction, in radians, up to which the light has full intensity.
+        inner_angle: f32,
+        /// Angle from the direction, in radians, at which the light has faded out.
+        outer_angle: f32,
+    },
+}
+
+/// A light source. Every entity with this component lights the scene.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Light {
+    pub kind: LightKind,
+    /// Linear rgb.
+    pub color: Vec3,
+    pub intensity: f32,
+    /// Only the first directional light with this set casts shadows.
+    pub casts_shadows: bool,
+}
+
+impl Light {
+    pub fn directional(direction: Vec3, color: Vec3, intensity: f32) -> Self {
+        Self {
+            kind: LightKind::Directional { direction },
+            color,
+            intensity,
+            casts_shadows: true,
+        }
+    }
+
+    pub fn point(position: Vec3, range: f32, color: Vec3, intensity: f32) -> Self {
+        Self {
+            kind: LightKind::Point { position, range },
+            color,
+            intensity,
+            casts_shadows: false,
+        }
+    }
+
+    pub fn spot(
+        position: Vec3,
+        direction: Vec3,
+        range: f32,
+        (inner_angle, outer_angle): (f32, f32),
+        color: Vec3,
+        intensity: f32,
+    ) -> Self {
+        Self {
+            kind: LightKind::Spot {
+                position,
+                direction,
+                range,
+                inner_angle,
+                outer_angle,
+            },
+            color,
+            intensity,
+            casts_shadows: false,
+        }
+    }
+
+    fn gpu_light(&self) -> GPULight {
+        let mut gpu_light = GPULight {
+            color: self.color,
+            intensity: self.intensity,
+            ..Default::default()
+        };
+
+        match self.kind {
+            LightKind::Directional { direction } => {
+                gpu_light.kind = GPU_LIGHT_DIRECTIONAL;
+                gpu_light.direction = direction.normalize();
+            }
+            LightKind::Point { position, range } => {
+                gpu_light.kind = GPU_LIGHT_POINT;
+                gpu_light.position = position;
+                gpu_light.range = range;
+            }
+            LightKind::Spot {
+                position,
+                direction,
+                range,
+                inner_angle,
+                outer_angle,
+            } => {
+                gpu_light.kind = GPU_LIGHT_SPOT;
+                gpu_light.position = position;
+                gpu_light.direction = direction.normalize();
+                gpu_light.range = range;
+                gpu_light.spot_cos_inner = inner_angle.cos();
+                gpu_light.spot_cos_outer = outer_angle.cos();
+            }
+        }
+        gpu_light
+    }
+}
+
+/// The lights of the current frame, in the layout of the light buffer.
+pub struct LightsResource {
+    /// Linear rgb light that reaches every surface.
+    pub ambient_color: Vec3,
+    lights: Vec<GPULight>,
+    warned_about_overflow: bool,
+}
+
+impl LightsResource {
+    pub fn new(ambient_color: Vec3) -> Self {
+        Self {
+            ambient_color,
+            lights: Vec::new(),
+            warned_about_overflow: false,
+        }
+    }
+
+    pub fn lights(&self) -> &[GPULight] {
+        &self.lights
+    }
+
+    /// The lights padded to the size of the light buffer.
+    pub fn light_buffer(&self) -> [GPULight; crate::config::MAX_LIGHTS] {
+        let mut buffer = [GPULight::default(); crate::config::MAX_LIGHTS];
+        buffer[..self.lights.len()].copy_from_slice(&self.lights);
+        buffer
+    }
+}
+
+/// Collects the `Light` components into the light buffer, and points the shadow map at the
+/// shadow casting directional light.
+#[system]
+pub fn gather_lights(
+    world: &SubWorld,
+    query: &mut Query<&Light>,
+    #[resource] lights: &mut LightsResource,
+    #[resource] shadows: &mut ShadowResource,
+) {
+    lights.lights.clear();
+
+    let mut shadow_caster_found = false;
+    for light in query.iter(world) {
+        if lights.lights.len() == crate::config::MAX_LIGHTS {
+            if !lights.warned_about_overflow {
+                log::warn!(
+                    "More than {} lights, the rest are ignored.",
+                    crate::config::MAX_LIGHTS
+                );
+                lights.warned_about_overflow = true;
+            }
+            break;
+        }
+
+        let mut gpu_light = light.gpu_light();
+        if let LightKind::Directional { direction } = light.kind {
+            if light.casts_shadows && !shadow_caster_found {
+                shadows.light_direction = direction.normalize();
+                gpu_light.casts_shadows = 1;
+                shadow_caster_found = true;
+            }
+        }
+        lights.lights.push(gpu_light);
+    }
+}
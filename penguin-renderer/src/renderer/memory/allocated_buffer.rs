@@ -1,5 +1,6 @@
 use crate::renderer::memory::device_memory::{DeviceMemory, DeviceMemoryCreateInfoFromBuffer};
 use crate::renderer::memory::{DeviceMemoryWriteInfo, MemoryUsage};
+use crate::renderer::sync::{buffer_barrier, PipelineBarrierBuilder, ResourceUsage};
 use crate::renderer::vk_types::VkContext;
 use ash::vk;
 
@@ -8,6 +9,8 @@ pub struct AllocatedBuffer {
     pub handle: vk::Buffer,
     memory: DeviceMemory,
     size: vk::DeviceSize,
+    /// how the buffer was last accessed, kept up to date by `transition_to`
+    usage: ResourceUsage,
 }
 impl AllocatedBuffer {
     pub fn destroy(&mut self, context: &VkContext) {
@@ -28,6 +31,49 @@ impl AllocatedBuffer {
     }
 }
 
+impl AllocatedBuffer {
+    /// Maps the whole buffer. The pointer stays valid until the buffer is unmapped, which makes
+    /// it possible to keep buffers that are written every frame persistently mapped.
+    pub fn map_memory(&self, context: &VkContext) -> *mut core::ffi::c_void {
+        context.map_memory(
+            self.memory.handle,
+            0,
+            vk::WHOLE_SIZE,
+            self.memory.map_flags,
+        )
+    }
+
+    pub fn unmap_memory(&self, context: &VkContext) {
+        context.unmap_memory(self.memory.handle);
+    }
+}
+
+impl AllocatedBuffer {
+    pub fn usage(&self) -> ResourceUsage {
+        self.usage
+    }
+
+    /// Records the barrier needed before accessing the buffer as `usage`, based on the last
+    /// recorded access. Nothing is recorded if no barrier is needed.
+    pub fn transition_to(
+        &mut self,
+        context: &VkContext,
+        command_buffer: vk::CommandBuffer,
+        usage: ResourceUsage,
+    ) {
+        if let Some((src_stage_mask, dst_stage_mask, barrier)) =
+            buffer_barrier(self.handle, (self.usage, usage))
+        {
+            PipelineBarrierBuilder::builder()
+                .src_stage_mask(src_stage_mask)
+                .dst_stage_mask(dst_stage_mask)
+                .buffer_memory_barriers(&[barrier])
+                .build_exec(context, command_buffer);
+        }
+        self.usage = usage;
+    }
+}
+
 impl AllocatedBuffer {
     /// write to allocated gpu memory
     pub fn write_memory<T: Copy>(
@@ -113,6 +159,11 @@ impl AllocatedBuffer {
             handle: buffer,
             memory: device_memory,
             size,
+            usage: if create_info.initial_data.is_empty() {
+                ResourceUsage::Undefined
+            } else {
+                ResourceUsage::HostWrite
+            },
         }
     }
 }
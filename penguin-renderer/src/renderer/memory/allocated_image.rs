@@ -1,14 +1,18 @@
 use crate::renderer::memory;
 use crate::renderer::memory::device_memory::DeviceMemory;
+use crate::renderer::memory::util::aspect_mask_from_format;
 use crate::renderer::memory::{
     MemoryUsage, UploadContext,
 };
+use crate::renderer::sync::{ImageStateTracker, PipelineBarrierBuilder, ResourceUsage, SubresourceState};
 use crate::renderer::vk_types::VkContext;
 use ash::vk;
 
 pub struct AllocatedImage {
     pub handle: vk::Image,
     memory: DeviceMemory,
+    /// layout and last access of every subresource, kept up to date by `transition_to`
+    pub state: ImageStateTracker,
 }
 
 pub enum ImageExtent {
@@ -42,12 +46,22 @@ impl AllocatedImage {
 
         log::info!("image memory requirements: {:?}", image_memory_requirements);
 
-        let image_memory_index = memory::util::find_memory_type_index(
-            &image_memory_requirements,
-            &context.pd_mem_properties(),
-            create_info.memory_usage.memory_property_flags(),
-        )
-        .expect("Couldn't find suitable memory index for image");
+        let memory_properties = context.pd_mem_properties();
+        let find_memory_type_index = |memory_usage: MemoryUsage| {
+            memory::util::find_memory_type_index(
+                &image_memory_requirements,
+                &memory_properties,
+                memory_usage.memory_property_flags(),
+            )
+        };
+
+        let image_memory_index = find_memory_type_index(create_info.memory_usage)
+            .or_else(|| match create_info.memory_usage {
+                // lazily allocated memory is usually only available on tiled (mobile) GPUs
+                MemoryUsage::GpuOnlyLazy => find_memory_type_index(MemoryUsage::GpuOnly),
+                _ => None,
+            })
+            .expect("Couldn't find suitable memory index for image");
 
         let image_allocate_info = vk::MemoryAllocateInfo::builder()
             .allocation_size(image_memory_requirements.size)
@@ -58,6 +72,8 @@ impl AllocatedImage {
 
         context.bind_image_memory(image, memory);
 
+        let image_create_info = &create_info.image_create_info;
+
         Self {
             handle: image,
             memory: DeviceMemory {
@@ -65,6 +81,52 @@ impl AllocatedImage {
                 size: image_memory_requirements.size,
                 map_flags: vk::MemoryMapFlags::empty(),
             },
+            state: ImageStateTracker::new(
+                aspect_mask_from_format(image_create_info.format),
+                image_create_info.mip_levels,
+                image_create_info.array_layers,
+            ),
         }
     }
 }
+
+impl AllocatedImage {
+    /// Transitions the whole image, see `transition_range_to`.
+    pub fn transition_to(
+        &mut self,
+        context: &VkContext,
+        command_buffer: vk::CommandBuffer,
+        layout: vk::ImageLayout,
+        usage: ResourceUsage,
+    ) {
+        let range = self.state.full_range();
+        self.transition_range_to(context, command_buffer, range, layout, usage);
+    }
+
+    /// Records the barrier needed to access the subresources in `range` in `layout` as `usage`,
+    /// based on the tracked state. Nothing is recorded if no barrier is needed.
+    pub fn transition_range_to(
+        &mut self,
+        context: &VkContext,
+        command_buffer: vk::CommandBuffer,
+        range: vk::ImageSubresourceRange,
+        layout: vk::ImageLayout,
+        usage: ResourceUsage,
+    ) {
+        if let Some((src_stage_mask, dst_stage_mask, barriers)) =
+            self.state
+                .transition(self.handle, range, SubresourceState { layout, usage })
+        {
+            PipelineBarrierBuilder::builder()
+                .src_stage_mask(src_stage_mask)
+                .dst_stage_mask(dst_stage_mask)
+                .image_memory_barriers(&barriers)
+                .build_exec(context, command_buffer);
+        }
+    }
+
+    /// Panics in debug builds if the tracked layout of `range` isn't `layout`.
+    pub fn expect_layout(&self, range: vk::ImageSubresourceRange, layout: vk::ImageLayout) {
+        self.state.expect_layout(range, layout);
+    }
+}
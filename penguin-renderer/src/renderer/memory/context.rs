@@ -47,6 +47,12 @@ impl VkContext {
             .limits
             .min_uniform_buffer_offset_alignment
     }
+
+    pub fn min_storage_buffer_offset_alignment(&self) -> vk::DeviceSize {
+        self.pd_device_properties()
+            .limits
+            .min_storage_buffer_offset_alignment
+    }
 }
 
 impl VkContext {
@@ -17,3 +17,6 @@ pub use memory_usage::*;
 
 mod upload_context;
 pub use upload_context::*;
+
+mod ring_buffer;
+pub use ring_buffer::*;
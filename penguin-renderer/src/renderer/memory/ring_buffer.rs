@@ -0,0 +1,267 @@
+use crate::renderer::memory::util::align_up;
+use crate::renderer::memory::{AllocatedBuffer, AllocatedBufferCreateInfo, MemoryUsage};
+use crate::renderer::vk_types::VkContext;
+use ash::vk;
+use std::collections::VecDeque;
+
+/// Offset bookkeeping for a ring of memory that is written linearly every frame.
+///
+///     Allocations of a frame stay alive until the frame is released, which should only happen
+///     once the GPU is done with it (after waiting for the frame's fence). Allocations that don't
+///     fit before the end of the ring wrap around to offset 0, the skipped bytes count as used
+///     until the frame that skipped them is released.
+#[derive(Debug)]
+pub struct RingAllocator {
+    capacity: u64,
+    /// offset where the next allocation starts searching from
+    head: u64,
+    /// bytes in use by frames that haven't been released, padding included
+    used: u64,
+    /// bytes used by the frame currently being recorded
+    current_frame_bytes: u64,
+    /// bytes used by every finished but not yet released frame, oldest first
+    pending_frames: VecDeque<u64>,
+}
+
+impl RingAllocator {
+    pub fn new(capacity: u64) -> Self {
+        Self {
+            capacity,
+            head: 0,
+            used: 0,
+            current_frame_bytes: 0,
+            pending_frames: VecDeque::new(),
+        }
+    }
+
+    pub fn capacity(&self) -> u64 {
+        self.capacity
+    }
+
+    pub fn used(&self) -> u64 {
+        self.used
+    }
+
+    /// Number of finished frames that haven't been released yet.
+    pub fn pending_frames(&self) -> usize {
+        self.pending_frames.len()
+    }
+
+    /// Returns the offset of `size` free bytes aligned to `alignment`, or None if the ring is
+    /// full.
+    pub fn allocate(&mut self, size: u64, alignment: u64) -> Option<u64> {
+        if size > self.capacity {
+            return None;
+        }
+
+        let aligned = align_up(self.head, alignment);
+        let (offset, consumed) = if aligned + size <= self.capacity {
+            (aligned, aligned - self.head + size)
+        } else {
+            // wrap around, the rest of the ring is skipped
+            (0, self.capacity - self.head + size)
+        };
+
+        if self.used + consumed > self.capacity {
+            return None;
+        }
+
+        self.head = offset + size;
+        self.used += consumed;
+        self.current_frame_bytes += consumed;
+
+        Some(offset)
+    }
+
+    /// Marks the end of the allocations belonging to the current frame.
+    pub fn finish_frame(&mut self) {
+        self.pending_frames.push_back(self.current_frame_bytes);
+        self.current_frame_bytes = 0;
+    }
+
+    /// Frees the memory of the oldest finished frame.
+    pub fn release_frame(&mut self) {
+        if let Some(bytes) = self.pending_frames.pop_front() {
+            self.used -= bytes;
+        }
+    }
+}
+
+/// A persistently mapped buffer that transient uniform and storage data gets pushed into every
+/// frame, bound through dynamic descriptor offsets.
+///
+///     Call `begin_frame` after waiting for the frame's fence and `end_frame` once all data of
+///     the frame has been pushed. Data of the last `frames_in_flight` frames is never overwritten.
+pub struct UniformRingBuffer {
+    pub buffer: AllocatedBuffer,
+    mapped_ptr: *mut u8,
+    allocator: RingAllocator,
+    frames_in_flight: usize,
+    min_uniform_alignment: u64,
+    min_storage_alignment: u64,
+}
+
+// The mapped pointer is only written through `&mut self`.
+unsafe impl Send for UniformRingBuffer {}
+unsafe impl Sync for UniformRingBuffer {}
+
+impl UniformRingBuffer {
+    pub fn new(context: &VkContext, capacity: u64, frames_in_flight: usize) -> Self {
+        let buffer = AllocatedBuffer::create_buffer(
+            context,
+            AllocatedBufferCreateInfo::<u8> {
+                initial_data: &[],
+                buffer_size: capacity,
+                buffer_usage: vk::BufferUsageFlags::UNIFORM_BUFFER
+                    | vk::BufferUsageFlags::STORAGE_BUFFER,
+                memory_usage: MemoryUsage::GpuMemCpuWritable,
+                sharing_mode: vk::SharingMode::EXCLUSIVE,
+                memory_map_flags: vk::MemoryMapFlags::empty(),
+            },
+        );
+
+        let mapped_ptr = buffer.map_memory(context) as *mut u8;
+
+        Self {
+            buffer,
+            mapped_ptr,
+            allocator: RingAllocator::new(capacity),
+            frames_in_flight,
+            min_uniform_alignment: context.min_uniform_buffer_offset_alignment(),
+            min_storage_alignment: context.min_storage_buffer_offset_alignment(),
+        }
+    }
+
+    pub fn destroy(&mut self, context: &VkContext) {
+        self.buffer.unmap_memory(context);
+        self.buffer.destroy(context);
+    }
+
+    /// Releases the data of the frame that previously used this frame's fence.
+    pub fn begin_frame(&mut self) {
+        while self.allocator.pending_frames() >= self.frames_in_flight {
+            self.allocator.release_frame();
+        }
+    }
+
+    pub fn end_frame(&mut self) {
+        self.allocator.finish_frame();
+    }
+
+    /// Copies `data` into the ring and returns the dynamic offset to bind it with.
+    pub fn push_uniform<T: Copy>(&mut self, data: &T) -> u32 {
+        self.push(std::slice::from_ref(data), self.min_uniform_alignment)
+    }
+
+    /// Copies `data` into the ring and returns the dynamic offset to bind it with.
+    pub fn push_storage<T: Copy>(&mut self, data: &[T]) -> u32 {
+        self.push(data, self.min_storage_alignment)
+    }
+
+    /// Buffer info for a dynamic descriptor reading one `T` at a time.
+    pub fn descriptor_buffer_info<T>(&self) -> vk::DescriptorBufferInfo {
+        vk::DescriptorBufferInfo::builder()
+            .buffer(self.buffer.handle)
+            .offset(0)
+            .range(std::mem::size_of::<T>() as _)
+            .build()
+    }
+
+    fn push<T: Copy>(&mut self, data: &[T], min_alignment: u64) -> u32 {
+        let size = std::mem::size_of_val(data) as u64;
+        let alignment = min_alignment.max(std::mem::align_of::<T>() as u64);
+
+        let offset = self
+            .allocator
+            .allocate(size, alignment)
+            .expect("uniform ring buffer is full");
+
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                data.as_ptr() as *const u8,
+                self.mapped_ptr.add(offset as usize),
+                size as usize,
+            );
+        }
+
+        offset as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allocations_are_aligned() {
+        let mut ring = RingAllocator::new(1024);
+
+        assert_eq!(ring.allocate(80, 256), Some(0));
+        assert_eq!(ring.allocate(80, 256), Some(256));
+        assert_eq!(ring.allocate(4, 4), Some(336));
+        assert_eq!(ring.used(), 340);
+    }
+
+    #[test]
+    fn full_ring_refuses_allocation() {
+        let mut ring = RingAllocator::new(512);
+
+        assert_eq!(ring.allocate(256, 256), Some(0));
+        assert_eq!(ring.allocate(256, 256), Some(256));
+        assert_eq!(ring.allocate(1, 1), None);
+        assert_eq!(ring.allocate(1024, 1), None);
+    }
+
+    #[test]
+    fn releasing_a_frame_frees_its_memory() {
+        let mut ring = RingAllocator::new(512);
+
+        ring.allocate(256, 256).unwrap();
+        ring.finish_frame();
+        ring.allocate(256, 256).unwrap();
+        ring.finish_frame();
+        assert_eq!(ring.allocate(256, 256), None);
+
+        ring.release_frame();
+        assert_eq!(ring.used(), 256);
+        assert_eq!(ring.allocate(256, 256), Some(0));
+    }
+
+    #[test]
+    fn wrap_around_never_overwrites_pending_frames() {
+        let mut ring = RingAllocator::new(1024);
+
+        // frame 0
+        assert_eq!(ring.allocate(300, 256), Some(0));
+        ring.finish_frame();
+        // frame 1
+        assert_eq!(ring.allocate(300, 256), Some(512));
+        ring.finish_frame();
+
+        // frame 0 done, the end of the ring is too small so the allocation wraps around
+        ring.release_frame();
+        assert_eq!(ring.allocate(300, 256), Some(0));
+        // frame 1 and the skipped end of the ring are still in use
+        assert_eq!(ring.allocate(1, 1), None);
+        ring.finish_frame();
+
+        // frame 1 done, only the region it used is free
+        ring.release_frame();
+        assert_eq!(ring.used(), 512);
+        assert_eq!(ring.allocate(512, 1), Some(300));
+        assert_eq!(ring.allocate(1, 1), None);
+    }
+
+    #[test]
+    fn releasing_all_frames_empties_the_ring() {
+        let mut ring = RingAllocator::new(256);
+
+        for _ in 0..10 {
+            ring.allocate(100, 64).unwrap();
+            ring.allocate(20, 64).unwrap();
+            ring.finish_frame();
+            ring.release_frame();
+            assert_eq!(ring.used(), 0);
+        }
+    }
+}
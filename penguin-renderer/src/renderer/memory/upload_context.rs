@@ -1,24 +1,22 @@
+use crate::renderer::sync::{GraphicsSubmission, TimelinePoint};
 use crate::renderer::vk_types::VkContext;
 use ash::vk;
 
 // Context for memory transfers
 #[derive(Copy, Clone)]
 pub struct UploadContext {
-    upload_fence: vk::Fence,
     command_pool: vk::CommandPool,
 }
 
 impl UploadContext {
     pub fn destroy(&mut self, context: &VkContext) {
         unsafe {
-            context.device.destroy_fence(self.upload_fence, None);
             context.device.destroy_command_pool(self.command_pool, None);
         };
     }
 
     pub fn init(context: &VkContext) -> Self {
         Self {
-            upload_fence: context.create_fence(vk::FenceCreateFlags::empty()),
             command_pool: context.alloc_command_pool(
                 // todo: Check for and use a transfer queue index
                 context.physical_device.graphics_queue_index,
@@ -31,7 +29,23 @@ impl UploadContext {
     /// render loop sync. This can also be used to submit commands from a thread separate from
     /// the render loop thread.
     #[allow(unused)]
-    pub fn immediate_submit<F: FnOnce(vk::CommandBuffer)>(&self, context: &VkContext, f: F) {
+    pub fn immediate_submit<F: FnOnce(vk::CommandBuffer)>(
+        &self,
+        context: &VkContext,
+        f: F,
+    ) -> TimelinePoint {
+        self.immediate_submit_after(context, &[], f)
+    }
+
+    /// Like `immediate_submit`, but the commands only start once the GPU reached `wait_for`,
+    /// e.g. a frame's `render_complete` before overwriting something that frame reads.
+    ///     Returns the point of the upload, which is already complete when this returns.
+    pub fn immediate_submit_after<F: FnOnce(vk::CommandBuffer)>(
+        &self,
+        context: &VkContext,
+        wait_for: &[TimelinePoint],
+        f: F,
+    ) -> TimelinePoint {
         let command_buffer = context.allocate_command_buffers(self.command_pool, 1);
         let command_buffer = command_buffer[0];
 
@@ -41,14 +55,16 @@ impl UploadContext {
 
         context.end_command_buffer(command_buffer);
 
-        let command_buffers = [command_buffer];
-        let submit_info = vk::SubmitInfo::builder().command_buffers(&command_buffers).build();
-
-        context.submit_to_graphics_queue(submit_info, self.upload_fence);
+        let upload_complete = context.submit_graphics(GraphicsSubmission {
+            command_buffers: &[command_buffer],
+            wait_for,
+            ..Default::default()
+        });
 
-        // TODO: Connect it to the render semaphores?
-        context.wait_for_fence(self.upload_fence, std::time::Duration::from_secs(10));
+        // the command pool is reset below, so the commands have to be finished
+        context.wait_for_timeline(upload_complete, std::time::Duration::from_secs(10));
 
         context.reset_command_pool(self.command_pool, vk::CommandPoolResetFlags::empty());
+        upload_complete
     }
 }
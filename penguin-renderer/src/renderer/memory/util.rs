@@ -1,23 +1,27 @@
 // https://stackoverflow.com/questions/28127165/how-to-convert-struct-to-u8
 
-pub fn packed_range_from_min_align_manual(mem_range: u64, min_align: u64) -> u64 {
-    let mut packed_range = 0;
-    while packed_range < mem_range && packed_range < min_align {
-        packed_range += min_align;
+/// Rounds `value` up to the closest multiple of `alignment`. An alignment of 0 leaves the value
+/// untouched.
+pub const fn align_up(value: u64, alignment: u64) -> u64 {
+    if alignment == 0 {
+        return value;
+    }
+    match value % alignment {
+        0 => value,
+        remainder => value + (alignment - remainder),
     }
-    packed_range
 }
 
+/// The size of `mem_range` padded so that consecutive ranges all start on a `min_align` boundary.
+pub fn packed_range_from_min_align_manual(mem_range: u64, min_align: u64) -> u64 {
+    align_up(mem_range, min_align)
+}
+
+/// The size of `T` padded so that consecutive values all start on a `min_align` boundary.
 pub fn packed_range_from_min_align<T>(min_align: u64) -> u64 {
-    let mem_range = std::mem::size_of::<T>() as u64;
-    let mut packed_range = 0;
-    while packed_range < mem_range && packed_range < min_align {
-        packed_range += min_align;
-    }
-    packed_range
+    packed_range_from_min_align_manual(std::mem::size_of::<T>() as u64, min_align)
 }
 
-#[allow(unused)]
 pub unsafe fn as_u8_slice<T: Sized>(p: &T) -> &[u8] {
     std::slice::from_raw_parts((p as *const T) as *const u8, std::mem::size_of::<T>())
 }
@@ -43,3 +47,57 @@ pub(super) fn find_memory_type_index(
         })
         .map(|(index, _memory_type)| index as _)
 }
+
+/// The aspects of an image with the given format.
+pub fn aspect_mask_from_format(format: vk::Format) -> vk::ImageAspectFlags {
+    match format {
+        vk::Format::D16_UNORM | vk::Format::D32_SFLOAT | vk::Format::X8_D24_UNORM_PACK32 => {
+            vk::ImageAspectFlags::DEPTH
+        }
+        vk::Format::D16_UNORM_S8_UINT
+        | vk::Format::D24_UNORM_S8_UINT
+        | vk::Format::D32_SFLOAT_S8_UINT => {
+            vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL
+        }
+        vk::Format::S8_UINT => vk::ImageAspectFlags::STENCIL,
+        _ => vk::ImageAspectFlags::COLOR,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn align_up_rounds_to_next_multiple() {
+        assert_eq!(align_up(0, 256), 0);
+        assert_eq!(align_up(1, 256), 256);
+        assert_eq!(align_up(255, 256), 256);
+        assert_eq!(align_up(256, 256), 256);
+        assert_eq!(align_up(257, 256), 512);
+        assert_eq!(align_up(100, 48), 144);
+    }
+
+    #[test]
+    fn align_up_zero_alignment_is_identity() {
+        assert_eq!(align_up(37, 0), 37);
+    }
+
+    #[test]
+    fn packed_range_of_struct_larger_than_alignment() {
+        #[allow(unused)]
+        struct Large([u8; 300]);
+
+        assert_eq!(packed_range_from_min_align::<Large>(256), 512);
+        assert_eq!(packed_range_from_min_align::<Large>(64), 320);
+    }
+
+    #[test]
+    fn packed_range_of_struct_smaller_than_alignment() {
+        #[allow(unused)]
+        struct Small([u8; 80]);
+
+        assert_eq!(packed_range_from_min_align::<Small>(256), 256);
+        assert_eq!(packed_range_from_min_align_manual(256, 256), 256);
+    }
+}
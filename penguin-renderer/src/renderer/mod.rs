@@ -1,4 +1,5 @@
-mod debug;
+pub mod debug;
+pub mod device_report;
 mod frame_data;
 mod gpu_data;
 mod render_loop;
@@ -9,7 +10,12 @@ mod sync;
 mod ecs_plugin;
 pub use ecs_plugin::*;
 
+pub mod lights;
 pub mod memory;
+pub mod post_process;
+pub mod profiling;
+pub mod render_graph;
 pub mod render_objects;
+pub mod shadows;
 mod shader;
 pub mod vk_types;
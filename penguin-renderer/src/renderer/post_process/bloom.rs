@@ -0,0 +1,212 @@
+use crate::renderer::post_process::{
+    FullscreenPipeline, FullscreenPipelineCreateInfo, PostProcessEffect,
+};
+use crate::renderer::render_graph::{ImageDesc, ImageHandle, RenderGraph};
+use crate::renderer::vk_types::VkContext;
+use ash::vk;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct BloomSettings {
+    /// Brightness above which colors start to bloom.
+    pub threshold: f32,
+    /// Width of the soft transition below the threshold, relative to it.
+    pub knee: f32,
+    /// How much of the blurred image gets added to the scene color.
+    pub intensity: f32,
+    /// Number of times the image gets halved. Can't be raised above its startup value.
+    pub mip_count: u32,
+}
+impl Default for BloomSettings {
+    fn default() -> Self {
+        Self {
+            threshold: 1.0,
+            knee: 0.5,
+            intensity: 0.05,
+            mip_count: 5,
+        }
+    }
+}
+
+#[repr(C)]
+struct DownsamplePushConstants {
+    threshold: f32,
+    knee: f32,
+    /// only the first downsample filters out the colors that don't bloom
+    apply_threshold: u32,
+}
+
+#[repr(C)]
+struct CompositePushConstants {
+    intensity: f32,
+}
+
+struct BloomPipelines {
+    downsample: FullscreenPipeline,
+    upsample: FullscreenPipeline,
+    composite: FullscreenPipeline,
+}
+
+/// Adds a blurred version of the bright parts of the scene on top of it.
+///
+///     The bright colors are downsampled into a chain of images of half the size each, then
+///     blurred back up with a tent filter, adding every level to the one above it.
+#[derive(Default)]
+pub struct Bloom {
+    pub enabled: bool,
+    pub settings: BloomSettings,
+    max_mip_count: u32,
+    pipelines: Option<BloomPipelines>,
+}
+impl Bloom {
+    pub fn init(
+        &mut self,
+        context: &VkContext,
+        frames_in_flight: usize,
+        settings: BloomSettings,
+    ) {
+        self.settings = settings;
+        self.max_mip_count = settings.mip_count.clamp(1, crate::config::MAX_BLOOM_MIP_COUNT);
+
+        let format = crate::config::HDR_COLOR_FORMAT;
+        let pipeline = |fragment_shader: &str,
+                        input_count: u32,
+                        push_constants_size: usize,
+                        max_draws_per_frame: u32| {
+            FullscreenPipeline::new(
+                context,
+                FullscreenPipelineCreateInfo {
+                    fragment_shader,
+                    output_format: format,
+                    input_count,
+                    push_constants_size: push_constants_size as _,
+                    frames_in_flight,
+                    max_draws_per_frame,
+                },
+            )
+        };
+
+        self.pipelines = Some(BloomPipelines {
+            downsample: pipeline(
+                "bloom_downsample.frag",
+                1,
+                std::mem::size_of::<DownsamplePushConstants>(),
+                self.max_mip_count,
+            ),
+            upsample: pipeline("bloom_upsample.frag", 2, 0, self.max_mip_count),
+            composite: pipeline(
+                "bloom_composite.frag",
+                2,
+                std::mem::size_of::<CompositePushConstants>(),
+                1,
+            ),
+        });
+    }
+}
+
+impl PostProcessEffect for Bloom {
+    fn name(&self) -> &'static str {
+        "bloom"
+    }
+
+    fn is_hdr(&self) -> bool {
+        true
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn begin_frame(&mut self, frame_index: usize) {
+        if let Some(pipelines) = self.pipelines.as_mut() {
+            pipelines.downsample.begin_frame(frame_index);
+            pipelines.upsample.begin_frame(frame_index);
+            pipelines.composite.begin_frame(frame_index);
+        }
+    }
+
+    fn add_passes<'a>(
+        &'a self,
+        graph: &mut RenderGraph<'a>,
+        input: ImageHandle,
+        output: ImageHandle,
+        extent: vk::Extent2D,
+    ) {
+        let pipelines = self.pipelines.as_ref().expect("bloom isn't initialized");
+
+        // stop before the images get smaller than a pixel
+        let max_fitting_mip_count = 32 - extent.width.max(extent.height).leading_zeros() - 1;
+        let mip_count = self
+            .settings
+            .mip_count
+            .min(self.max_mip_count)
+            .min(max_fitting_mip_count)
+            .max(1);
+
+        let mip_desc = |mip_level: u32| {
+            ImageDesc::new_2d(
+                crate::config::HDR_COLOR_FORMAT,
+                vk::Extent2D {
+                    width: (extent.width >> (mip_level + 1)).max(1),
+                    height: (extent.height >> (mip_level + 1)).max(1),
+                },
+            )
+        };
+
+        // downsample, mip 0 has half the size of the input
+        let mut downsampled = Vec::with_capacity(mip_count as usize);
+        let mut source = input;
+        for mip_level in 0..mip_count {
+            let target = graph.create_image("bloom downsample", mip_desc(mip_level));
+            pipelines.downsample.add_pass(
+                graph,
+                "bloom downsample",
+                &[source],
+                &[],
+                target,
+                move || DownsamplePushConstants {
+                    threshold: self.settings.threshold,
+                    knee: self.settings.knee,
+                    apply_threshold: (mip_level == 0) as u32,
+                },
+            );
+            downsampled.push(target);
+            source = target;
+        }
+
+        // upsample, adding each level to the blurred levels below it
+        let mut blurred = source;
+        for mip_level in (0..mip_count - 1).rev() {
+            let target = graph.create_image("bloom upsample", mip_desc(mip_level));
+            pipelines.upsample.add_pass(
+                graph,
+                "bloom upsample",
+                &[downsampled[mip_level as usize], blurred],
+                &[],
+                target,
+                || (),
+            );
+            blurred = target;
+        }
+
+        pipelines.composite.add_pass(
+            graph,
+            "bloom composite",
+            &[input, blurred],
+            &[],
+            output,
+            move || CompositePushConstants {
+                intensity: self.settings.intensity,
+            },
+        );
+    }
+
+    fn destroy(&mut self, context: &VkContext) {
+        if let Some(mut pipelines) = self.pipelines.take() {
+            pipelines.downsample.destroy(context);
+            pipelines.upsample.destroy(context);
+            pipelines.composite.destroy(context);
+        }
+    }
+}
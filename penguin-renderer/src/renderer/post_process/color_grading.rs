@@ -0,0 +1,136 @@
+use crate::renderer::memory::UploadContext;
+use crate::renderer::post_process::{
+    FullscreenPipeline, FullscreenPipelineCreateInfo, PostProcessEffect,
+};
+use crate::renderer::render_graph::{ImageHandle, RenderGraph};
+use crate::renderer::render_objects::Texture;
+use crate::renderer::vk_types::VkContext;
+use ash::vk;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ColorGradingSettings {
+    /// Lookup table in the images folder, see [`Texture::color_lut_from_image_file`]. Without
+    /// one, colors are mapped to themselves.
+    pub lut: Option<String>,
+    /// Blend between the original (0) and the graded (1) colors.
+    pub strength: f32,
+}
+impl Default for ColorGradingSettings {
+    fn default() -> Self {
+        Self {
+            lut: None,
+            strength: 1.0,
+        }
+    }
+}
+
+#[repr(C)]
+struct ColorGradingPushConstants {
+    strength: f32,
+    /// lookup tables are indexed with sRGB encoded colors
+    encode_input: u32,
+}
+
+/// Maps colors through a 3D lookup table.
+#[derive(Default)]
+pub struct ColorGrading {
+    pub enabled: bool,
+    pub settings: ColorGradingSettings,
+    pipeline: Option<FullscreenPipeline>,
+    lut: Option<Texture>,
+    encode_input: bool,
+}
+impl ColorGrading {
+    /// `input_is_encoded` tells whether the colors read from the LDR images are still sRGB
+    /// encoded, see [`super::Tonemap::output_is_encoded`].
+    pub fn init(
+        &mut self,
+        context: &VkContext,
+        upload_context: &UploadContext,
+        output_format: vk::Format,
+        input_is_encoded: bool,
+        frames_in_flight: usize,
+        settings: ColorGradingSettings,
+    ) {
+        self.encode_input = !input_is_encoded;
+
+        self.lut = Some(match &settings.lut {
+            Some(lut) => Texture::color_lut_from_image_file(context, upload_context, lut),
+            None => Texture::identity_color_lut(
+                context,
+                upload_context,
+                crate::config::IDENTITY_COLOR_LUT_SIZE,
+            ),
+        });
+        self.settings = settings;
+
+        self.pipeline = Some(FullscreenPipeline::new(
+            context,
+            FullscreenPipelineCreateInfo {
+                fragment_shader: "color_grading.frag",
+                output_format,
+                input_count: 2,
+                push_constants_size: std::mem::size_of::<ColorGradingPushConstants>() as _,
+                frames_in_flight,
+                max_draws_per_frame: 1,
+            },
+        ));
+    }
+}
+
+impl PostProcessEffect for ColorGrading {
+    fn name(&self) -> &'static str {
+        "color grading"
+    }
+
+    fn is_hdr(&self) -> bool {
+        false
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn begin_frame(&mut self, frame_index: usize) {
+        if let Some(pipeline) = self.pipeline.as_mut() {
+            pipeline.begin_frame(frame_index);
+        }
+    }
+
+    fn add_passes<'a>(
+        &'a self,
+        graph: &mut RenderGraph<'a>,
+        input: ImageHandle,
+        output: ImageHandle,
+        _extent: vk::Extent2D,
+    ) {
+        let pipeline = self
+            .pipeline
+            .as_ref()
+            .expect("color grading isn't initialized");
+        let lut = self.lut.as_ref().expect("color grading isn't initialized");
+
+        pipeline.add_pass(
+            graph,
+            "color grading",
+            &[input],
+            &[lut.image_view],
+            output,
+            move || ColorGradingPushConstants {
+                strength: self.settings.strength,
+                encode_input: self.encode_input as u32,
+            },
+        );
+    }
+
+    fn destroy(&mut self, context: &VkContext) {
+        if let Some(mut pipeline) = self.pipeline.take() {
+            pipeline.destroy(context);
+        }
+        if let Some(mut lut) = self.lut.take() {
+            lut.destroy(context);
+        }
+    }
+}
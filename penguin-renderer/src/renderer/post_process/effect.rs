@@ -0,0 +1,40 @@
+use crate::renderer::render_graph::{ImageHandle, RenderGraph};
+use crate::renderer::vk_types::VkContext;
+use ash::vk;
+
+/// The effects a post process stack can run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PostProcessEffectKind {
+    Bloom,
+    Fxaa,
+    Vignette,
+    ColorGrading,
+}
+impl PostProcessEffectKind {
+    pub const ALL: [Self; 4] = [Self::Bloom, Self::Fxaa, Self::Vignette, Self::ColorGrading];
+}
+
+/// A screen-space effect made of one or more full-screen passes.
+pub trait PostProcessEffect {
+    fn name(&self) -> &'static str;
+
+    /// Whether the effect runs before tonemapping, on the HDR scene color.
+    fn is_hdr(&self) -> bool;
+
+    fn is_enabled(&self) -> bool;
+
+    /// Only called after the previous use of the frame has finished on the GPU.
+    fn begin_frame(&mut self, frame_index: usize);
+
+    /// Adds the passes that read `input` and write the result into `output`. Both have the
+    /// given extent.
+    fn add_passes<'a>(
+        &'a self,
+        graph: &mut RenderGraph<'a>,
+        input: ImageHandle,
+        output: ImageHandle,
+        extent: vk::Extent2D,
+    );
+
+    fn destroy(&mut self, context: &VkContext);
+}
@@ -0,0 +1,324 @@
+use crate::renderer::memory::util::as_u8_slice;
+use crate::renderer::render_graph::{AttachmentLoad, ImageHandle, PassContext, RenderGraph};
+use crate::renderer::sync::ResourceUsage;
+use crate::renderer::vk_types::{
+    DescriptorPool, DescriptorSetLayout, Pipeline, PipelineLayout, VkContext,
+};
+use ash::vk;
+use std::cell::Cell;
+
+pub struct FullscreenPipelineCreateInfo<'a> {
+    /// fragment shader in src/shaders, sampling its inputs from set 0, binding 0..input_count
+    pub fragment_shader: &'a str,
+    pub output_format: vk::Format,
+    pub input_count: u32,
+    /// size in bytes of the fragment shader's push constants, 0 if it has none
+    pub push_constants_size: u32,
+    pub frames_in_flight: usize,
+    pub max_draws_per_frame: u32,
+}
+
+/// A fragment shader drawn over the whole output image with a single triangle.
+///
+///     The shader's inputs are bound as combined image samplers of a set owned by the pipeline.
+///     Every draw of a frame gets its own set, which is rewritten when drawing, so the inputs
+///     can be different images every time. The viewport is dynamic and covers the pass' extent.
+pub struct FullscreenPipeline {
+    pipeline: Pipeline,
+    /// only used to create the pipeline, the graph's render passes are compatible with it
+    render_pass: vk::RenderPass,
+    set_layout: DescriptorSetLayout,
+    descriptor_pool: DescriptorPool,
+    /// `max_draws_per_frame` sets per frame in flight
+    sets: Vec<vk::DescriptorSet>,
+    sampler: vk::Sampler,
+    input_count: u32,
+    push_constants_size: u32,
+    max_draws_per_frame: u32,
+    frame_index: usize,
+    draw_count: Cell<u32>,
+}
+
+impl FullscreenPipeline {
+    pub fn new(context: &VkContext, create_info: FullscreenPipelineCreateInfo) -> Self {
+        let render_pass = output_render_pass(context, create_info.output_format);
+
+        let set_layout = (0..create_info.input_count)
+            .fold(DescriptorSetLayout::builder(), |builder, binding| {
+                builder.layout_binding(
+                    vk::DescriptorSetLayoutBinding::builder()
+                        .binding(binding)
+                        .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                        .descriptor_count(1)
+                        .stage_flags(vk::ShaderStageFlags::FRAGMENT),
+                )
+            })
+            .build(context);
+
+        let set_count = create_info.frames_in_flight as u32 * create_info.max_draws_per_frame;
+        let descriptor_pool = DescriptorPool::from_sizes(
+            context,
+            set_count,
+            vk::DescriptorPoolCreateFlags::empty(),
+            &[vk::DescriptorPoolSize::builder()
+                .ty(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .descriptor_count(set_count * create_info.input_count.max(1))
+                .build()],
+        );
+
+        let set_layouts = vec![set_layout.handle; set_count as usize];
+        let allocate_info = vk::DescriptorSetAllocateInfo::builder()
+            .descriptor_pool(descriptor_pool.handle)
+            .set_layouts(&set_layouts);
+        let sets = unsafe { context.device.allocate_descriptor_sets(&allocate_info) }
+            .expect("Couldn't allocate fullscreen pipeline sets");
+
+        let sampler = linear_clamp_sampler(context);
+
+        let mut pipeline_layout = PipelineLayout::builder().add_layout(set_layout.handle);
+        if create_info.push_constants_size > 0 {
+            pipeline_layout = pipeline_layout.push_constant_range(
+                vk::ShaderStageFlags::FRAGMENT,
+                0,
+                create_info.push_constants_size,
+            );
+        }
+        let pipeline_layout = pipeline_layout.build(context);
+
+        // the extent is ignored with a dynamic viewport
+        let pipeline = Pipeline::builder(
+            context,
+            vk::Extent2D {
+                width: 1,
+                height: 1,
+            },
+            render_pass,
+            vk::PipelineBindPoint::GRAPHICS,
+        )
+        .shaders(&["fullscreen.vert", create_info.fragment_shader])
+        .pipeline_layout(pipeline_layout.handle)
+        .cull_mode(vk::CullModeFlags::NONE)
+        .no_depth()
+        .dynamic_viewport()
+        .build();
+
+        Self {
+            pipeline,
+            render_pass,
+            set_layout,
+            descriptor_pool,
+            sets,
+            sampler,
+            input_count: create_info.input_count,
+            push_constants_size: create_info.push_constants_size,
+            max_draws_per_frame: create_info.max_draws_per_frame,
+            frame_index: 0,
+            draw_count: Cell::new(0),
+        }
+    }
+
+    pub fn destroy(&mut self, context: &VkContext) {
+        self.pipeline.destroy(context);
+        unsafe {
+            context.device.destroy_render_pass(self.render_pass, None);
+            context.device.destroy_sampler(self.sampler, None);
+        }
+        self.descriptor_pool.destroy(context);
+        self.set_layout.destroy(context);
+    }
+
+    /// Switches to the sets of the given frame. Only call this after the previous use of the
+    /// frame has finished on the GPU.
+    pub fn begin_frame(&mut self, frame_index: usize) {
+        self.frame_index = frame_index;
+        self.draw_count.set(0);
+    }
+
+    /// Adds a pass that draws into `output`. The shader's inputs are the graph images `inputs`,
+    /// followed by `static_inputs`. The push constants are created when the pass is recorded,
+    /// so they use the settings of that moment.
+    pub fn add_pass<'a, PushConstants, F>(
+        &'a self,
+        graph: &mut RenderGraph<'a>,
+        name: &str,
+        inputs: &[ImageHandle],
+        static_inputs: &[vk::ImageView],
+        output: ImageHandle,
+        push_constants: F,
+    ) where
+        F: FnOnce() -> PushConstants + 'a,
+    {
+        let input_handles = inputs.to_vec();
+        let static_inputs = static_inputs.to_vec();
+
+        graph.add_pass(
+            name,
+            |pass| {
+                // every pixel gets overwritten
+                let pass = pass.color_attachment(output, AttachmentLoad::DontCare);
+                inputs.iter().fold(pass, |pass, &input| {
+                    pass.read_image(input, ResourceUsage::FragmentShaderRead)
+                });
+            },
+            move |pass_context| {
+                let input_views = input_handles
+                    .iter()
+                    .map(|&input| pass_context.image_view(input))
+                    .chain(static_inputs)
+                    .collect::<Vec<_>>();
+                self.draw(pass_context, &input_views, &push_constants());
+            },
+        );
+    }
+
+    /// Binds `inputs`, which have to be in SHADER_READ_ONLY_OPTIMAL, and draws over the pass'
+    /// color attachment.
+    pub fn draw<PushConstants>(
+        &self,
+        pass_context: &PassContext,
+        inputs: &[vk::ImageView],
+        push_constants: &PushConstants,
+    ) {
+        assert_eq!(
+            inputs.len(),
+            self.input_count as usize,
+            "wrong number of fullscreen pipeline inputs"
+        );
+        debug_assert_eq!(
+            std::mem::size_of::<PushConstants>(),
+            self.push_constants_size as usize
+        );
+
+        let draw_index = self.draw_count.get();
+        assert!(
+            draw_index < self.max_draws_per_frame,
+            "fullscreen pipeline drawn more than {} times in a frame",
+            self.max_draws_per_frame
+        );
+        self.draw_count.set(draw_index + 1);
+
+        let context = pass_context.context;
+        let command_buffer = pass_context.command_buffer;
+        let set =
+            self.sets[self.frame_index * self.max_draws_per_frame as usize + draw_index as usize];
+
+        // the frame's previous use of the set finished when its fence was waited on
+        let image_infos = inputs
+            .iter()
+            .map(|&image_view| {
+                vk::DescriptorImageInfo::builder()
+                    .sampler(self.sampler)
+                    .image_view(image_view)
+                    .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                    .build()
+            })
+            .collect::<Vec<_>>();
+        let writes = image_infos
+            .iter()
+            .enumerate()
+            .map(|(binding, image_info)| {
+                vk::WriteDescriptorSet::builder()
+                    .dst_set(set)
+                    .dst_binding(binding as _)
+                    .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                    .image_info(std::slice::from_ref(image_info))
+                    .build()
+            })
+            .collect::<Vec<_>>();
+
+        let viewport = vk::Viewport {
+            x: 0.0,
+            y: 0.0,
+            width: pass_context.extent.width as f32,
+            height: pass_context.extent.height as f32,
+            min_depth: 0.0,
+            max_depth: 1.0,
+        };
+        let scissor = vk::Rect2D {
+            offset: vk::Offset2D { x: 0, y: 0 },
+            extent: pass_context.extent,
+        };
+
+        unsafe {
+            context.device.update_descriptor_sets(&writes, &[]);
+
+            context.device.cmd_bind_pipeline(
+                command_buffer,
+                self.pipeline.pipeline_bind_point,
+                self.pipeline.handle,
+            );
+            context.device.cmd_bind_descriptor_sets(
+                command_buffer,
+                self.pipeline.pipeline_bind_point,
+                self.pipeline.pipeline_layout,
+                0,
+                &[set],
+                &[],
+            );
+            context
+                .device
+                .cmd_set_viewport(command_buffer, 0, &[viewport]);
+            context.device.cmd_set_scissor(command_buffer, 0, &[scissor]);
+            if self.push_constants_size > 0 {
+                context.device.cmd_push_constants(
+                    command_buffer,
+                    self.pipeline.pipeline_layout,
+                    vk::ShaderStageFlags::FRAGMENT,
+                    0,
+                    as_u8_slice(push_constants),
+                );
+            }
+
+            context.device.cmd_draw(command_buffer, 3, 1, 0, 0);
+        }
+    }
+}
+
+/// Color only render pass the pipeline is created against.
+fn output_render_pass(context: &VkContext, format: vk::Format) -> vk::RenderPass {
+    let attachments = [vk::AttachmentDescription::builder()
+        .format(format)
+        .samples(vk::SampleCountFlags::TYPE_1)
+        .load_op(vk::AttachmentLoadOp::DONT_CARE)
+        .store_op(vk::AttachmentStoreOp::STORE)
+        .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+        .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+        .initial_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+        .final_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+        .build()];
+
+    let color_attachment_refs = [vk::AttachmentReference {
+        attachment: 0,
+        layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+    }];
+
+    let subpasses = [vk::SubpassDescription::builder()
+        .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+        .color_attachments(&color_attachment_refs)
+        .build()];
+
+    let render_pass_create_info = vk::RenderPassCreateInfo::builder()
+        .attachments(&attachments)
+        .subpasses(&subpasses);
+
+    unsafe {
+        context
+            .device
+            .create_render_pass(&render_pass_create_info, None)
+    }
+    .expect("Couldn't create fullscreen render pass")
+}
+
+fn linear_clamp_sampler(context: &VkContext) -> vk::Sampler {
+    let filter = vk::Filter::LINEAR;
+    let address_mode = vk::SamplerAddressMode::CLAMP_TO_EDGE;
+    let sampler_create_info = vk::SamplerCreateInfo::builder()
+        .mag_filter(filter)
+        .min_filter(filter)
+        .address_mode_u(address_mode)
+        .address_mode_v(address_mode)
+        .address_mode_w(address_mode);
+
+    unsafe { context.device.create_sampler(&sampler_create_info, None) }
+        .expect("couldn't create linear clamp sampler")
+}
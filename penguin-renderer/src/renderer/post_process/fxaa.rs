@@ -0,0 +1,108 @@
+use crate::renderer::post_process::{
+    FullscreenPipeline, FullscreenPipelineCreateInfo, PostProcessEffect,
+};
+use crate::renderer::render_graph::{ImageHandle, RenderGraph};
+use crate::renderer::vk_types::VkContext;
+use ash::vk;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct FxaaSettings {
+    /// Minimum local contrast, relative to the brightest neighbour, that counts as an edge.
+    pub edge_threshold: f32,
+    /// Contrast below which dark areas are never treated as edges.
+    pub edge_threshold_min: f32,
+    /// Maximum distance in pixels that is sampled along an edge.
+    pub span_max: f32,
+}
+impl Default for FxaaSettings {
+    fn default() -> Self {
+        Self {
+            edge_threshold: 0.125,
+            edge_threshold_min: 0.0312,
+            span_max: 8.0,
+        }
+    }
+}
+
+#[repr(C)]
+struct FxaaPushConstants {
+    edge_threshold: f32,
+    edge_threshold_min: f32,
+    span_max: f32,
+}
+
+/// Fast approximate anti-aliasing, blurs along edges found by their luma contrast.
+#[derive(Default)]
+pub struct Fxaa {
+    pub enabled: bool,
+    pub settings: FxaaSettings,
+    pipeline: Option<FullscreenPipeline>,
+}
+impl Fxaa {
+    pub fn init(
+        &mut self,
+        context: &VkContext,
+        output_format: vk::Format,
+        frames_in_flight: usize,
+        settings: FxaaSettings,
+    ) {
+        self.settings = settings;
+        self.pipeline = Some(FullscreenPipeline::new(
+            context,
+            FullscreenPipelineCreateInfo {
+                fragment_shader: "fxaa.frag",
+                output_format,
+                input_count: 1,
+                push_constants_size: std::mem::size_of::<FxaaPushConstants>() as _,
+                frames_in_flight,
+                max_draws_per_frame: 1,
+            },
+        ));
+    }
+}
+
+impl PostProcessEffect for Fxaa {
+    fn name(&self) -> &'static str {
+        "fxaa"
+    }
+
+    fn is_hdr(&self) -> bool {
+        false
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn begin_frame(&mut self, frame_index: usize) {
+        if let Some(pipeline) = self.pipeline.as_mut() {
+            pipeline.begin_frame(frame_index);
+        }
+    }
+
+    fn add_passes<'a>(
+        &'a self,
+        graph: &mut RenderGraph<'a>,
+        input: ImageHandle,
+        output: ImageHandle,
+        _extent: vk::Extent2D,
+    ) {
+        let pipeline = self.pipeline.as_ref().expect("fxaa isn't initialized");
+
+        pipeline.add_pass(graph, "fxaa", &[input], &[], output, move || {
+            FxaaPushConstants {
+                edge_threshold: self.settings.edge_threshold,
+                edge_threshold_min: self.settings.edge_threshold_min,
+                span_max: self.settings.span_max,
+            }
+        });
+    }
+
+    fn destroy(&mut self, context: &VkContext) {
+        if let Some(mut pipeline) = self.pipeline.take() {
+            pipeline.destroy(context);
+        }
+    }
+}
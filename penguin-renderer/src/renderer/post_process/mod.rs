@@ -0,0 +1,23 @@
+mod fullscreen;
+pub use fullscreen::*;
+
+mod effect;
+pub use effect::*;
+
+mod stack;
+pub use stack::*;
+
+mod tonemap;
+pub use tonemap::*;
+
+mod bloom;
+pub use bloom::*;
+
+mod color_grading;
+pub use color_grading::*;
+
+mod fxaa;
+pub use fxaa::*;
+
+mod vignette;
+pub use vignette::*;
@@ -0,0 +1,256 @@
+use crate::renderer::memory::UploadContext;
+use crate::renderer::post_process::{
+    Bloom, BloomSettings, ColorGrading, ColorGradingSettings, Fxaa, FxaaSettings,
+    PostProcessEffect, PostProcessEffectKind, Tonemap, TonemapSettings, Vignette,
+    VignetteSettings,
+};
+use crate::renderer::render_graph::{ImageDesc, ImageHandle, RenderGraph};
+use crate::renderer::vk_types::{Swapchain, VkContext};
+use ash::vk;
+use serde::Deserialize;
+
+/// An effect and its settings, as listed in the renderer config.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "effect", rename_all = "snake_case")]
+pub enum PostProcessEffectSettings {
+    Bloom(BloomSettings),
+    Fxaa(FxaaSettings),
+    Vignette(VignetteSettings),
+    ColorGrading(ColorGradingSettings),
+}
+impl PostProcessEffectSettings {
+    pub fn kind(&self) -> PostProcessEffectKind {
+        match self {
+            Self::Bloom(_) => PostProcessEffectKind::Bloom,
+            Self::Fxaa(_) => PostProcessEffectKind::Fxaa,
+            Self::Vignette(_) => PostProcessEffectKind::Vignette,
+            Self::ColorGrading(_) => PostProcessEffectKind::ColorGrading,
+        }
+    }
+
+    fn default_for(kind: PostProcessEffectKind) -> Self {
+        match kind {
+            PostProcessEffectKind::Bloom => Self::Bloom(Default::default()),
+            PostProcessEffectKind::Fxaa => Self::Fxaa(Default::default()),
+            PostProcessEffectKind::Vignette => Self::Vignette(Default::default()),
+            PostProcessEffectKind::ColorGrading => Self::ColorGrading(Default::default()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct PostProcessEffectConfig {
+    #[serde(flatten)]
+    pub effect: PostProcessEffectSettings,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+impl PostProcessEffectConfig {
+    /// Chain used when the renderer config doesn't list any effects.
+    pub fn default_chain() -> Vec<Self> {
+        [
+            (PostProcessEffectKind::Bloom, true),
+            (PostProcessEffectKind::ColorGrading, false),
+            (PostProcessEffectKind::Vignette, true),
+            (PostProcessEffectKind::Fxaa, true),
+        ]
+        .into_iter()
+        .map(|(kind, enabled)| Self {
+            effect: PostProcessEffectSettings::default_for(kind),
+            enabled,
+        })
+        .collect()
+    }
+}
+
+/// Ordered chain of full-screen effects that turns the HDR scene color into the final image.
+///
+///     Enabled HDR effects run first, then the tonemap pass, then the enabled LDR effects, each
+///     in the configured order. Every step writes a new graph image and the last one writes the
+///     output, so the graph ends up ping-ponging between two intermediate images per format.
+///     Effects can be toggled and their settings changed while the renderer runs.
+#[derive(Default)]
+pub struct PostProcessResource {
+    pub tonemap: Tonemap,
+    pub bloom: Bloom,
+    pub fxaa: Fxaa,
+    pub vignette: Vignette,
+    pub color_grading: ColorGrading,
+    order: Vec<PostProcessEffectKind>,
+    /// format of the output and of the images between the LDR effects
+    ldr_format: vk::Format,
+}
+
+impl PostProcessResource {
+    /// Effects missing from `chain` are added, disabled, after the listed ones.
+    pub fn init(
+        &mut self,
+        context: &VkContext,
+        upload_context: &UploadContext,
+        swapchain: &Swapchain,
+        frames_in_flight: usize,
+        tonemap_settings: TonemapSettings,
+        chain: &[PostProcessEffectConfig],
+    ) {
+        self.ldr_format = swapchain.format;
+        self.tonemap
+            .init(context, self.ldr_format, frames_in_flight, tonemap_settings);
+
+        let mut configs: Vec<PostProcessEffectConfig> = Vec::with_capacity(chain.len());
+        for config in chain {
+            if configs
+                .iter()
+                .any(|other| other.effect.kind() == config.effect.kind())
+            {
+                log::warn!(
+                    "Post process effect {:?} is listed more than once, ignoring the duplicate.",
+                    config.effect.kind()
+                );
+                continue;
+            }
+            configs.push(config.clone());
+        }
+        for kind in PostProcessEffectKind::ALL {
+            if !configs.iter().any(|config| config.effect.kind() == kind) {
+                configs.push(PostProcessEffectConfig {
+                    effect: PostProcessEffectSettings::default_for(kind),
+                    enabled: false,
+                });
+            }
+        }
+
+        self.order = configs.iter().map(|config| config.effect.kind()).collect();
+
+        for config in configs {
+            match config.effect {
+                PostProcessEffectSettings::Bloom(settings) => {
+                    self.bloom.init(context, frames_in_flight, settings);
+                    self.bloom.enabled = config.enabled;
+                }
+                PostProcessEffectSettings::Fxaa(settings) => {
+                    self.fxaa
+                        .init(context, self.ldr_format, frames_in_flight, settings);
+                    self.fxaa.enabled = config.enabled;
+                }
+                PostProcessEffectSettings::Vignette(settings) => {
+                    self.vignette
+                        .init(context, self.ldr_format, frames_in_flight, settings);
+                    self.vignette.enabled = config.enabled;
+                }
+                PostProcessEffectSettings::ColorGrading(settings) => {
+                    self.color_grading.init(
+                        context,
+                        upload_context,
+                        self.ldr_format,
+                        self.tonemap.output_is_encoded(),
+                        frames_in_flight,
+                        settings,
+                    );
+                    self.color_grading.enabled = config.enabled;
+                }
+            }
+        }
+
+        log::debug!("Post process order: {:?}", self.order);
+    }
+
+    pub fn destroy(&mut self, context: &VkContext) {
+        self.tonemap.destroy(context);
+        for kind in PostProcessEffectKind::ALL {
+            self.effect_mut(kind).destroy(context);
+        }
+    }
+
+    /// Only call this after the previous use of the frame has finished on the GPU.
+    pub fn begin_frame(&mut self, frame_index: usize) {
+        self.tonemap.begin_frame(frame_index);
+        for kind in PostProcessEffectKind::ALL {
+            self.effect_mut(kind).begin_frame(frame_index);
+        }
+    }
+
+    pub fn order(&self) -> &[PostProcessEffectKind] {
+        &self.order
+    }
+
+    /// Effects left out of `order` don't run, even if they're enabled.
+    pub fn set_order(&mut self, order: Vec<PostProcessEffectKind>) {
+        debug_assert!(
+            order
+                .iter()
+                .enumerate()
+                .all(|(index, kind)| !order[..index].contains(kind)),
+            "post process effect listed more than once"
+        );
+        self.order = order;
+    }
+
+    pub fn effect(&self, kind: PostProcessEffectKind) -> &dyn PostProcessEffect {
+        match kind {
+            PostProcessEffectKind::Bloom => &self.bloom,
+            PostProcessEffectKind::Fxaa => &self.fxaa,
+            PostProcessEffectKind::Vignette => &self.vignette,
+            PostProcessEffectKind::ColorGrading => &self.color_grading,
+        }
+    }
+
+    pub fn effect_mut(&mut self, kind: PostProcessEffectKind) -> &mut dyn PostProcessEffect {
+        match kind {
+            PostProcessEffectKind::Bloom => &mut self.bloom,
+            PostProcessEffectKind::Fxaa => &mut self.fxaa,
+            PostProcessEffectKind::Vignette => &mut self.vignette,
+            PostProcessEffectKind::ColorGrading => &mut self.color_grading,
+        }
+    }
+
+    /// Adds the passes that take `scene_color` (in [`crate::config::HDR_COLOR_FORMAT`]) through
+    /// the chain into `output`, which has the swapchain's format.
+    pub fn add_passes<'a>(
+        &'a self,
+        graph: &mut RenderGraph<'a>,
+        scene_color: ImageHandle,
+        output: ImageHandle,
+        extent: vk::Extent2D,
+    ) {
+        let (hdr_effects, ldr_effects): (Vec<_>, Vec<_>) = self
+            .order
+            .iter()
+            .map(|&kind| self.effect(kind))
+            .filter(|effect| effect.is_enabled())
+            .partition(|effect| effect.is_hdr());
+
+        let mut color = scene_color;
+        for effect in hdr_effects {
+            let target = graph.create_image(
+                effect.name(),
+                ImageDesc::new_2d(crate::config::HDR_COLOR_FORMAT, extent),
+            );
+            effect.add_passes(graph, color, target, extent);
+            color = target;
+        }
+
+        // the last step writes straight into the output
+        let ldr_target = |graph: &mut RenderGraph<'a>, name: &str, is_last: bool| {
+            if is_last {
+                output
+            } else {
+                graph.create_image(name, ImageDesc::new_2d(self.ldr_format, extent))
+            }
+        };
+
+        let tonemapped = ldr_target(graph, "tonemapped", ldr_effects.is_empty());
+        self.tonemap.add_passes(graph, color, tonemapped);
+        color = tonemapped;
+
+        for (index, &effect) in ldr_effects.iter().enumerate() {
+            let target = ldr_target(graph, effect.name(), index + 1 == ldr_effects.len());
+            effect.add_passes(graph, color, target, extent);
+            color = target;
+        }
+    }
+}
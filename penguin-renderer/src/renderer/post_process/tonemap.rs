@@ -0,0 +1,131 @@
+use crate::renderer::post_process::{FullscreenPipeline, FullscreenPipelineCreateInfo};
+use crate::renderer::render_graph::{ImageHandle, RenderGraph};
+use crate::renderer::vk_types::VkContext;
+use ash::vk;
+use serde::Deserialize;
+
+/// Curve that maps HDR colors into the displayable 0..1 range. The values match the constants
+/// in tonemap.frag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[repr(u32)]
+pub enum TonemapOperator {
+    Aces = 0,
+    Reinhard = 1,
+    /// John Hable's Uncharted 2 curve
+    Filmic = 2,
+}
+impl Default for TonemapOperator {
+    fn default() -> Self {
+        Self::Aces
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TonemapSettings {
+    pub operator: TonemapOperator,
+    /// HDR colors are multiplied by this before being tonemapped.
+    pub exposure: f32,
+}
+impl Default for TonemapSettings {
+    fn default() -> Self {
+        Self {
+            operator: TonemapOperator::default(),
+            exposure: crate::config::DEFAULT_EXPOSURE,
+        }
+    }
+}
+
+#[repr(C)]
+struct TonemapPushConstants {
+    exposure: f32,
+    tonemap_operator: u32,
+    encode_srgb: u32,
+}
+
+/// Full-screen pass that maps the HDR scene color into the output format.
+///
+///     The settings are read every frame, so they can be changed while the renderer runs.
+#[derive(Default)]
+pub struct Tonemap {
+    pub settings: TonemapSettings,
+    pipeline: Option<FullscreenPipeline>,
+    /// the output image isn't sRGB, so the shader has to encode its output
+    encode_srgb: bool,
+}
+impl Tonemap {
+    pub fn init(
+        &mut self,
+        context: &VkContext,
+        output_format: vk::Format,
+        frames_in_flight: usize,
+        settings: TonemapSettings,
+    ) {
+        self.settings = settings;
+        self.encode_srgb = !is_srgb_format(output_format);
+        if self.encode_srgb {
+            log::info!(
+                "Output format {:?} isn't sRGB, encoding in the tonemap shader.",
+                output_format
+            );
+        }
+
+        self.pipeline = Some(FullscreenPipeline::new(
+            context,
+            FullscreenPipelineCreateInfo {
+                fragment_shader: "tonemap.frag",
+                output_format,
+                input_count: 1,
+                push_constants_size: std::mem::size_of::<TonemapPushConstants>() as _,
+                frames_in_flight,
+                max_draws_per_frame: 1,
+            },
+        ));
+    }
+
+    pub fn destroy(&mut self, context: &VkContext) {
+        if let Some(mut pipeline) = self.pipeline.take() {
+            pipeline.destroy(context);
+        }
+    }
+
+    pub fn begin_frame(&mut self, frame_index: usize) {
+        if let Some(pipeline) = self.pipeline.as_mut() {
+            pipeline.begin_frame(frame_index);
+        }
+    }
+
+    /// Whether colors written by the tonemap pass are still sRGB encoded when read back, because
+    /// the output format doesn't decode them.
+    pub fn output_is_encoded(&self) -> bool {
+        self.encode_srgb
+    }
+
+    pub fn add_passes<'a>(
+        &'a self,
+        graph: &mut RenderGraph<'a>,
+        input: ImageHandle,
+        output: ImageHandle,
+    ) {
+        let pipeline = self.pipeline.as_ref().expect("tonemap isn't initialized");
+
+        pipeline.add_pass(graph, "tonemap", &[input], &[], output, move || {
+            TonemapPushConstants {
+                exposure: self.settings.exposure,
+                tonemap_operator: self.settings.operator as u32,
+                encode_srgb: self.encode_srgb as u32,
+            }
+        });
+    }
+}
+
+pub(super) fn is_srgb_format(format: vk::Format) -> bool {
+    matches!(
+        format,
+        vk::Format::R8G8B8A8_SRGB
+            | vk::Format::B8G8R8A8_SRGB
+            | vk::Format::R8G8B8_SRGB
+            | vk::Format::B8G8R8_SRGB
+            | vk::Format::A8B8G8R8_SRGB_PACK32
+    )
+}
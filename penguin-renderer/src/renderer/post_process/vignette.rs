@@ -0,0 +1,108 @@
+use crate::renderer::post_process::{
+    FullscreenPipeline, FullscreenPipelineCreateInfo, PostProcessEffect,
+};
+use crate::renderer::render_graph::{ImageHandle, RenderGraph};
+use crate::renderer::vk_types::VkContext;
+use ash::vk;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct VignetteSettings {
+    /// How dark the corners get, 0 leaves the image unchanged.
+    pub intensity: f32,
+    /// Distance from the center where the darkening is complete, 1 being the corners.
+    pub radius: f32,
+    /// Width of the transition towards the radius.
+    pub smoothness: f32,
+}
+impl Default for VignetteSettings {
+    fn default() -> Self {
+        Self {
+            intensity: 0.3,
+            radius: 1.0,
+            smoothness: 0.6,
+        }
+    }
+}
+
+#[repr(C)]
+struct VignettePushConstants {
+    intensity: f32,
+    radius: f32,
+    smoothness: f32,
+}
+
+/// Darkens the image towards its corners.
+#[derive(Default)]
+pub struct Vignette {
+    pub enabled: bool,
+    pub settings: VignetteSettings,
+    pipeline: Option<FullscreenPipeline>,
+}
+impl Vignette {
+    pub fn init(
+        &mut self,
+        context: &VkContext,
+        output_format: vk::Format,
+        frames_in_flight: usize,
+        settings: VignetteSettings,
+    ) {
+        self.settings = settings;
+        self.pipeline = Some(FullscreenPipeline::new(
+            context,
+            FullscreenPipelineCreateInfo {
+                fragment_shader: "vignette.frag",
+                output_format,
+                input_count: 1,
+                push_constants_size: std::mem::size_of::<VignettePushConstants>() as _,
+                frames_in_flight,
+                max_draws_per_frame: 1,
+            },
+        ));
+    }
+}
+
+impl PostProcessEffect for Vignette {
+    fn name(&self) -> &'static str {
+        "vignette"
+    }
+
+    fn is_hdr(&self) -> bool {
+        false
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn begin_frame(&mut self, frame_index: usize) {
+        if let Some(pipeline) = self.pipeline.as_mut() {
+            pipeline.begin_frame(frame_index);
+        }
+    }
+
+    fn add_passes<'a>(
+        &'a self,
+        graph: &mut RenderGraph<'a>,
+        input: ImageHandle,
+        output: ImageHandle,
+        _extent: vk::Extent2D,
+    ) {
+        let pipeline = self.pipeline.as_ref().expect("vignette isn't initialized");
+
+        pipeline.add_pass(graph, "vignette", &[input], &[], output, move || {
+            VignettePushConstants {
+                intensity: self.settings.intensity,
+                radius: self.settings.radius,
+                smoothness: self.settings.smoothness,
+            }
+        });
+    }
+
+    fn destroy(&mut self, context: &VkContext) {
+        if let Some(mut pipeline) = self.pipeline.take() {
+            pipeline.destroy(context);
+        }
+    }
+}
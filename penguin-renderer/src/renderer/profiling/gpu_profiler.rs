@@ -0,0 +1,596 @@
+use crate::renderer::profiling::{
+    GpuStatsResource, OcclusionResult, PipelineStatistics, QueryPool, SamplesPassed, ScopeTiming,
+    Timestamp,
+};
+use crate::renderer::vk_types::{DeviceFeature, FeatureRequest, VkContext};
+use ash::vk;
+use serde::Deserialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ProfilingSettings {
+    /// Records GPU timestamps around every render graph pass. Only read at startup.
+    pub enabled: bool,
+    /// Also counts the vertices, primitives and shader invocations of every pass, where the
+    /// device supports it. Only read at startup.
+    pub pipeline_statistics: bool,
+    /// Counts the samples of every drawn object that pass the depth test, up to
+    /// `MAX_OCCLUSION_QUERIES` objects a frame.
+    pub occlusion_queries: bool,
+    /// Frames the rolling averages are taken over. Only read at startup.
+    pub average_frames: u32,
+    /// Logs the averages every this many frames, 0 never logs them.
+    pub log_interval: u32,
+}
+impl Default for ProfilingSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            pipeline_statistics: false,
+            occlusion_queries: false,
+            average_frames: 60,
+            log_interval: 0,
+        }
+    }
+}
+
+/// Measures how long the GPU takes for scopes of a frame's commands, using a timestamp query
+/// pool per frame in flight. Optionally counts the work of top level scopes with pipeline
+/// statistics queries, and runs occlusion queries for the draw recording code.
+///
+///     A frame's results are read when its frame data is reused, when the GPU is known to have
+///     finished it, and published to the `GpuStatsResource`.
+#[derive(Default)]
+pub struct GpuProfilerResource {
+    settings: ProfilingSettings,
+    objects: Option<ProfilerObjects>,
+    warned_about_occlusion_overflow: AtomicBool,
+}
+
+struct ProfilerObjects {
+    frames: Vec<ProfilerFrame>,
+    current_frame: usize,
+    /// nanoseconds per timestamp tick
+    timestamp_period: f32,
+    timestamp_valid_bits: u32,
+}
+
+struct ProfilerFrame {
+    /// scope `i` writes its timestamps into queries `2i` and `2i + 1`
+    timestamps: QueryPool<Timestamp>,
+    statistics: Option<QueryPool<PipelineStatistics>>,
+    occlusion: QueryPool<SamplesPassed>,
+    recorded: Mutex<RecordedQueries>,
+}
+
+#[derive(Default)]
+struct RecordedQueries {
+    scopes: Vec<RecordedScope>,
+    statistics_count: u32,
+    /// names of the occlusion queries, in query order
+    occlusion: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct RecordedScope {
+    name: String,
+    depth: u32,
+    ended: bool,
+    statistics_query: Option<u32>,
+}
+
+impl GpuProfilerResource {
+    /// Pipeline statistics are only counted on devices that support them.
+    pub const FEATURE_REQUEST: FeatureRequest = FeatureRequest {
+        subsystem: "pipeline statistics",
+        required: false,
+        extensions: &[],
+        features: &[DeviceFeature::PipelineStatisticsQuery],
+    };
+
+    pub fn init(
+        &mut self,
+        context: &VkContext,
+        frames_in_flight: usize,
+        settings: ProfilingSettings,
+    ) {
+        self.settings = settings;
+        if !settings.enabled {
+            return;
+        }
+
+        let timestamp_valid_bits = unsafe {
+            context
+                .instance
+                .get_physical_device_queue_family_properties(context.physical_device.handle)
+        }[context.physical_device.graphics_queue_index as usize]
+            .timestamp_valid_bits;
+        if timestamp_valid_bits == 0 {
+            log::info!("GPU profiling disabled, the graphics queue doesn't support timestamps.");
+            return;
+        }
+
+        let count_statistics = settings.pipeline_statistics
+            && context
+                .enabled_features()
+                .is_enabled(Self::FEATURE_REQUEST.subsystem);
+        if settings.pipeline_statistics && !count_statistics {
+            log::info!("Pipeline statistics disabled, the device doesn't support them.");
+        }
+
+        let frames = (0..frames_in_flight)
+            .map(|_| ProfilerFrame {
+                timestamps: QueryPool::new(context, crate::config::MAX_GPU_PROFILE_SCOPES * 2),
+                statistics: count_statistics
+                    .then(|| QueryPool::new(context, crate::config::MAX_GPU_PROFILE_SCOPES)),
+                occlusion: QueryPool::new(context, crate::config::MAX_OCCLUSION_QUERIES),
+                recorded: Mutex::new(RecordedQueries::default()),
+            })
+            .collect();
+
+        self.objects = Some(ProfilerObjects {
+            frames,
+            current_frame: 0,
+            timestamp_period: context.pd_device_properties().limits.timestamp_period,
+            timestamp_valid_bits,
+        });
+    }
+
+    pub fn destroy(&mut self, context: &VkContext) {
+        if let Some(objects) = self.objects.take() {
+            for mut frame in objects.frames {
+                frame.timestamps.destroy(context);
+                if let Some(statistics) = &mut frame.statistics {
+                    statistics.destroy(context);
+                }
+                frame.occlusion.destroy(context);
+            }
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.objects.is_some()
+    }
+
+    /// Publishes the results the frame data recorded when it was last used and resets its
+    /// queries. Has to be recorded before any scope and outside of a render pass.
+    pub fn begin_frame(
+        &mut self,
+        context: &VkContext,
+        command_buffer: vk::CommandBuffer,
+        frame_index: usize,
+        stats: &mut GpuStatsResource,
+    ) {
+        let log_interval = self.settings.log_interval;
+        let objects = match &mut self.objects {
+            Some(objects) => objects,
+            None => return,
+        };
+        objects.current_frame = frame_index;
+
+        let frame = &mut objects.frames[frame_index];
+        let recorded = std::mem::take(
+            frame
+                .recorded
+                .get_mut()
+                .expect("profile scopes lock was poisoned"),
+        );
+
+        if !recorded.scopes.is_empty() || !recorded.occlusion.is_empty() {
+            let timestamps = frame
+                .timestamps
+                .results(context, 0, recorded.scopes.len() as u32 * 2);
+            let statistics = match &frame.statistics {
+                Some(pool) if recorded.statistics_count > 0 => {
+                    pool.results(context, 0, recorded.statistics_count)
+                }
+                _ => Some(Vec::new()),
+            };
+            let occlusion = frame
+                .occlusion
+                .results(context, 0, recorded.occlusion.len() as u32);
+
+            match (timestamps, statistics, occlusion) {
+                (Some(timestamps), Some(statistics), Some(occlusion)) => {
+                    stats.publish(
+                        resolve_timings(
+                            &recorded.scopes,
+                            &timestamps,
+                            &statistics,
+                            objects.timestamp_valid_bits,
+                            objects.timestamp_period,
+                        ),
+                        resolve_occlusion(recorded.occlusion, &occlusion),
+                    );
+                    if log_interval > 0 && stats.resolved_frames() % log_interval as u64 == 0 {
+                        stats.log_averages();
+                    }
+                }
+                _ => log::warn!("GPU profiling results of a finished frame aren't available"),
+            }
+        }
+
+        frame.timestamps.cmd_reset(context, command_buffer);
+        if let Some(statistics) = &frame.statistics {
+            statistics.cmd_reset(context, command_buffer);
+        }
+        frame.occlusion.cmd_reset(context, command_buffer);
+    }
+
+    /// Measures the commands recorded until the returned scope is dropped. Scopes can nest,
+    /// pipeline statistics are only counted for scopes that aren't nested.
+    pub fn scope<'p>(
+        &'p self,
+        context: &'p VkContext,
+        command_buffer: vk::CommandBuffer,
+        name: &str,
+    ) -> GpuProfileScope<'p> {
+        let frame = match self.current_frame() {
+            Some(frame) => frame,
+            None => return GpuProfileScope::disabled(),
+        };
+        let mut recorded = frame
+            .recorded
+            .lock()
+            .expect("profile scopes lock was poisoned");
+
+        if recorded.scopes.len() as u32 == crate::config::MAX_GPU_PROFILE_SCOPES {
+            log::warn!("Too many GPU profile scopes, {} isn't measured", name);
+            return GpuProfileScope::disabled();
+        }
+
+        let index = recorded.scopes.len() as u32;
+        let depth = recorded.scopes.iter().filter(|scope| !scope.ended).count() as u32;
+        // only one pipeline statistics query can be active at a time
+        let statistics_query = match &frame.statistics {
+            Some(statistics) if depth == 0 => {
+                let query = recorded.statistics_count;
+                recorded.statistics_count += 1;
+                statistics.cmd_begin(context, command_buffer, query);
+                Some(query)
+            }
+            _ => None,
+        };
+        recorded.scopes.push(RecordedScope {
+            name: name.to_owned(),
+            depth,
+            ended: false,
+            statistics_query,
+        });
+        frame.timestamps.cmd_write_timestamp(
+            context,
+            command_buffer,
+            vk::PipelineStageFlags::TOP_OF_PIPE,
+            index * 2,
+        );
+
+        GpuProfileScope {
+            open: Some(OpenScope {
+                context,
+                command_buffer,
+                frame,
+                kind: OpenQuery::Profile {
+                    index,
+                    statistics_query,
+                },
+            }),
+        }
+    }
+
+    /// Counts the samples of the draws recorded until the returned scope is dropped that pass
+    /// the depth and stencil tests. Has to end in the subpass it began in, and can't nest.
+    pub fn occlusion_query<'p>(
+        &'p self,
+        context: &'p VkContext,
+        command_buffer: vk::CommandBuffer,
+        name: &str,
+    ) -> GpuProfileScope<'p> {
+        let frame = match self.current_frame() {
+            Some(frame) if self.settings.occlusion_queries => frame,
+            _ => return GpuProfileScope::disabled(),
+        };
+        let mut recorded = frame
+            .recorded
+            .lock()
+            .expect("profile scopes lock was poisoned");
+
+        if recorded.occlusion.len() as u32 == crate::config::MAX_OCCLUSION_QUERIES {
+            if !self
+                .warned_about_occlusion_overflow
+                .swap(true, Ordering::Relaxed)
+            {
+                log::warn!(
+                    "More than {} occlusion queries a frame, {} and the rest aren't counted",
+                    crate::config::MAX_OCCLUSION_QUERIES,
+                    name
+                );
+            }
+            return GpuProfileScope::disabled();
+        }
+
+        let query = recorded.occlusion.len() as u32;
+        recorded.occlusion.push(name.to_owned());
+        frame.occlusion.cmd_begin(context, command_buffer, query);
+
+        GpuProfileScope {
+            open: Some(OpenScope {
+                context,
+                command_buffer,
+                frame,
+                kind: OpenQuery::Occlusion { query },
+            }),
+        }
+    }
+
+    fn current_frame(&self) -> Option<&ProfilerFrame> {
+        self.objects
+            .as_ref()
+            .map(|objects| &objects.frames[objects.current_frame])
+    }
+}
+
+/// Ends the queries of its scope when dropped.
+#[must_use = "the scope ends when it's dropped"]
+pub struct GpuProfileScope<'p> {
+    open: Option<OpenScope<'p>>,
+}
+
+struct OpenScope<'p> {
+    context: &'p VkContext,
+    command_buffer: vk::CommandBuffer,
+    frame: &'p ProfilerFrame,
+    kind: OpenQuery,
+}
+
+enum OpenQuery {
+    Profile {
+        index: u32,
+        statistics_query: Option<u32>,
+    },
+    Occlusion {
+        query: u32,
+    },
+}
+
+impl<'p> GpuProfileScope<'p> {
+    /// A scope that measures nothing, for when profiling is disabled.
+    pub fn disabled() -> Self {
+        Self { open: None }
+    }
+}
+
+impl<'p> Drop for GpuProfileScope<'p> {
+    fn drop(&mut self) {
+        let open = match self.open.take() {
+            Some(open) => open,
+            None => return,
+        };
+
+        match open.kind {
+            OpenQuery::Profile {
+                index,
+                statistics_query,
+            } => {
+                open.frame.timestamps.cmd_write_timestamp(
+                    open.context,
+                    open.command_buffer,
+                    vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                    index * 2 + 1,
+                );
+                if let (Some(statistics), Some(query)) = (&open.frame.statistics, statistics_query)
+                {
+                    statistics.cmd_end(open.context, open.command_buffer, query);
+                }
+                open.frame
+                    .recorded
+                    .lock()
+                    .expect("profile scopes lock was poisoned")
+                    .scopes[index as usize]
+                    .ended = true;
+            }
+            OpenQuery::Occlusion { query } => {
+                open.frame
+                    .occlusion
+                    .cmd_end(open.context, open.command_buffer, query);
+            }
+        }
+    }
+}
+
+/// Milliseconds between two timestamps. Only the valid bits count, so the difference is right
+/// even if the counter wrapped around in between.
+fn ticks_to_milliseconds(
+    begin: Timestamp,
+    end: Timestamp,
+    valid_bits: u32,
+    timestamp_period: f32,
+) -> f64 {
+    let mask = if valid_bits >= 64 {
+        u64::MAX
+    } else {
+        (1 << valid_bits) - 1
+    };
+    let ticks = end.0.wrapping_sub(begin.0) & mask;
+    ticks as f64 * timestamp_period as f64 / 1_000_000.0
+}
+
+fn resolve_timings(
+    scopes: &[RecordedScope],
+    timestamps: &[Timestamp],
+    statistics: &[PipelineStatistics],
+    valid_bits: u32,
+    timestamp_period: f32,
+) -> Vec<ScopeTiming> {
+    scopes
+        .iter()
+        .zip(timestamps.chunks_exact(2))
+        .filter(|(scope, _)| scope.ended)
+        .map(|(scope, timestamps)| ScopeTiming {
+            name: scope.name.clone(),
+            depth: scope.depth,
+            milliseconds: ticks_to_milliseconds(
+                timestamps[0],
+                timestamps[1],
+                valid_bits,
+                timestamp_period,
+            ),
+            statistics: scope
+                .statistics_query
+                .and_then(|query| statistics.get(query as usize))
+                .copied(),
+        })
+        .collect()
+}
+
+fn resolve_occlusion(names: Vec<String>, samples: &[SamplesPassed]) -> Vec<OcclusionResult> {
+    names
+        .into_iter()
+        .zip(samples)
+        .map(|(name, samples)| OcclusionResult {
+            name,
+            samples_passed: samples.0,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scope(name: &str, depth: u32) -> RecordedScope {
+        RecordedScope {
+            name: name.to_owned(),
+            depth,
+            ended: true,
+            statistics_query: None,
+        }
+    }
+
+    fn timestamps(ticks: &[u64]) -> Vec<Timestamp> {
+        ticks.iter().copied().map(Timestamp).collect()
+    }
+
+    #[test]
+    fn ticks_are_scaled_by_the_timestamp_period() {
+        assert_eq!(
+            ticks_to_milliseconds(Timestamp(1_000), Timestamp(3_000), 64, 1.0),
+            0.002
+        );
+        assert_eq!(
+            ticks_to_milliseconds(Timestamp(0), Timestamp(1_000_000), 64, 2.5),
+            2.5
+        );
+    }
+
+    #[test]
+    fn wrapped_timestamps_only_count_the_valid_bits() {
+        // a 36 bit counter that wrapped around between begin and end
+        let begin = Timestamp((1 << 36) - 10);
+        let end = Timestamp(90);
+
+        assert_eq!(ticks_to_milliseconds(begin, end, 36, 1_000_000.0), 100.0);
+    }
+
+    #[test]
+    fn timings_keep_the_scope_order_and_nesting() {
+        let scopes = [scope("forward", 0), scope("transparent", 1)];
+        let timestamps = timestamps(&[0, 4_000_000, 1_000_000, 3_000_000]);
+
+        let timings = resolve_timings(&scopes, &timestamps, &[], 64, 1.0);
+
+        assert_eq!(
+            timings,
+            [
+                ScopeTiming {
+                    name: "forward".to_owned(),
+                    depth: 0,
+                    milliseconds: 4.0,
+                    statistics: None,
+                },
+                ScopeTiming {
+                    name: "transparent".to_owned(),
+                    depth: 1,
+                    milliseconds: 2.0,
+                    statistics: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn scopes_that_never_ended_are_left_out() {
+        let scopes = [
+            scope("shadows", 0),
+            RecordedScope {
+                ended: false,
+                ..scope("forward", 0)
+            },
+        ];
+
+        let timings = resolve_timings(&scopes, &timestamps(&[0, 1, 2, 3]), &[], 64, 1.0);
+
+        assert_eq!(timings.len(), 1);
+        assert_eq!(timings[0].name, "shadows");
+    }
+
+    #[test]
+    fn statistics_belong_to_the_scope_that_counted_them() {
+        let scopes = [
+            RecordedScope {
+                statistics_query: Some(0),
+                ..scope("shadows", 0)
+            },
+            scope("cascade", 1),
+            RecordedScope {
+                statistics_query: Some(1),
+                ..scope("forward", 0)
+            },
+        ];
+        let statistics = [
+            PipelineStatistics {
+                vertex_shader_invocations: 30,
+                ..Default::default()
+            },
+            PipelineStatistics {
+                fragment_shader_invocations: 500,
+                ..Default::default()
+            },
+        ];
+
+        let timings = resolve_timings(
+            &scopes,
+            &timestamps(&[0, 1, 0, 1, 0, 1]),
+            &statistics,
+            64,
+            1.0,
+        );
+
+        assert_eq!(timings[0].statistics, Some(statistics[0]));
+        assert_eq!(timings[1].statistics, None);
+        assert_eq!(timings[2].statistics, Some(statistics[1]));
+    }
+
+    #[test]
+    fn occlusion_results_are_named_in_query_order() {
+        let results = resolve_occlusion(
+            vec!["sky".to_owned(), "monkey".to_owned()],
+            &[SamplesPassed(0), SamplesPassed(1200)],
+        );
+
+        assert_eq!(
+            results,
+            [
+                OcclusionResult {
+                    name: "sky".to_owned(),
+                    samples_passed: 0,
+                },
+                OcclusionResult {
+                    name: "monkey".to_owned(),
+                    samples_passed: 1200,
+                },
+            ]
+        );
+    }
+}
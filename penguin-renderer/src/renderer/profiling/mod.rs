@@ -0,0 +1,8 @@
+mod gpu_profiler;
+pub use gpu_profiler::*;
+
+mod query_pool;
+pub use query_pool::*;
+
+mod stats;
+pub use stats::*;
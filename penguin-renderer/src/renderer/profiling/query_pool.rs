@@ -0,0 +1,198 @@
+use crate::renderer::vk_types::VkContext;
+use ash::vk;
+use std::marker::PhantomData;
+
+/// The result of one query, laid out the way Vulkan writes it with `TYPE_64`.
+pub trait Query: Copy + Default {
+    const QUERY_TYPE: vk::QueryType;
+    /// statistics a pipeline statistics query counts, empty for other queries
+    const PIPELINE_STATISTICS: vk::QueryPipelineStatisticFlags =
+        vk::QueryPipelineStatisticFlags::empty();
+}
+
+/// GPU clock ticks, see `timestampPeriod` for their length.
+#[repr(transparent)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Timestamp(pub u64);
+impl Query for Timestamp {
+    const QUERY_TYPE: vk::QueryType = vk::QueryType::TIMESTAMP;
+}
+
+/// Samples that passed the depth and stencil tests. Without precise occlusion queries, only
+/// whether it's zero is reliable.
+#[repr(transparent)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SamplesPassed(pub u64);
+impl Query for SamplesPassed {
+    const QUERY_TYPE: vk::QueryType = vk::QueryType::OCCLUSION;
+}
+
+/// How much work the commands between a query's begin and end did.
+///
+///     The fields are in the order of their flags' bits, which is the order Vulkan writes them
+///     in.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PipelineStatistics {
+    pub input_assembly_vertices: u64,
+    pub input_assembly_primitives: u64,
+    pub vertex_shader_invocations: u64,
+    /// primitives that reached the clipping stage
+    pub clipping_invocations: u64,
+    /// primitives that left the clipping stage
+    pub clipping_primitives: u64,
+    pub fragment_shader_invocations: u64,
+    pub compute_shader_invocations: u64,
+}
+impl Query for PipelineStatistics {
+    const QUERY_TYPE: vk::QueryType = vk::QueryType::PIPELINE_STATISTICS;
+    const PIPELINE_STATISTICS: vk::QueryPipelineStatisticFlags =
+        vk::QueryPipelineStatisticFlags::from_raw(
+            vk::QueryPipelineStatisticFlags::INPUT_ASSEMBLY_VERTICES.as_raw()
+                | vk::QueryPipelineStatisticFlags::INPUT_ASSEMBLY_PRIMITIVES.as_raw()
+                | vk::QueryPipelineStatisticFlags::VERTEX_SHADER_INVOCATIONS.as_raw()
+                | vk::QueryPipelineStatisticFlags::CLIPPING_INVOCATIONS.as_raw()
+                | vk::QueryPipelineStatisticFlags::CLIPPING_PRIMITIVES.as_raw()
+                | vk::QueryPipelineStatisticFlags::FRAGMENT_SHADER_INVOCATIONS.as_raw()
+                | vk::QueryPipelineStatisticFlags::COMPUTE_SHADER_INVOCATIONS.as_raw(),
+        );
+}
+
+/// A `vk::QueryPool` whose queries all return `T`.
+///
+///     Queries have to be reset before they're used again, with `cmd_reset` outside of a
+///     render pass. A query begun inside a render pass has to end in the same subpass.
+pub struct QueryPool<T: Query> {
+    pub handle: vk::QueryPool,
+    pub query_count: u32,
+    _query: PhantomData<T>,
+}
+
+impl<T: Query> QueryPool<T> {
+    pub fn new(context: &VkContext, query_count: u32) -> Self {
+        let create_info = vk::QueryPoolCreateInfo::builder()
+            .query_type(T::QUERY_TYPE)
+            .query_count(query_count)
+            .pipeline_statistics(T::PIPELINE_STATISTICS);
+
+        let handle = unsafe { context.device.create_query_pool(&create_info, None) }
+            .expect("Couldn't create query pool");
+
+        Self {
+            handle,
+            query_count,
+            _query: PhantomData,
+        }
+    }
+
+    pub fn destroy(&mut self, context: &VkContext) {
+        unsafe { context.device.destroy_query_pool(self.handle, None) };
+    }
+
+    /// Resets every query of the pool.
+    pub fn cmd_reset(&self, context: &VkContext, command_buffer: vk::CommandBuffer) {
+        unsafe {
+            context
+                .device
+                .cmd_reset_query_pool(command_buffer, self.handle, 0, self.query_count)
+        };
+    }
+
+    /// Results of the queries `first..first + count`, or `None` if any of them isn't available
+    /// yet or they couldn't be read. Doesn't wait for the GPU.
+    pub fn results(&self, context: &VkContext, first: u32, count: u32) -> Option<Vec<T>> {
+        if count == 0 {
+            return Some(Vec::new());
+        }
+        let mut results = vec![T::default(); count as usize];
+        let status = unsafe {
+            context.device.get_query_pool_results(
+                self.handle,
+                first,
+                count,
+                &mut results,
+                vk::QueryResultFlags::TYPE_64,
+            )
+        };
+
+        match status {
+            Ok(()) => Some(results),
+            Err(vk::Result::NOT_READY) => None,
+            Err(error) => {
+                // profiling data isn't worth aborting the renderer over
+                log::error!("Couldn't get query pool results: {}", error);
+                None
+            }
+        }
+    }
+}
+
+impl QueryPool<Timestamp> {
+    /// Writes the time at which every earlier command finished the stage.
+    pub fn cmd_write_timestamp(
+        &self,
+        context: &VkContext,
+        command_buffer: vk::CommandBuffer,
+        stage: vk::PipelineStageFlags,
+        query: u32,
+    ) {
+        unsafe {
+            context
+                .device
+                .cmd_write_timestamp(command_buffer, stage, self.handle, query)
+        };
+    }
+}
+
+/// Occlusion and pipeline statistics queries count between a begin and an end.
+pub trait ScopedQuery: Query {}
+impl ScopedQuery for SamplesPassed {}
+impl ScopedQuery for PipelineStatistics {}
+
+impl<T: ScopedQuery> QueryPool<T> {
+    pub fn cmd_begin(&self, context: &VkContext, command_buffer: vk::CommandBuffer, query: u32) {
+        unsafe {
+            context.device.cmd_begin_query(
+                command_buffer,
+                self.handle,
+                query,
+                vk::QueryControlFlags::empty(),
+            )
+        };
+    }
+
+    pub fn cmd_end(&self, context: &VkContext, command_buffer: vk::CommandBuffer, query: u32) {
+        unsafe {
+            context
+                .device
+                .cmd_end_query(command_buffer, self.handle, query)
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn results_have_the_size_vulkan_writes() {
+        let statistics_count = PipelineStatistics::PIPELINE_STATISTICS
+            .as_raw()
+            .count_ones();
+
+        assert_eq!(
+            std::mem::size_of::<PipelineStatistics>(),
+            statistics_count as usize * 8
+        );
+        assert_eq!(std::mem::size_of::<Timestamp>(), 8);
+        assert_eq!(std::mem::size_of::<SamplesPassed>(), 8);
+    }
+
+    #[test]
+    fn only_pipeline_statistics_queries_count_statistics() {
+        assert!(Timestamp::PIPELINE_STATISTICS.is_empty());
+        assert!(SamplesPassed::PIPELINE_STATISTICS.is_empty());
+        assert!(PipelineStatistics::PIPELINE_STATISTICS
+            .contains(vk::QueryPipelineStatisticFlags::FRAGMENT_SHADER_INVOCATIONS));
+    }
+}
@@ -0,0 +1,167 @@
+use crate::renderer::profiling::PipelineStatistics;
+use std::collections::{BTreeMap, VecDeque};
+
+/// GPU time a profile scope took in one frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScopeTiming {
+    pub name: String,
+    /// how many scopes this one is nested in
+    pub depth: u32,
+    pub milliseconds: f64,
+    /// counted for scopes that aren't nested, if pipeline statistics are enabled
+    pub statistics: Option<PipelineStatistics>,
+}
+
+/// Result of a named occlusion query in one frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OcclusionResult {
+    pub name: String,
+    pub samples_passed: u64,
+}
+
+/// What the GPU did, published by the renderer once a frame's results are available. That's
+/// the case when its frame data is reused, so the numbers trail the recorded frame.
+#[derive(Debug, Default)]
+pub struct GpuStatsResource {
+    /// timings of the latest resolved frame, in the order the scopes began
+    pub scope_timings: Vec<ScopeTiming>,
+    /// occlusion queries of the latest resolved frame, in the order they began
+    pub occlusion: Vec<OcclusionResult>,
+    averages: BTreeMap<String, RollingAverage>,
+    average_frames: usize,
+    resolved_frames: u64,
+}
+
+impl GpuStatsResource {
+    /// Averages are taken over the last `average_frames` resolved frames.
+    pub fn new(average_frames: usize) -> Self {
+        Self {
+            average_frames: average_frames.max(1),
+            ..Default::default()
+        }
+    }
+
+    pub fn resolved_frames(&self) -> u64 {
+        self.resolved_frames
+    }
+
+    pub fn average_milliseconds(&self, scope: &str) -> Option<f64> {
+        self.averages.get(scope).map(RollingAverage::average)
+    }
+
+    pub(crate) fn publish(&mut self, timings: Vec<ScopeTiming>, occlusion: Vec<OcclusionResult>) {
+        let average_frames = self.average_frames.max(1);
+        for timing in &timings {
+            self.averages
+                .entry(timing.name.clone())
+                .or_insert_with(|| RollingAverage::new(average_frames))
+                .push(timing.milliseconds);
+        }
+        self.scope_timings = timings;
+        self.occlusion = occlusion;
+        self.resolved_frames += 1;
+    }
+
+    pub fn log_averages(&self) {
+        let averages = self
+            .scope_timings
+            .iter()
+            .filter_map(|timing| {
+                self.average_milliseconds(&timing.name).map(|average| {
+                    let statistics = timing.statistics.map_or(String::new(), |statistics| {
+                        format!(
+                            " ({} vertices, {} fragments)",
+                            statistics.vertex_shader_invocations,
+                            statistics.fragment_shader_invocations
+                        )
+                    });
+                    format!(
+                        "{:indent$}{}: {:.3} ms{}",
+                        "",
+                        timing.name,
+                        average,
+                        statistics,
+                        indent = timing.depth as usize * 2
+                    )
+                })
+            })
+            .collect::<Vec<_>>();
+
+        log::info!(
+            "GPU timings, averaged over {} frames:\n{}",
+            self.average_frames,
+            averages.join("\n")
+        );
+    }
+}
+
+/// Average of the latest samples.
+#[derive(Debug, Clone)]
+struct RollingAverage {
+    samples: VecDeque<f64>,
+    capacity: usize,
+    sum: f64,
+}
+
+impl RollingAverage {
+    fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+            sum: 0.0,
+        }
+    }
+
+    fn push(&mut self, sample: f64) {
+        if self.samples.len() == self.capacity {
+            self.sum -= self.samples.pop_front().unwrap_or_default();
+        }
+        self.samples.push_back(sample);
+        self.sum += sample;
+    }
+
+    fn average(&self) -> f64 {
+        if self.samples.is_empty() {
+            0.0
+        } else {
+            self.sum / self.samples.len() as f64
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timing(name: &str, milliseconds: f64) -> ScopeTiming {
+        ScopeTiming {
+            name: name.to_owned(),
+            depth: 0,
+            milliseconds,
+            statistics: None,
+        }
+    }
+
+    #[test]
+    fn rolling_average_only_keeps_the_latest_samples() {
+        let mut average = RollingAverage::new(2);
+        average.push(1.0);
+        average.push(2.0);
+        average.push(4.0);
+
+        assert_eq!(average.average(), 3.0);
+    }
+
+    #[test]
+    fn published_timings_are_averaged_per_scope() {
+        let mut stats = GpuStatsResource::new(4);
+        stats.publish(vec![timing("shadows", 1.0), timing("forward", 3.0)], vec![]);
+        stats.publish(vec![timing("shadows", 2.0)], vec![]);
+
+        assert_eq!(stats.resolved_frames(), 2);
+        assert_eq!(stats.scope_timings, [timing("shadows", 2.0)]);
+        assert_eq!(stats.average_milliseconds("shadows"), Some(1.5));
+        assert_eq!(stats.average_milliseconds("forward"), Some(3.0));
+        assert_eq!(stats.average_milliseconds("bloom"), None);
+    }
+}
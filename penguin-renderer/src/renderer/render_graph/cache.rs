@@ -0,0 +1,321 @@
+use crate::renderer::memory::util::aspect_mask_from_format;
+use crate::renderer::memory::{AllocatedImage, AllocatedImageCreateInfo, MemoryUsage};
+use crate::renderer::render_graph::ImageDesc;
+use crate::renderer::vk_types::VkContext;
+use ash::vk;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(super) struct TransientImageKey {
+    pub desc: ImageDesc,
+    pub usage: vk::ImageUsageFlags,
+}
+
+struct TransientImage {
+    key: TransientImageKey,
+    image: AllocatedImage,
+    image_view: vk::ImageView,
+    used_this_frame: bool,
+}
+impl TransientImage {
+    fn new(context: &VkContext, key: TransientImageKey) -> Self {
+        let desc = key.desc;
+
+        let image = AllocatedImage::create(
+            context,
+            AllocatedImageCreateInfo {
+                image_create_info: vk::ImageCreateInfo::builder()
+                    .image_type(vk::ImageType::TYPE_2D)
+                    .format(desc.format)
+                    .extent(vk::Extent3D {
+                        width: desc.width,
+                        height: desc.height,
+                        depth: 1,
+                    })
+                    .mip_levels(1)
+                    .array_layers(desc.array_layers)
+                    .samples(desc.samples)
+                    .tiling(vk::ImageTiling::OPTIMAL)
+                    .usage(key.usage)
+                    .sharing_mode(vk::SharingMode::EXCLUSIVE),
+                memory_usage: if key.usage.contains(vk::ImageUsageFlags::TRANSIENT_ATTACHMENT) {
+                    MemoryUsage::GpuOnlyLazy
+                } else {
+                    MemoryUsage::GpuOnly
+                },
+            },
+        );
+
+        let image_view_create_info = vk::ImageViewCreateInfo::builder()
+            .image(image.handle)
+            .format(desc.format)
+            .view_type(if desc.array_layers > 1 {
+                vk::ImageViewType::TYPE_2D_ARRAY
+            } else {
+                vk::ImageViewType::TYPE_2D
+            })
+            .subresource_range(vk::ImageSubresourceRange {
+                aspect_mask: desc.aspect_mask(),
+                base_mip_level: 0,
+                level_count: 1,
+                base_array_layer: 0,
+                layer_count: desc.array_layers,
+            });
+
+        let image_view = unsafe {
+            context
+                .device
+                .create_image_view(&image_view_create_info, None)
+        }
+        .expect("couldn't create transient image view");
+
+        Self {
+            key,
+            image,
+            image_view,
+            used_this_frame: false,
+        }
+    }
+
+    fn destroy(&mut self, context: &VkContext) {
+        unsafe { context.device.destroy_image_view(self.image_view, None) };
+        self.image.destroy(context);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(super) struct AttachmentKey {
+    pub format: vk::Format,
+    pub samples: vk::SampleCountFlags,
+    pub load_op: vk::AttachmentLoadOp,
+    pub store_op: vk::AttachmentStoreOp,
+    pub layout: vk::ImageLayout,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(super) struct RenderPassKey {
+    pub color_attachments: Vec<AttachmentKey>,
+    pub depth_attachment: Option<AttachmentKey>,
+    pub resolve_attachments: Vec<AttachmentKey>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct FramebufferKey {
+    render_pass: vk::RenderPass,
+    image_views: Vec<vk::ImageView>,
+    width: u32,
+    height: u32,
+}
+
+/// Vulkan objects the render graph keeps between frames.
+///
+///     Transient images are pooled per frame in flight, so a frame never aliases memory the GPU
+///     may still be using for the previous frame. Images that weren't used during the last frame
+///     that used the pool get destroyed, which takes care of images with outdated sizes.
+#[derive(Default)]
+pub struct RenderGraphResources {
+    transient_pools: Vec<Vec<TransientImage>>,
+    current_pool: usize,
+    render_passes: HashMap<RenderPassKey, vk::RenderPass>,
+    framebuffers: HashMap<FramebufferKey, vk::Framebuffer>,
+}
+
+impl RenderGraphResources {
+    pub fn new(frames_in_flight: usize) -> Self {
+        Self {
+            transient_pools: (0..frames_in_flight).map(|_| Vec::new()).collect(),
+            ..Default::default()
+        }
+    }
+
+    pub fn destroy(&mut self, context: &VkContext) {
+        self.framebuffers
+            .drain()
+            .for_each(|(_, framebuffer)| unsafe {
+                context.device.destroy_framebuffer(framebuffer, None)
+            });
+        self.render_passes
+            .drain()
+            .for_each(|(_, render_pass)| unsafe {
+                context.device.destroy_render_pass(render_pass, None)
+            });
+        self.transient_pools
+            .iter_mut()
+            .flat_map(|pool| pool.drain(..))
+            .for_each(|mut image| image.destroy(context));
+    }
+
+    /// Switches to the transient image pool of the given frame. Only call this after the
+    /// previous use of the frame has finished on the GPU.
+    pub fn begin_frame(&mut self, context: &VkContext, frame_index: usize) {
+        self.current_pool = frame_index;
+
+        let pool = &mut self.transient_pools[frame_index];
+        let (used, unused): (Vec<_>, Vec<_>) =
+            pool.drain(..).partition(|image| image.used_this_frame);
+
+        for mut image in unused {
+            log::debug!("Destroying unused transient image {:?}", image.key.desc);
+            self.framebuffers.retain(|key, framebuffer| {
+                let uses_image = key.image_views.contains(&image.image_view);
+                if uses_image {
+                    unsafe { context.device.destroy_framebuffer(*framebuffer, None) };
+                }
+                !uses_image
+            });
+            image.destroy(context);
+        }
+
+        *pool = used;
+        pool.iter_mut()
+            .for_each(|image| image.used_this_frame = false);
+    }
+
+    /// An image from the current frame's pool that isn't used by anything else this frame.
+    pub(super) fn acquire_image(
+        &mut self,
+        context: &VkContext,
+        key: TransientImageKey,
+    ) -> (vk::Image, vk::ImageView) {
+        let pool = &mut self.transient_pools[self.current_pool];
+
+        let index = match pool
+            .iter()
+            .position(|image| !image.used_this_frame && image.key == key)
+        {
+            Some(index) => index,
+            None => {
+                log::debug!("Creating transient image {:?}", key.desc);
+                pool.push(TransientImage::new(context, key));
+                pool.len() - 1
+            }
+        };
+
+        let image = &mut pool[index];
+        image.used_this_frame = true;
+        (image.image.handle, image.image_view)
+    }
+
+    pub(super) fn render_pass(&mut self, context: &VkContext, key: &RenderPassKey) -> vk::RenderPass {
+        if let Some(&render_pass) = self.render_passes.get(key) {
+            return render_pass;
+        }
+
+        let render_pass = create_render_pass(context, key);
+        self.render_passes.insert(key.clone(), render_pass);
+        render_pass
+    }
+
+    pub(super) fn framebuffer(
+        &mut self,
+        context: &VkContext,
+        render_pass: vk::RenderPass,
+        image_views: &[vk::ImageView],
+        extent: vk::Extent2D,
+    ) -> vk::Framebuffer {
+        let key = FramebufferKey {
+            render_pass,
+            image_views: image_views.to_vec(),
+            width: extent.width,
+            height: extent.height,
+        };
+
+        *self.framebuffers.entry(key).or_insert_with(|| {
+            let create_info = vk::FramebufferCreateInfo::builder()
+                .render_pass(render_pass)
+                .attachments(image_views)
+                .width(extent.width)
+                .height(extent.height)
+                .layers(1);
+
+            unsafe { context.device.create_framebuffer(&create_info, None) }
+                .expect("Couldn't create framebuffer")
+        })
+    }
+}
+
+/// Layout transitions are done by the graph's barriers, so attachments start and end in the
+/// layout they're used in and the render pass has no external dependencies.
+fn create_render_pass(context: &VkContext, key: &RenderPassKey) -> vk::RenderPass {
+    let attachment_description = |attachment: &AttachmentKey| {
+        // the stencil aspect of depth-stencil formats is loaded and stored like the depth
+        let (stencil_load_op, stencil_store_op) = if aspect_mask_from_format(attachment.format)
+            .contains(vk::ImageAspectFlags::STENCIL)
+        {
+            (attachment.load_op, attachment.store_op)
+        } else {
+            (vk::AttachmentLoadOp::DONT_CARE, vk::AttachmentStoreOp::DONT_CARE)
+        };
+
+        vk::AttachmentDescription::builder()
+            .format(attachment.format)
+            .samples(attachment.samples)
+            .load_op(attachment.load_op)
+            .store_op(attachment.store_op)
+            .stencil_load_op(stencil_load_op)
+            .stencil_store_op(stencil_store_op)
+            .initial_layout(attachment.layout)
+            .final_layout(attachment.layout)
+            .build()
+    };
+
+    let attachments = key
+        .color_attachments
+        .iter()
+        .chain(key.depth_attachment.iter())
+        .chain(key.resolve_attachments.iter())
+        .map(attachment_description)
+        .collect::<Vec<_>>();
+
+    let color_attachment_refs = key
+        .color_attachments
+        .iter()
+        .enumerate()
+        .map(|(index, attachment)| vk::AttachmentReference {
+            attachment: index as _,
+            layout: attachment.layout,
+        })
+        .collect::<Vec<_>>();
+
+    let depth_attachment_ref = key
+        .depth_attachment
+        .as_ref()
+        .map(|attachment| vk::AttachmentReference {
+            attachment: key.color_attachments.len() as _,
+            layout: attachment.layout,
+        });
+
+    let first_resolve_attachment =
+        key.color_attachments.len() + key.depth_attachment.iter().count();
+    let resolve_attachment_refs = key
+        .resolve_attachments
+        .iter()
+        .enumerate()
+        .map(|(index, attachment)| vk::AttachmentReference {
+            attachment: (first_resolve_attachment + index) as _,
+            layout: attachment.layout,
+        })
+        .collect::<Vec<_>>();
+
+    let mut subpass = vk::SubpassDescription::builder()
+        .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+        .color_attachments(&color_attachment_refs);
+    if let Some(depth_attachment_ref) = depth_attachment_ref.as_ref() {
+        subpass = subpass.depth_stencil_attachment(depth_attachment_ref);
+    }
+    if !resolve_attachment_refs.is_empty() {
+        subpass = subpass.resolve_attachments(&resolve_attachment_refs);
+    }
+    let subpasses = [subpass.build()];
+
+    let render_pass_create_info = vk::RenderPassCreateInfo::builder()
+        .attachments(&attachments)
+        .subpasses(&subpasses);
+
+    unsafe {
+        context
+            .device
+            .create_render_pass(&render_pass_create_info, None)
+    }
+    .expect("Couldn't create render graph render pass")
+}
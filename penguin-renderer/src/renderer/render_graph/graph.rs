@@ -0,0 +1,908 @@
+use crate::renderer::profiling::GpuProfilerResource;
+use crate::renderer::render_graph::cache::{AttachmentKey, RenderPassKey, TransientImageKey};
+use crate::renderer::render_graph::pass::{Attachment, Pass};
+use crate::renderer::render_graph::{
+    AttachmentLoad, BufferHandle, GraphBuffer, GraphImage, GraphImageKind, ImageDesc,
+    ImageHandle, ImportedBuffer, ImportedImage, PassBuilder, PassContext, RenderGraphResources,
+};
+use crate::renderer::sync::{buffer_barrier, image_barrier, PipelineBarrierBuilder, ResourceUsage};
+use crate::renderer::vk_types::VkContext;
+use ash::vk;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum ResourceId {
+    Image(usize),
+    Buffer(usize),
+}
+
+/// A physical image shared by transient images with equal descriptions.
+struct PhysicalImageSlot {
+    desc: ImageDesc,
+    usage: vk::ImageUsageFlags,
+    last_use: usize,
+    /// every image using the slot lives within a single pass
+    single_pass: bool,
+}
+impl PhysicalImageSlot {
+    fn image_usage(&self) -> vk::ImageUsageFlags {
+        // contents that live within one render pass are never loaded or stored, so the memory
+        // only needs to exist on tiled GPUs' on-chip memory
+        if self.single_pass
+            && (vk::ImageUsageFlags::COLOR_ATTACHMENT
+                | vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT
+                | vk::ImageUsageFlags::INPUT_ATTACHMENT)
+                .contains(self.usage)
+        {
+            self.usage | vk::ImageUsageFlags::TRANSIENT_ATTACHMENT
+        } else {
+            self.usage
+        }
+    }
+}
+
+/// A frame described as passes and the resources they read and write.
+///
+///     The graph is built every frame. On execution, passes are ordered so that every read sees
+///     the latest write declared before it and writes wait for the reads they overwrite,
+///     passes whose output is never used are culled, transient images get allocated (sharing
+///     memory where lifetimes don't overlap) and barriers and layout transitions are inserted
+///     between passes.
+pub struct RenderGraph<'a> {
+    images: Vec<GraphImage>,
+    buffers: Vec<GraphBuffer>,
+    passes: Vec<Pass<'a>>,
+    profiler: Option<&'a GpuProfilerResource>,
+}
+
+impl<'a> Default for RenderGraph<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> RenderGraph<'a> {
+    pub fn new() -> Self {
+        Self {
+            images: Vec::new(),
+            buffers: Vec::new(),
+            passes: Vec::new(),
+            profiler: None,
+        }
+    }
+
+    /// Measures the GPU time of every pass, reported under the pass' name.
+    pub fn profile_passes(&mut self, profiler: &'a GpuProfilerResource) {
+        self.profiler = Some(profiler);
+    }
+
+    pub fn import_image(&mut self, name: &str, image: ImportedImage) -> ImageHandle {
+        self.images.push(GraphImage {
+            name: name.to_owned(),
+            kind: GraphImageKind::Imported(image),
+        });
+        ImageHandle(self.images.len() - 1)
+    }
+
+    /// An image that only lives during the graph's execution.
+    pub fn create_image(&mut self, name: &str, desc: ImageDesc) -> ImageHandle {
+        self.images.push(GraphImage {
+            name: name.to_owned(),
+            kind: GraphImageKind::Transient(desc),
+        });
+        ImageHandle(self.images.len() - 1)
+    }
+
+    pub fn import_buffer(&mut self, name: &str, buffer: ImportedBuffer) -> BufferHandle {
+        self.buffers.push(GraphBuffer {
+            name: name.to_owned(),
+            imported: buffer,
+        });
+        BufferHandle(self.buffers.len() - 1)
+    }
+
+    /// Adds a pass. `setup` declares the resources the pass uses, `execute` records its commands
+    /// once the graph executes. Passes with attachments are recorded inside a render pass.
+    pub fn add_pass<S, E>(&mut self, name: &str, setup: S, execute: E)
+    where
+        S: for<'p> FnOnce(PassBuilder<'p, 'a>),
+        E: FnOnce(&PassContext) + 'a,
+    {
+        let mut pass = Pass::new(name);
+        setup(PassBuilder { pass: &mut pass });
+        pass.execute = Some(Box::new(execute));
+
+        debug_assert!(
+            Self::attachments_have_same_extent(&self.images, &pass),
+            "attachments of pass {} have different extents",
+            pass.name
+        );
+
+        self.passes.push(pass);
+    }
+
+    /// Compiles the graph and records it into the command buffer.
+    pub fn execute(
+        mut self,
+        context: &VkContext,
+        command_buffer: vk::CommandBuffer,
+        resources: &mut RenderGraphResources,
+    ) {
+        let order = self.sorted_passes();
+        let order = self.cull_passes(&order);
+
+        let lifetimes = self.image_lifetimes(&order);
+        let physical_images = self.allocate_images(context, resources, &order, &lifetimes);
+
+        let mut usages: HashMap<vk::Image, ResourceUsage> = HashMap::new();
+        for (image, physical) in self.images.iter().zip(&physical_images) {
+            if let (GraphImageKind::Imported(imported), Some((handle, _))) =
+                (&image.kind, physical)
+            {
+                usages.insert(*handle, imported.initial_usage);
+            }
+        }
+        let mut buffer_usages = self
+            .buffers
+            .iter()
+            .map(|buffer| buffer.imported.initial_usage)
+            .collect::<Vec<_>>();
+        let mut written = vec![false; self.images.len()];
+
+        for (position, &pass_index) in order.iter().enumerate() {
+            self.record_barriers(
+                context,
+                command_buffer,
+                &self.passes[pass_index],
+                &physical_images,
+                &mut usages,
+                &mut buffer_usages,
+                &written,
+            );
+
+            let pass = &mut self.passes[pass_index];
+            log::trace!("Render graph: executing pass {}", pass.name);
+            context.cmd_begin_label(command_buffer, &pass.name);
+            let _pass_scope = self
+                .profiler
+                .map(|profiler| profiler.scope(context, command_buffer, &pass.name));
+
+            let (render_pass, extent) = if pass.is_raster_pass() {
+                Self::begin_render_pass(
+                    context,
+                    command_buffer,
+                    resources,
+                    &self.images,
+                    pass,
+                    &physical_images,
+                    &written,
+                    |image| {
+                        self.images[image.0].is_imported()
+                            || lifetimes[image.0].map_or(false, |(_, last_use)| last_use > position)
+                    },
+                )
+            } else {
+                (vk::RenderPass::null(), vk::Extent2D::default())
+            };
+
+            if let Some(execute) = pass.execute.take() {
+                execute(&PassContext {
+                    context,
+                    command_buffer,
+                    render_pass,
+                    extent,
+                    images: &physical_images,
+                    profiler: self.profiler,
+                });
+            }
+
+            if pass.is_raster_pass() {
+                unsafe { context.device.cmd_end_render_pass(command_buffer) };
+            }
+            context.cmd_end_label(command_buffer);
+
+            pass.image_writes()
+                .for_each(|image| written[image.0] = true);
+        }
+
+        // hand imported images over in the layout they're used in next
+        let mut image_barriers = Vec::new();
+        let mut src_stage_mask = vk::PipelineStageFlags::empty();
+        let mut dst_stage_mask = vk::PipelineStageFlags::empty();
+        for (image, physical) in self.images.iter().zip(&physical_images) {
+            if let (GraphImageKind::Imported(imported), Some((handle, _))) =
+                (&image.kind, physical)
+            {
+                let current = usages[handle];
+                match imported.final_usage {
+                    Some(final_usage) if final_usage != current => {
+                        let (src, dst, barrier) = image_barrier(
+                            *handle,
+                            image.subresource_range(),
+                            (current, final_usage),
+                        );
+                        src_stage_mask |= src;
+                        dst_stage_mask |= dst;
+                        image_barriers.push(barrier);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        if !image_barriers.is_empty() {
+            PipelineBarrierBuilder::builder()
+                .src_stage_mask(src_stage_mask)
+                .dst_stage_mask(dst_stage_mask)
+                .image_memory_barriers(&image_barriers)
+                .build_exec(context, command_buffer);
+        }
+    }
+
+    fn attachments_have_same_extent(images: &[GraphImage], pass: &Pass) -> bool {
+        let mut extents = pass
+            .attachments()
+            .map(|attachment| images[attachment.image.0].extent());
+
+        match extents.next() {
+            Some(first) => extents.all(|extent| extent == first),
+            None => true,
+        }
+    }
+
+    fn pass_reads(pass: &Pass) -> Vec<ResourceId> {
+        pass.image_reads()
+            .map(|image| ResourceId::Image(image.0))
+            .chain(pass.buffer_reads().map(|buffer| ResourceId::Buffer(buffer.0)))
+            .collect()
+    }
+
+    fn pass_writes(pass: &Pass) -> Vec<ResourceId> {
+        pass.image_writes()
+            .map(|image| ResourceId::Image(image.0))
+            .chain(pass.buffer_writes().map(|buffer| ResourceId::Buffer(buffer.0)))
+            .collect()
+    }
+
+    fn is_imported(&self, resource: ResourceId) -> bool {
+        match resource {
+            ResourceId::Image(index) => self.images[index].is_imported(),
+            ResourceId::Buffer(_) => true,
+        }
+    }
+
+    fn resource_name(&self, resource: ResourceId) -> &str {
+        match resource {
+            ResourceId::Image(index) => &self.images[index].name,
+            ResourceId::Buffer(index) => &self.buffers[index].name,
+        }
+    }
+
+    /// Topological order of all passes. Every read sees the latest write declared before it,
+    /// and a write waits for the reads of the contents it overwrites. Independent passes keep
+    /// their declaration order.
+    fn sorted_passes(&self) -> Vec<usize> {
+        let pass_count = self.passes.len();
+
+        // latest writer of every resource and the passes that read what it wrote
+        let mut versions: HashMap<ResourceId, (Option<usize>, Vec<usize>)> = HashMap::new();
+        let mut edges: Vec<HashSet<usize>> = vec![HashSet::new(); pass_count];
+        for (pass_index, pass) in self.passes.iter().enumerate() {
+            let writes = Self::pass_writes(pass);
+
+            for resource in Self::pass_reads(pass) {
+                if writes.contains(&resource) {
+                    continue;
+                }
+                let (writer, readers) = versions.entry(resource).or_default();
+                if let Some(writer) = *writer {
+                    edges[writer].insert(pass_index);
+                }
+                readers.push(pass_index);
+            }
+
+            for resource in writes {
+                let (writer, readers) = versions.entry(resource).or_default();
+                if let Some(writer) = *writer {
+                    edges[writer].insert(pass_index);
+                }
+                // write after read
+                for reader in readers.drain(..) {
+                    edges[reader].insert(pass_index);
+                }
+                *writer = Some(pass_index);
+            }
+        }
+
+        let mut in_degree = vec![0; pass_count];
+        edges
+            .iter()
+            .flatten()
+            .for_each(|&to| in_degree[to] += 1);
+
+        let mut ready = (0..pass_count)
+            .filter(|&pass_index| in_degree[pass_index] == 0)
+            .map(Reverse)
+            .collect::<BinaryHeap<_>>();
+
+        let mut order = Vec::with_capacity(pass_count);
+        while let Some(Reverse(pass_index)) = ready.pop() {
+            order.push(pass_index);
+            for &to in &edges[pass_index] {
+                in_degree[to] -= 1;
+                if in_degree[to] == 0 {
+                    ready.push(Reverse(to));
+                }
+            }
+        }
+
+        // edges only point to later declared passes, so there are no cycles
+        debug_assert_eq!(order.len(), pass_count);
+
+        order
+    }
+
+    /// Drops passes that neither have side effects nor write anything that is imported or read
+    /// by a pass that is kept.
+    fn cull_passes(&self, order: &[usize]) -> Vec<usize> {
+        let mut needed: HashSet<ResourceId> = HashSet::new();
+        let mut kept = Vec::with_capacity(order.len());
+
+        for &pass_index in order.iter().rev() {
+            let pass = &self.passes[pass_index];
+            let keep = pass.has_side_effects
+                || Self::pass_writes(pass)
+                    .into_iter()
+                    .any(|resource| self.is_imported(resource) || needed.contains(&resource));
+
+            if keep {
+                kept.push(pass_index);
+                needed.extend(Self::pass_reads(pass));
+            } else {
+                log::trace!("Render graph: culled pass {}", pass.name);
+            }
+        }
+
+        kept.reverse();
+
+        if let Some(&resource) = needed
+            .iter()
+            .find(|&&resource| !self.is_imported(resource) && !self.is_written(&kept, resource))
+        {
+            panic!(
+                "render graph resource {} is read but never written",
+                self.resource_name(resource)
+            );
+        }
+
+        kept
+    }
+
+    fn is_written(&self, order: &[usize], resource: ResourceId) -> bool {
+        order
+            .iter()
+            .any(|&pass_index| Self::pass_writes(&self.passes[pass_index]).contains(&resource))
+    }
+
+    /// First and last position in `order` that uses each image.
+    fn image_lifetimes(&self, order: &[usize]) -> Vec<Option<(usize, usize)>> {
+        let mut lifetimes = vec![None; self.images.len()];
+
+        for (position, &pass_index) in order.iter().enumerate() {
+            for (image, _) in self.passes[pass_index].image_usages() {
+                let lifetime = lifetimes[image.0].get_or_insert((position, position));
+                lifetime.1 = position;
+            }
+        }
+
+        lifetimes
+    }
+
+    /// Physical image and view of every image handle, None for images no kept pass uses.
+    fn allocate_images(
+        &self,
+        context: &VkContext,
+        resources: &mut RenderGraphResources,
+        order: &[usize],
+        lifetimes: &[Option<(usize, usize)>],
+    ) -> Vec<Option<(vk::Image, vk::ImageView)>> {
+        let (slots, image_slots) = self.assign_image_slots(order, lifetimes);
+
+        let slot_images = slots
+            .iter()
+            .map(|slot| {
+                resources.acquire_image(
+                    context,
+                    TransientImageKey {
+                        desc: slot.desc,
+                        usage: slot.image_usage(),
+                    },
+                )
+            })
+            .collect::<Vec<_>>();
+
+        self.images
+            .iter()
+            .enumerate()
+            .map(|(index, image)| match &image.kind {
+                GraphImageKind::Imported(imported) => lifetimes[index]
+                    .map(|_| (imported.image, imported.image_view)),
+                GraphImageKind::Transient(_) => image_slots[index].map(|slot| slot_images[slot]),
+            })
+            .collect()
+    }
+
+    /// Physical image slots and the slot of every transient image that's used.
+    ///     Transient images with equal descriptions share a slot when their lifetimes don't
+    ///     overlap.
+    fn assign_image_slots(
+        &self,
+        order: &[usize],
+        lifetimes: &[Option<(usize, usize)>],
+    ) -> (Vec<PhysicalImageSlot>, Vec<Option<usize>>) {
+        let mut slots: Vec<PhysicalImageSlot> = Vec::new();
+        let mut image_slots = vec![None; self.images.len()];
+
+        let mut transients = self
+            .images
+            .iter()
+            .enumerate()
+            .filter_map(|(index, image)| match (&image.kind, lifetimes[index]) {
+                (GraphImageKind::Transient(desc), Some(lifetime)) => Some((index, *desc, lifetime)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        transients.sort_by_key(|&(_, _, (first_use, _))| first_use);
+
+        for (index, desc, (first_use, last_use)) in transients {
+            let slot = match slots
+                .iter()
+                .position(|slot| slot.desc == desc && slot.last_use < first_use)
+            {
+                Some(slot) => slot,
+                None => {
+                    slots.push(PhysicalImageSlot {
+                        desc,
+                        usage: vk::ImageUsageFlags::empty(),
+                        last_use,
+                        single_pass: true,
+                    });
+                    slots.len() - 1
+                }
+            };
+
+            slots[slot].last_use = last_use;
+            slots[slot].single_pass &= first_use == last_use;
+            image_slots[index] = Some(slot);
+        }
+
+        for &pass_index in order {
+            for (image, usage) in self.passes[pass_index].image_usages() {
+                if let Some(slot) = image_slots[image.0] {
+                    slots[slot].usage |= usage.image_usage_flags();
+                }
+            }
+        }
+
+        (slots, image_slots)
+    }
+
+    /// Transitions everything the pass uses from its previous usage.
+    #[allow(clippy::too_many_arguments)]
+    fn record_barriers(
+        &self,
+        context: &VkContext,
+        command_buffer: vk::CommandBuffer,
+        pass: &Pass,
+        physical_images: &[Option<(vk::Image, vk::ImageView)>],
+        usages: &mut HashMap<vk::Image, ResourceUsage>,
+        buffer_usages: &mut [ResourceUsage],
+        written: &[bool],
+    ) {
+        let mut src_stage_mask = vk::PipelineStageFlags::empty();
+        let mut dst_stage_mask = vk::PipelineStageFlags::empty();
+        let mut image_barriers = Vec::new();
+        let mut buffer_barriers = Vec::new();
+
+        for (image, next) in pass.image_usages() {
+            let (handle, _) = physical_images[image.0].unwrap();
+            let previous = usages
+                .get(&handle)
+                .copied()
+                .unwrap_or(ResourceUsage::Undefined);
+
+            if previous == next && !next.is_write() {
+                continue;
+            }
+
+            let (src, dst, mut barrier) = image_barrier(
+                handle,
+                self.images[image.0].subresource_range(),
+                (previous, next),
+            );
+
+            // the previous contents belong to another image sharing the memory
+            if !self.images[image.0].is_imported() && !written[image.0] {
+                barrier.old_layout = vk::ImageLayout::UNDEFINED;
+            }
+
+            src_stage_mask |= src;
+            dst_stage_mask |= dst;
+            image_barriers.push(barrier);
+            usages.insert(handle, next);
+        }
+
+        for &(buffer, next) in &pass.buffer_accesses {
+            let previous = buffer_usages[buffer.0];
+
+            if let Some((src, dst, barrier)) =
+                buffer_barrier(self.buffers[buffer.0].imported.buffer, (previous, next))
+            {
+                src_stage_mask |= src;
+                dst_stage_mask |= dst;
+                buffer_barriers.push(barrier);
+            }
+            buffer_usages[buffer.0] = next;
+        }
+
+        if image_barriers.is_empty() && buffer_barriers.is_empty() {
+            return;
+        }
+
+        PipelineBarrierBuilder::builder()
+            .src_stage_mask(src_stage_mask)
+            .dst_stage_mask(dst_stage_mask)
+            .image_memory_barriers(&image_barriers)
+            .buffer_memory_barriers(&buffer_barriers)
+            .build_exec(context, command_buffer);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn begin_render_pass(
+        context: &VkContext,
+        command_buffer: vk::CommandBuffer,
+        resources: &mut RenderGraphResources,
+        images: &[GraphImage],
+        pass: &Pass,
+        physical_images: &[Option<(vk::Image, vk::ImageView)>],
+        written: &[bool],
+        is_used_later: impl Fn(ImageHandle) -> bool,
+    ) -> (vk::RenderPass, vk::Extent2D) {
+        let attachment_key = |attachment: &Attachment| {
+            let image = &images[attachment.image.0];
+            let has_contents = image.is_imported() || written[attachment.image.0];
+
+            AttachmentKey {
+                format: image.format(),
+                samples: image.samples(),
+                load_op: match attachment.load {
+                    AttachmentLoad::Clear(_) => vk::AttachmentLoadOp::CLEAR,
+                    AttachmentLoad::Load if has_contents => vk::AttachmentLoadOp::LOAD,
+                    _ => vk::AttachmentLoadOp::DONT_CARE,
+                },
+                store_op: if is_used_later(attachment.image) {
+                    vk::AttachmentStoreOp::STORE
+                } else {
+                    vk::AttachmentStoreOp::DONT_CARE
+                },
+                layout: attachment.usage.image_layout(),
+            }
+        };
+
+        let render_pass = resources.render_pass(
+            context,
+            &RenderPassKey {
+                color_attachments: pass.color_attachments.iter().map(attachment_key).collect(),
+                depth_attachment: pass.depth_attachment.as_ref().map(attachment_key),
+                resolve_attachments: pass.resolve_attachments.iter().map(attachment_key).collect(),
+            },
+        );
+
+        let extent = pass
+            .attachments()
+            .next()
+            .map(|attachment| images[attachment.image.0].extent())
+            .unwrap();
+
+        let image_views = pass
+            .attachments()
+            .map(|attachment| physical_images[attachment.image.0].unwrap().1)
+            .collect::<Vec<_>>();
+        let framebuffer = resources.framebuffer(context, render_pass, &image_views, extent);
+
+        let clear_values = pass
+            .attachments()
+            .map(|attachment| match attachment.load {
+                AttachmentLoad::Clear(clear_value) => clear_value,
+                _ => vk::ClearValue::default(),
+            })
+            .collect::<Vec<_>>();
+
+        let render_pass_begin_info = vk::RenderPassBeginInfo::builder()
+            .render_pass(render_pass)
+            .framebuffer(framebuffer)
+            .render_area(vk::Rect2D {
+                offset: vk::Offset2D { x: 0, y: 0 },
+                extent,
+            })
+            .clear_values(&clear_values);
+
+        unsafe {
+            context.device.cmd_begin_render_pass(
+                command_buffer,
+                &render_pass_begin_info,
+                vk::SubpassContents::INLINE,
+            );
+        }
+
+        (render_pass, extent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXTENT: vk::Extent2D = vk::Extent2D {
+        width: 4,
+        height: 4,
+    };
+
+    fn color_desc() -> ImageDesc {
+        ImageDesc::new_2d(vk::Format::R8G8B8A8_UNORM, EXTENT)
+    }
+
+    fn imported_image() -> ImportedImage {
+        ImportedImage {
+            image: vk::Image::null(),
+            image_view: vk::ImageView::null(),
+            format: vk::Format::R8G8B8A8_UNORM,
+            extent: EXTENT,
+            samples: vk::SampleCountFlags::TYPE_1,
+            initial_usage: ResourceUsage::Undefined,
+            final_usage: None,
+        }
+    }
+
+    fn imported_buffer() -> ImportedBuffer {
+        ImportedBuffer {
+            buffer: vk::Buffer::null(),
+            initial_usage: ResourceUsage::Undefined,
+        }
+    }
+
+    fn clear() -> AttachmentLoad {
+        AttachmentLoad::Clear(vk::ClearValue::default())
+    }
+
+    /// The passes `execute` records, in order.
+    fn compiled_order(graph: &RenderGraph) -> Vec<usize> {
+        graph.cull_passes(&graph.sorted_passes())
+    }
+
+    #[test]
+    fn reads_between_writes_see_the_earlier_write() {
+        let mut graph = RenderGraph::new();
+        let target = graph.import_image("target", imported_image());
+        let history = graph.import_buffer("history", imported_buffer());
+
+        graph.add_pass(
+            "write",
+            |pass| {
+                pass.write_buffer(history, ResourceUsage::TransferWrite);
+            },
+            |_| {},
+        );
+        graph.add_pass(
+            "read",
+            |pass| {
+                pass.read_buffer(history, ResourceUsage::TransferRead)
+                    .color_attachment(target, clear());
+            },
+            |_| {},
+        );
+        graph.add_pass(
+            "overwrite",
+            |pass| {
+                pass.write_buffer(history, ResourceUsage::TransferWrite);
+            },
+            |_| {},
+        );
+
+        assert_eq!(graph.sorted_passes(), [0, 1, 2]);
+    }
+
+    #[test]
+    fn read_then_write_chains_dont_form_cycles() {
+        let mut graph = RenderGraph::new();
+        let a = graph.import_buffer("a", imported_buffer());
+        let b = graph.import_buffer("b", imported_buffer());
+
+        graph.add_pass(
+            "write a",
+            |pass| {
+                pass.write_buffer(a, ResourceUsage::TransferWrite);
+            },
+            |_| {},
+        );
+        graph.add_pass(
+            "read b, write a",
+            |pass| {
+                pass.read_buffer(b, ResourceUsage::TransferRead)
+                    .write_buffer(a, ResourceUsage::TransferWrite);
+            },
+            |_| {},
+        );
+        graph.add_pass(
+            "read a, write b",
+            |pass| {
+                pass.read_buffer(a, ResourceUsage::TransferRead)
+                    .write_buffer(b, ResourceUsage::TransferWrite);
+            },
+            |_| {},
+        );
+
+        assert_eq!(graph.sorted_passes(), [0, 1, 2]);
+    }
+
+    #[test]
+    fn passes_whose_output_is_unused_are_culled() {
+        let mut graph = RenderGraph::new();
+        let target = graph.import_image("target", imported_image());
+        let intermediate = graph.create_image("intermediate", color_desc());
+        let unused = graph.create_image("unused", color_desc());
+
+        graph.add_pass(
+            "feeds unused",
+            |pass| {
+                pass.color_attachment(intermediate, clear());
+            },
+            |_| {},
+        );
+        graph.add_pass(
+            "unused",
+            |pass| {
+                pass.read_image(intermediate, ResourceUsage::FragmentShaderRead)
+                    .color_attachment(unused, clear());
+            },
+            |_| {},
+        );
+        graph.add_pass(
+            "side effects",
+            |pass| {
+                pass.has_side_effects();
+            },
+            |_| {},
+        );
+        graph.add_pass(
+            "present",
+            |pass| {
+                pass.color_attachment(target, clear());
+            },
+            |_| {},
+        );
+
+        assert_eq!(compiled_order(&graph), [2, 3]);
+    }
+
+    #[test]
+    #[should_panic(expected = "render graph resource never written is read but never written")]
+    fn reading_an_image_nothing_writes_panics() {
+        let mut graph = RenderGraph::new();
+        let target = graph.import_image("target", imported_image());
+        let never_written = graph.create_image("never written", color_desc());
+
+        graph.add_pass(
+            "read",
+            |pass| {
+                pass.read_image(never_written, ResourceUsage::FragmentShaderRead)
+                    .color_attachment(target, clear());
+            },
+            |_| {},
+        );
+
+        compiled_order(&graph);
+    }
+
+    #[test]
+    fn transients_with_disjoint_lifetimes_share_a_slot() {
+        let mut graph = RenderGraph::new();
+        let target = graph.import_image("target", imported_image());
+        let first = graph.create_image("first", color_desc());
+        let second = graph.create_image("second", color_desc());
+
+        for image in [first, second] {
+            graph.add_pass(
+                "write",
+                |pass| {
+                    pass.color_attachment(image, clear());
+                },
+                |_| {},
+            );
+            graph.add_pass(
+                "read",
+                |pass| {
+                    pass.read_image(image, ResourceUsage::FragmentShaderRead)
+                        .color_attachment(target, AttachmentLoad::Load);
+                },
+                |_| {},
+            );
+        }
+
+        let order = compiled_order(&graph);
+        let lifetimes = graph.image_lifetimes(&order);
+        let (slots, image_slots) = graph.assign_image_slots(&order, &lifetimes);
+
+        assert_eq!(order, [0, 1, 2, 3]);
+        assert_eq!(slots.len(), 1);
+        assert_eq!(image_slots[first.0], Some(0));
+        assert_eq!(image_slots[second.0], Some(0));
+        assert_eq!(
+            slots[0].image_usage(),
+            vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::SAMPLED
+        );
+    }
+
+    #[test]
+    fn transients_with_overlapping_lifetimes_get_their_own_slots() {
+        let mut graph = RenderGraph::new();
+        let target = graph.import_image("target", imported_image());
+        let first = graph.create_image("first", color_desc());
+        let second = graph.create_image("second", color_desc());
+
+        for image in [first, second] {
+            graph.add_pass(
+                "write",
+                |pass| {
+                    pass.color_attachment(image, clear());
+                },
+                |_| {},
+            );
+        }
+        graph.add_pass(
+            "read both",
+            |pass| {
+                pass.read_image(first, ResourceUsage::FragmentShaderRead)
+                    .read_image(second, ResourceUsage::FragmentShaderRead)
+                    .color_attachment(target, clear());
+            },
+            |_| {},
+        );
+
+        let order = compiled_order(&graph);
+        let lifetimes = graph.image_lifetimes(&order);
+        let (slots, image_slots) = graph.assign_image_slots(&order, &lifetimes);
+
+        assert_eq!(slots.len(), 2);
+        assert_ne!(image_slots[first.0], image_slots[second.0]);
+    }
+
+    #[test]
+    fn attachments_living_within_one_pass_are_transient() {
+        let mut graph = RenderGraph::new();
+        let target = graph.import_image("target", imported_image());
+        let multisampled = graph.create_image(
+            "multisampled",
+            color_desc().samples(vk::SampleCountFlags::TYPE_4),
+        );
+
+        graph.add_pass(
+            "resolve",
+            |pass| {
+                pass.color_attachment(multisampled, clear())
+                    .resolve_attachment(target);
+            },
+            |_| {},
+        );
+
+        let order = compiled_order(&graph);
+        let lifetimes = graph.image_lifetimes(&order);
+        let (slots, _) = graph.assign_image_slots(&order, &lifetimes);
+
+        assert_eq!(
+            slots[0].image_usage(),
+            vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSIENT_ATTACHMENT
+        );
+    }
+}
@@ -0,0 +1,11 @@
+mod resource;
+pub use resource::*;
+
+mod pass;
+pub use pass::*;
+
+mod graph;
+pub use graph::*;
+
+mod cache;
+pub use cache::*;
@@ -0,0 +1,223 @@
+use crate::renderer::profiling::{GpuProfileScope, GpuProfilerResource};
+use crate::renderer::render_graph::{BufferHandle, ImageHandle};
+use crate::renderer::sync::ResourceUsage;
+use crate::renderer::vk_types::VkContext;
+use ash::vk;
+
+/// What happens to the previous contents of an attachment when a pass begins.
+#[derive(Clone, Copy)]
+pub enum AttachmentLoad {
+    Clear(vk::ClearValue),
+    /// Keeps what earlier passes wrote.
+    Load,
+    DontCare,
+}
+
+pub(super) struct Attachment {
+    pub image: ImageHandle,
+    pub load: AttachmentLoad,
+    pub usage: ResourceUsage,
+}
+
+pub(super) struct Pass<'a> {
+    pub name: String,
+    pub color_attachments: Vec<Attachment>,
+    pub depth_attachment: Option<Attachment>,
+    /// single sampled images the color attachment with the same index is resolved into
+    pub resolve_attachments: Vec<Attachment>,
+    pub image_accesses: Vec<(ImageHandle, ResourceUsage)>,
+    pub buffer_accesses: Vec<(BufferHandle, ResourceUsage)>,
+    /// never culled, even if nothing reads what it writes
+    pub has_side_effects: bool,
+    pub execute: Option<Box<dyn FnOnce(&PassContext) + 'a>>,
+}
+impl<'a> Pass<'a> {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            color_attachments: Vec::new(),
+            depth_attachment: None,
+            resolve_attachments: Vec::new(),
+            image_accesses: Vec::new(),
+            buffer_accesses: Vec::new(),
+            has_side_effects: false,
+            execute: None,
+        }
+    }
+
+    pub fn is_raster_pass(&self) -> bool {
+        !self.color_attachments.is_empty() || self.depth_attachment.is_some()
+    }
+
+    /// Color, depth and then resolve attachments, the order they're in in the framebuffer.
+    pub fn attachments(&self) -> impl Iterator<Item = &Attachment> {
+        self.color_attachments
+            .iter()
+            .chain(self.depth_attachment.iter())
+            .chain(self.resolve_attachments.iter())
+    }
+
+    /// Every image the pass touches, attachments included.
+    pub fn image_usages(&self) -> impl Iterator<Item = (ImageHandle, ResourceUsage)> + '_ {
+        self.attachments()
+            .map(|attachment| (attachment.image, attachment.usage))
+            .chain(self.image_accesses.iter().copied())
+    }
+
+    /// Images the pass needs the previous contents of.
+    pub fn image_reads(&self) -> impl Iterator<Item = ImageHandle> + '_ {
+        self.attachments()
+            .filter(|attachment| {
+                matches!(attachment.load, AttachmentLoad::Load) || !attachment.usage.is_write()
+            })
+            .map(|attachment| attachment.image)
+            .chain(
+                self.image_accesses
+                    .iter()
+                    .filter(|(_, usage)| !usage.is_write())
+                    .map(|&(image, _)| image),
+            )
+    }
+
+    pub fn image_writes(&self) -> impl Iterator<Item = ImageHandle> + '_ {
+        self.image_usages()
+            .filter(|(_, usage)| usage.is_write())
+            .map(|(image, _)| image)
+    }
+
+    pub fn buffer_reads(&self) -> impl Iterator<Item = BufferHandle> + '_ {
+        self.buffer_accesses
+            .iter()
+            .filter(|(_, usage)| !usage.is_write())
+            .map(|&(buffer, _)| buffer)
+    }
+
+    pub fn buffer_writes(&self) -> impl Iterator<Item = BufferHandle> + '_ {
+        self.buffer_accesses
+            .iter()
+            .filter(|(_, usage)| usage.is_write())
+            .map(|&(buffer, _)| buffer)
+    }
+}
+
+/// Declares which resources a pass reads and writes.
+pub struct PassBuilder<'p, 'a> {
+    pub(super) pass: &'p mut Pass<'a>,
+}
+impl<'p, 'a> PassBuilder<'p, 'a> {
+    pub fn color_attachment(self, image: ImageHandle, load: AttachmentLoad) -> Self {
+        self.pass.color_attachments.push(Attachment {
+            image,
+            load,
+            usage: ResourceUsage::ColorAttachmentWrite,
+        });
+        self
+    }
+
+    pub fn depth_attachment(self, image: ImageHandle, load: AttachmentLoad) -> Self {
+        self.pass.depth_attachment = Some(Attachment {
+            image,
+            load,
+            usage: ResourceUsage::DepthAttachmentWrite,
+        });
+        self
+    }
+
+    /// Resolves the multisampled color attachment declared at the same index into `image` at
+    /// the end of the pass.
+    pub fn resolve_attachment(self, image: ImageHandle) -> Self {
+        debug_assert!(
+            self.pass.resolve_attachments.len() < self.pass.color_attachments.len(),
+            "resolve attachment without a color attachment to resolve"
+        );
+        self.pass.resolve_attachments.push(Attachment {
+            image,
+            load: AttachmentLoad::DontCare,
+            usage: ResourceUsage::ColorAttachmentWrite,
+        });
+        self
+    }
+
+    /// Depth testing against an earlier pass' depth, without writing to it.
+    pub fn depth_attachment_read_only(self, image: ImageHandle) -> Self {
+        self.pass.depth_attachment = Some(Attachment {
+            image,
+            load: AttachmentLoad::Load,
+            usage: ResourceUsage::DepthAttachmentRead,
+        });
+        self
+    }
+
+    pub fn read_image(self, image: ImageHandle, usage: ResourceUsage) -> Self {
+        debug_assert!(!usage.is_write(), "{:?} isn't a read", usage);
+        self.pass.image_accesses.push((image, usage));
+        self
+    }
+
+    pub fn write_image(self, image: ImageHandle, usage: ResourceUsage) -> Self {
+        debug_assert!(usage.is_write(), "{:?} isn't a write", usage);
+        self.pass.image_accesses.push((image, usage));
+        self
+    }
+
+    pub fn read_buffer(self, buffer: BufferHandle, usage: ResourceUsage) -> Self {
+        debug_assert!(!usage.is_write(), "{:?} isn't a read", usage);
+        self.pass.buffer_accesses.push((buffer, usage));
+        self
+    }
+
+    pub fn write_buffer(self, buffer: BufferHandle, usage: ResourceUsage) -> Self {
+        debug_assert!(usage.is_write(), "{:?} isn't a write", usage);
+        self.pass.buffer_accesses.push((buffer, usage));
+        self
+    }
+
+    /// Keeps the pass even if nothing in the graph reads its output.
+    pub fn has_side_effects(self) -> Self {
+        self.pass.has_side_effects = true;
+        self
+    }
+}
+
+/// Everything a pass needs to record its commands.
+pub struct PassContext<'c> {
+    pub context: &'c VkContext,
+    pub command_buffer: vk::CommandBuffer,
+    /// render pass the pass' commands are recorded in, null if the pass has no attachments
+    pub render_pass: vk::RenderPass,
+    pub extent: vk::Extent2D,
+    pub(super) images: &'c [Option<(vk::Image, vk::ImageView)>],
+    pub(super) profiler: Option<&'c GpuProfilerResource>,
+}
+impl<'c> PassContext<'c> {
+    pub fn image(&self, handle: ImageHandle) -> vk::Image {
+        self.physical_image(handle).0
+    }
+
+    pub fn image_view(&self, handle: ImageHandle) -> vk::ImageView {
+        self.physical_image(handle).1
+    }
+
+    /// Measures the GPU time of the commands recorded until the scope is dropped, nested in the
+    /// pass' own measurement.
+    pub fn gpu_profile_scope(&self, name: &str) -> GpuProfileScope<'c> {
+        match self.profiler {
+            Some(profiler) => profiler.scope(self.context, self.command_buffer, name),
+            None => GpuProfileScope::disabled(),
+        }
+    }
+
+    /// Counts the samples of the draws recorded until the scope is dropped that pass the depth
+    /// and stencil tests, reported under `name`. Disabled unless the profiling settings enable
+    /// occlusion queries.
+    pub fn occlusion_query(&self, name: &str) -> GpuProfileScope<'c> {
+        match self.profiler {
+            Some(profiler) => profiler.occlusion_query(self.context, self.command_buffer, name),
+            None => GpuProfileScope::disabled(),
+        }
+    }
+
+    fn physical_image(&self, handle: ImageHandle) -> (vk::Image, vk::ImageView) {
+        self.images[handle.0].expect("image isn't used by any pass that was kept")
+    }
+}
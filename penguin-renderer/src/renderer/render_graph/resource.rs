@@ -0,0 +1,137 @@
+use crate::renderer::memory::util::aspect_mask_from_format;
+use crate::renderer::sync::ResourceUsage;
+use ash::vk;
+
+/// Handle to an image declared in a render graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ImageHandle(pub(super) usize);
+
+/// Handle to a buffer declared in a render graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BufferHandle(pub(super) usize);
+
+/// Description of an image the graph allocates itself. Transient images with identical
+/// descriptions and non-overlapping lifetimes within a frame share memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ImageDesc {
+    pub format: vk::Format,
+    pub width: u32,
+    pub height: u32,
+    pub samples: vk::SampleCountFlags,
+    pub array_layers: u32,
+}
+impl ImageDesc {
+    pub fn new_2d(format: vk::Format, extent: vk::Extent2D) -> Self {
+        Self {
+            format,
+            width: extent.width,
+            height: extent.height,
+            samples: vk::SampleCountFlags::TYPE_1,
+            array_layers: 1,
+        }
+    }
+
+    pub fn samples(mut self, samples: vk::SampleCountFlags) -> Self {
+        self.samples = samples;
+        self
+    }
+
+    pub fn array_layers(mut self, array_layers: u32) -> Self {
+        self.array_layers = array_layers;
+        self
+    }
+
+    pub fn extent(&self) -> vk::Extent2D {
+        vk::Extent2D {
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    pub fn aspect_mask(&self) -> vk::ImageAspectFlags {
+        aspect_mask_from_format(self.format)
+    }
+}
+
+/// An image owned outside of the graph, like a swapchain image.
+#[derive(Debug, Clone, Copy)]
+pub struct ImportedImage {
+    pub image: vk::Image,
+    pub image_view: vk::ImageView,
+    pub format: vk::Format,
+    pub extent: vk::Extent2D,
+    pub samples: vk::SampleCountFlags,
+    /// how the image was last used before the graph executes
+    pub initial_usage: ResourceUsage,
+    /// how the image will be used after the graph executes, if it has to be transitioned
+    pub final_usage: Option<ResourceUsage>,
+}
+
+/// A buffer owned outside of the graph.
+#[derive(Debug, Clone, Copy)]
+pub struct ImportedBuffer {
+    pub buffer: vk::Buffer,
+    /// how the buffer was last used before the graph executes
+    pub initial_usage: ResourceUsage,
+}
+
+#[derive(Debug)]
+pub(super) enum GraphImageKind {
+    Imported(ImportedImage),
+    Transient(ImageDesc),
+}
+
+#[derive(Debug)]
+pub(super) struct GraphImage {
+    pub name: String,
+    pub kind: GraphImageKind,
+}
+impl GraphImage {
+    pub fn format(&self) -> vk::Format {
+        match &self.kind {
+            GraphImageKind::Imported(imported) => imported.format,
+            GraphImageKind::Transient(desc) => desc.format,
+        }
+    }
+
+    pub fn samples(&self) -> vk::SampleCountFlags {
+        match &self.kind {
+            GraphImageKind::Imported(imported) => imported.samples,
+            GraphImageKind::Transient(desc) => desc.samples,
+        }
+    }
+
+    pub fn extent(&self) -> vk::Extent2D {
+        match &self.kind {
+            GraphImageKind::Imported(imported) => imported.extent,
+            GraphImageKind::Transient(desc) => desc.extent(),
+        }
+    }
+
+    pub fn layer_count(&self) -> u32 {
+        match &self.kind {
+            GraphImageKind::Imported(_) => 1,
+            GraphImageKind::Transient(desc) => desc.array_layers,
+        }
+    }
+
+    pub fn is_imported(&self) -> bool {
+        matches!(self.kind, GraphImageKind::Imported(_))
+    }
+
+    pub fn subresource_range(&self) -> vk::ImageSubresourceRange {
+        vk::ImageSubresourceRange {
+            aspect_mask: aspect_mask_from_format(self.format()),
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: self.layer_count(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(super) struct GraphBuffer {
+    pub name: String,
+    pub imported: ImportedBuffer,
+}
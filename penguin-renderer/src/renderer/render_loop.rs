@@ -4,7 +4,13 @@ use crate::renderer::frame_data::{
     FrameData,
     FrameDataContainer
 };
-use crate::renderer::vk_types::{BindDescriptorSetsInfo, DescriptorSetsResource, FrameBuffers, RenderPass, Swapchain, VkContext};
+use crate::renderer::post_process::PostProcessResource;
+use crate::renderer::profiling::{GpuProfilerResource, GpuStatsResource};
+use crate::renderer::render_graph::{AttachmentLoad, ImageDesc, ImportedImage, PassContext, RenderGraph, RenderGraphResources};
+use crate::renderer::lights::LightsResource;
+use crate::renderer::shadows::ShadowResource;
+use crate::renderer::sync::{GraphicsSubmission, ResourceUsage, TimelinePoint};
+use crate::renderer::vk_types::{BindDescriptorSetsInfo, DescriptorSetsResource, RenderPass, Swapchain, VkContext};
 use crate::renderer::resources::*;
 
 
@@ -13,66 +19,164 @@ pub fn render(
     context: &VkContext,
     frame_datas: &mut FrameDataContainer,
     swapchain: &Swapchain,
-    frame_buffers: &FrameBuffers,
     render_pass: &RenderPass,
+    uniform_ring_buffer: &mut UniformRingBuffer,
+    render_graph_resources: &mut RenderGraphResources,
 
     // things that draw need
     #[resource] window: &penguin_app::window::Window, // for aspect ratio
     #[resource] materials: &MaterialsResource,
-    #[resource] meshes: &MeshesResource,
     #[resource] render_objects: &RenderObjectsResource,
+    #[resource] lights: &LightsResource,
     #[resource] descriptor_sets: &DescriptorSetsResource,
+    #[resource] post_process: &mut PostProcessResource,
+    #[resource] shadows: &ShadowResource,
+    #[resource] gpu_profiler: &mut GpuProfilerResource,
+    #[resource] gpu_stats: &mut GpuStatsResource,
 ) {
     frame_datas.increment_frame();
+
+    // Wait for previous command buffer for this frame data.
+    //  In other words, wait until the GPU finished rendering the previous frame
+    //  associated with this frame data, before reusing anything it owns.
+    let frame_data = frame_datas.get_current_mut();
+    context.wait_for_timeline(frame_data.render_complete, std::time::Duration::MAX);
+    frame_data.descriptor_allocator.reset_pools(context);
+    uniform_ring_buffer.begin_frame();
+    render_graph_resources.begin_frame(context, frame_data.frame_index as _);
+    post_process.begin_frame(frame_data.frame_index as _);
+    // the graph's passes borrow the effects until the frame is recorded
+    let post_process: &PostProcessResource = post_process;
+
     let frame_data: &FrameData = frame_datas.get_current();
 
-    let (swapchain_image_index, frame_buffer) = GetNextFrameBuffer {
+    let camera = SceneCamera::new(
+        aspect_ratio(window.dimensions.width, window.dimensions.height),
+        70.0_f32.to_radians(),
+        render_pass.reversed_z,
+    );
+    let shadow_data = shadows.gpu_data(camera.view, camera.frustum_projection, (camera.z_near, camera.z_far));
+    let shadow_offset = uniform_ring_buffer.push_uniform(&shadow_data);
+
+    let swapchain_image_index = AcquireSwapchainImage {
         swapchain,
-        frame_buffers,
         signal_semaphore: frame_data.presenting_complete_semaphore,
         signal_fence: vk::Fence::null(),
     }.exec();
 
     RecordCommandBuffer {
         command_buffer: frame_data.command_buffer,
-        // Wait for previous command buffer for this frame data.
-        //  In other words, wait until the GPU finished rendering the previous frame
-        //  associated with this frame data.
-        wait_for_fence: frame_data.render_complete_fence,
         reset_before_begin_flags: vk::CommandBufferResetFlags::empty(),
         usage_flags: vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT,
     }.exec(context,
            ||
                {
-                   RecordRenderPass {
-                       command_buffer: frame_data.command_buffer,
-                       render_pass,
-                       frame_buffer,
-                       swapchain_extent: swapchain.extent,
-                   }.exec(context, ||
-                       {
+                   // this frame data's previous timings are complete, the frame waited for them
+                   gpu_profiler.begin_frame(context, frame_data.command_buffer, frame_data.frame_index as _, gpu_stats);
+                   let gpu_profiler: &GpuProfilerResource = gpu_profiler;
+
+                   let mut graph = RenderGraph::new();
+                   graph.profile_passes(gpu_profiler);
+
+                   let swapchain_image = graph.import_image("swapchain", ImportedImage {
+                       image: swapchain.images[swapchain_image_index as usize],
+                       image_view: swapchain.image_views[swapchain_image_index as usize],
+                       format: swapchain.format,
+                       extent: swapchain.extent,
+                       samples: vk::SampleCountFlags::TYPE_1,
+                       initial_usage: ResourceUsage::SwapchainAcquire,
+                       final_usage: Some(ResourceUsage::Present),
+                   });
+                   // the scene is rendered in HDR and post processed into the swapchain image
+                   let hdr_color_image = graph.create_image(
+                       "hdr color",
+                       ImageDesc::new_2d(crate::config::HDR_COLOR_FORMAT, swapchain.extent),
+                   );
+                   let shadow_map = graph.create_image("shadow map", shadows.shadow_map_desc());
+                   let depth_image = graph.create_image(
+                       "depth",
+                       ImageDesc::new_2d(render_pass.depth_format, swapchain.extent)
+                           .samples(render_pass.samples),
+                   );
+                   // with MSAA, rendering happens in a multisampled image that gets resolved
+                   // into the HDR image
+                   let msaa_color_image = render_pass.is_multisampled().then(|| {
+                       graph.create_image(
+                           "msaa color",
+                           ImageDesc::new_2d(crate::config::HDR_COLOR_FORMAT, swapchain.extent)
+                               .samples(render_pass.samples),
+                       )
+                   });
+
+                   graph.add_pass(
+                       "shadows",
+                       |pass| {
+                           pass.depth_attachment(shadow_map, AttachmentLoad::Clear(vk::ClearValue {
+                               depth_stencil: vk::ClearDepthStencilValue { depth: 1.0, stencil: 0 },
+                           }));
+                       },
+                       |pass_context| {
+                           shadows.draw(
+                               pass_context,
+                               frame_data.object_descriptor_set.handle,
+                               &shadow_data,
+                               render_objects,
+                           );
+                       },
+                   );
+
+                   graph.add_pass(
+                       "forward",
+                       |pass| {
+                           let color_clear = AttachmentLoad::Clear(vk::ClearValue {
+                               color: vk::ClearColorValue { float32: [0.0, 0., 0., 1.] },
+                           });
+                           let pass = match msaa_color_image {
+                               Some(msaa_color_image) => pass
+                                   .color_attachment(msaa_color_image, color_clear)
+                                   .resolve_attachment(hdr_color_image),
+                               None => pass.color_attachment(hdr_color_image, color_clear),
+                           };
+                           pass.depth_attachment(depth_image, AttachmentLoad::Clear(render_pass.depth_clear_value()))
+                               .read_image(shadow_map, ResourceUsage::FragmentShaderRead);
+                       },
+                       |pass_context| {
+                           let shadow_set = shadows.write_shadow_map(
+                               pass_context.context,
+                               frame_data.frame_index as _,
+                               pass_context.image_view(shadow_map),
+                           );
                            RecordDrawCommands {
                                params: DrawParams {
-                                   aspect_ratio: aspect_ratio(window.dimensions.width, window.dimensions.height),
-                                   fov_y: 70.0_f32.to_radians(),
+                                   camera: &camera,
+                                   shadow_set,
+                                   shadow_offset,
                                    frame_data,
                                    frame_count: frame_datas.frame_count(),
                                },
                                resources: DrawResourceAccess {
                                    materials,
-                                   meshes,
                                    descriptor_sets,
                                    render_objects,
+                                   uniform_ring_buffer,
+                                   lights,
                                }
-                           }.exec(context);
-                       });
+                           }.exec(pass_context);
+                       },
+                   );
+
+                   post_process.add_passes(&mut graph, hdr_color_image, swapchain_image, swapchain.extent);
+
+                   graph.execute(context, frame_data.command_buffer, render_graph_resources);
                }
     );
+    uniform_ring_buffer.end_frame();
 
-    SubmitCommandBufferToGraphicsQueue {
+    let render_complete = SubmitCommandBufferToGraphicsQueue {
         command_buffer: frame_data.command_buffer,
         pipeline_wait_stages: &[vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT],
-        wait_for_fence: frame_data.render_complete_fence,
+        // uploads are waited for when they are submitted
+        wait_for: &[],
         wait_for_semaphore: frame_data.presenting_complete_semaphore,
         // after this command buffer is complete, signal the rendering complete semaphore
         signal_semaphore: frame_data.rendering_complete_semaphore,
@@ -84,6 +188,11 @@ pub fn render(
         wait_for_semaphore: frame_data.rendering_complete_semaphore,
         swapchain_image_index,
     }.exec(context);
+
+    frame_datas.get_current_mut().render_complete = render_complete;
+
+    // panics here rather than in the validation callback, which runs inside the driver
+    context.validation_report().check();
 }
 
 
@@ -91,28 +200,24 @@ pub fn render(
 
 
 
-struct GetNextFrameBuffer<'a> {
+struct AcquireSwapchainImage<'a> {
     swapchain: &'a Swapchain,
-    frame_buffers: &'a FrameBuffers,
     signal_semaphore: vk::Semaphore,
     signal_fence: vk::Fence,
 }
-impl<'a> GetNextFrameBuffer<'a> {
-    fn exec(self) -> (u32, vk::Framebuffer) {
+impl<'a> AcquireSwapchainImage<'a> {
+    fn exec(self) -> u32 {
         // find next image
-        let swapchain_image_index = self.swapchain.acquire_next_swapchain_image(
+        self.swapchain.acquire_next_swapchain_image(
             self.signal_semaphore,
             self.signal_fence,
             std::time::Duration::from_secs(1),
-        );
-
-        (swapchain_image_index, self.frame_buffers.get(swapchain_image_index as _))
+        )
     }
 }
 
 struct RecordCommandBuffer {
     command_buffer: vk::CommandBuffer,
-    wait_for_fence: vk::Fence,
     reset_before_begin_flags: vk::CommandBufferResetFlags,
     usage_flags: vk::CommandBufferUsageFlags,
 }
@@ -124,7 +229,6 @@ impl RecordCommandBuffer {
         commands: F,
     ) {
         // begin
-        context.wait_for_fence(self.wait_for_fence, std::time::Duration::MAX);
         context.reset_command_buffer(self.command_buffer, self.reset_before_begin_flags);
         context.begin_command_buffer(self.command_buffer, self.usage_flags);
 
@@ -136,18 +240,61 @@ impl RecordCommandBuffer {
 }
 
 
+/// The camera the scene is drawn from.
+struct SceneCamera {
+    position: Vec3,
+    view: Mat4,
+    projection: Mat4,
+    /// the frustum between `z_near` and `z_far` in the conventional depth range, for code that
+    /// needs a far plane, like the shadow cascades
+    frustum_projection: Mat4,
+    z_near: f32,
+    z_far: f32,
+}
+impl SceneCamera {
+    /// With reversed z, the projection maps the near plane to depth 1 and has no far plane.
+    fn new(aspect_ratio: f32, fov_y: f32, reversed_z: bool) -> Self {
+        let camera_loc = Vec3::new(0.0, 10., -2.0);
+        let camera_forward = Vec3::new(0.0, 0.0, 1.0);
+        let camera_up = Vec3::new(0.0, 1.0, 0.0);
+
+        let view = Mat4::look_at_rh(camera_loc, camera_loc + camera_forward, camera_up);
+
+        let (z_near, z_far) = (0.1_f32, 200.0_f32);
+        let frustum_projection = Mat4::perspective_rh(fov_y, aspect_ratio, z_near, z_far);
+        let projection = if reversed_z {
+            Mat4::perspective_infinite_reverse_rh(fov_y, aspect_ratio, z_near)
+        } else {
+            frustum_projection
+        };
+
+        Self {
+            position: camera_loc,
+            view,
+            projection,
+            frustum_projection,
+            z_near,
+            z_far,
+        }
+    }
+}
+
 struct DrawParams<'a> {
-    aspect_ratio: f32,
-    fov_y: f32,
+    camera: &'a SceneCamera,
+    /// shadow map set, bound at set 3
+    shadow_set: vk::DescriptorSet,
+    /// dynamic offset of the shadow data in the uniform ring buffer
+    shadow_offset: u32,
     frame_data: &'a FrameData,
     frame_count: usize,
 }
 
 struct DrawResourceAccess<'a> {
     materials: &'a MaterialsResource,
-    meshes: &'a MeshesResource,
     descriptor_sets: &'a DescriptorSetsResource,
     render_objects: &'a RenderObjectsResource,
+    uniform_ring_buffer: &'a mut UniformRingBuffer,
+    lights: &'a LightsResource,
 }
 
 struct RecordDrawCommands<'a> {
@@ -160,49 +307,43 @@ struct RecordDrawCommands<'a> {
 
 
 use macaw::{Affine3A, Quat, Vec3};
-use crate::math_vk_format::{Mat4, Vec4};
-use crate::renderer::gpu_data::{GPUCameraData, GPUObjectData, SomeGPUData};
-use crate::renderer::memory::{AllocatedBuffer, AllocatedBufferCreateInfo, DeviceMemoryWriteInfo, MemoryUsage};
-use crate::renderer::render_objects::Vertex;
+use crate::math_vk_format::Mat4;
+use crate::renderer::gpu_data::{GPUCameraData, GPUFrameData, GPUObjectData, SomeGPUData};
+use crate::renderer::memory::{AllocatedBuffer, AllocatedBufferCreateInfo, DeviceMemoryWriteInfo, MemoryUsage, UniformRingBuffer};
+use crate::renderer::render_objects::{batch_by_material, sort_back_to_front, MaterialInstance, Mesh, RenderQueue, Vertex};
 
 impl<'a> RecordDrawCommands<'a> {
 
     // todo
-    fn _exec2(self, context: &VkContext) {
+    fn _exec2(mut self, context: &VkContext) {
         let world_transform = Affine3A::from_scale_rotation_translation(
             Vec3::ONE, // scale
             Quat::IDENTITY, // rotation
             Vec3::ZERO // translation
         );
 
+        let buffer_data = SomeGPUData {
+            data: world_transform,
+        };
+        let uniform_offset = self.resources.uniform_ring_buffer.push_uniform(&buffer_data);
+
         context.bind_descriptor_sets(BindDescriptorSetsInfo {
             command_buffer: self.params.frame_data.command_buffer,
             pipeline_bind_point: vk::PipelineBindPoint::GRAPHICS,
             pipeline_layout: self.resources.descriptor_sets.get_set(0).pipeline_layout,
             first_set: 0,
-            descriptor_set_handles: &self.resources.descriptor_sets.get_set_handles(&[0, 1 /*2*/]),
+            descriptor_set_handles: &[
+                self.params.frame_data.camera_descriptor_set.handle,
+                self.params.frame_data.object_descriptor_set.handle,
+            ],
+            dynamic_offsets: &[uniform_offset],
         });
 
-        let buffer_data = [SomeGPUData {
-            data: world_transform,
-        }];
-
-
-        let alignment = context.packed_uniform_buffer_range::<SomeGPUData>();
-        let offset = (self.params.frame_data.frame_index) as u64 * alignment;
-        self.resources.descriptor_sets.get_set(0).allocated_buffers[0].write_memory(
-            &context,
-            DeviceMemoryWriteInfo {
-                data: &buffer_data,
-                size: alignment,
-                offset,
-                alignment,
-            },
-        );
-
         // bind pipeline
-        self.resources.render_objects.render_objects[0]
-            .material
+        let material = &self.resources.render_objects.render_objects[0].material;
+        self.resources
+            .materials
+            .template(&material.template)
             .bind(&context, self.params.frame_data.command_buffer);
 
 
@@ -214,7 +355,8 @@ impl<'a> RecordDrawCommands<'a> {
                 position: Default::default(),
                 normal: Default::default(),
                 color: Default::default(),
-                uv: Default::default()
+                uv: Default::default(),
+                tangent: Default::default(),
             }
         ];
 
@@ -246,91 +388,152 @@ impl<'a> RecordDrawCommands<'a> {
 
     }
 
-    fn exec(self, context: &VkContext) {
-        //let material = resources.materials.get("default");
-        let mesh = self.resources.meshes.get("monkey");
-
-        // create mvp matrix
-        let camera_loc = Vec3::new(0.0, 10., -2.0);
-        let camera_forward = Vec3::new(0.0, 0.0, 1.0);
-        let camera_up = Vec3::new(0.0, 1.0, 0.0);
-
-        let view = Mat4::look_at_rh(camera_loc, camera_loc + camera_forward, camera_up);
+    fn exec(mut self, pass_context: &PassContext) {
+        let context = pass_context.context;
+        let camera_data = GPUCameraData {
+            // the lit shaders need the camera position for specular lighting
+            data: self.params.camera.position.extend(1.0),
+            proj_view: self.params.camera.projection * self.params.camera.view,
+        };
+        let camera_offset = self.resources.uniform_ring_buffer.push_uniform(&camera_data);
 
-        let (z_near, z_far) = (0.1_f32, 200.0_f32);
-        let projection = Mat4::perspective_rh(self.params.fov_y, self.params.aspect_ratio, z_near, z_far);
+        let lights = self.resources.lights;
+        let frame_data = GPUFrameData {
+            ambient_color: lights.ambient_color.extend(1.0),
+            light_count: lights.lights().len() as _,
+            ..Default::default()
+        };
+        let frame_data_offset = self.resources.uniform_ring_buffer.push_uniform(&frame_data);
+        let lights_offset = self.resources.uniform_ring_buffer.push_storage(&lights.light_buffer());
 
+        // set 2 holds the material's textures, it's bound together with the material
+        let pipeline_layout = self.resources.descriptor_sets.get_set(0).pipeline_layout;
         context.bind_descriptor_sets(BindDescriptorSetsInfo {
             command_buffer: self.params.frame_data.command_buffer,
             pipeline_bind_point: vk::PipelineBindPoint::GRAPHICS,
-            pipeline_layout: self.resources.descriptor_sets.get_set(0).pipeline_layout,
+            pipeline_layout,
             first_set: 0,
-            descriptor_set_handles: &self.resources.descriptor_sets.get_set_handles(&[0, 1 /*2*/]),
+            descriptor_set_handles: &[
+                self.params.frame_data.camera_descriptor_set.handle,
+                self.params.frame_data.object_descriptor_set.handle,
+            ],
+            dynamic_offsets: &[
+                camera_offset,
+                frame_data_offset,
+                lights_offset,
+            ],
+        });
+        context.bind_descriptor_sets(BindDescriptorSetsInfo {
+            command_buffer: self.params.frame_data.command_buffer,
+            pipeline_bind_point: vk::PipelineBindPoint::GRAPHICS,
+            pipeline_layout,
+            first_set: 3,
+            descriptor_set_handles: &[self.params.shadow_set],
+            dynamic_offsets: &[self.params.shadow_offset],
         });
-
-        let buffer_data = [GPUCameraData {
-            data: Vec4::default(),
-            proj_view: projection * view,
-        }];
-
-        let alignment = context.packed_uniform_buffer_range::<GPUCameraData>();
-        let offset = (self.params.frame_data.frame_index) as u64 * alignment;
-        self.resources.descriptor_sets.get_set(0).allocated_buffers[0].write_memory(
-            &context,
-            DeviceMemoryWriteInfo {
-                data: &buffer_data,
-                size: alignment,
-                offset,
-                alignment,
-            },
-        );
 
         let spin = (self.params.frame_count as f32 * 0.4).to_radians();
         //let spin = 0.0_f32.to_radians();
 
-        let buffer_data = [GPUObjectData {
-            transform: Mat4::from_rotation_x(spin),
-        }];
+        // the object at index i is drawn with first_instance i, the shaders use it to index
+        // the object buffer
+        let render_objects = self.resources.render_objects.drawn();
+        let buffer_data = render_objects
+            .iter()
+            .map(|render_object| GPUObjectData {
+                transform: Mat4::from_rotation_x(spin)
+                    * Mat4::from_translation(render_object.translation),
+            })
+            .collect::<Vec<_>>();
 
-        let alignment = std::mem::align_of::<GPUObjectData>() as _;
-        self.resources.descriptor_sets.get_set(1).allocated_buffers[0].write_memory(
+        let alignment = std::mem::size_of::<GPUObjectData>() as u64;
+        self.params.frame_data.object_buffer.write_memory(
             &context,
             DeviceMemoryWriteInfo {
                 data: &buffer_data,
-                size: alignment,
+                size: alignment * buffer_data.len() as u64,
                 offset: 0,
                 alignment,
             },
         );
 
-        // bind pipeline
-        self.resources.render_objects.render_objects[0]
-            .material
-            .bind(&context, self.params.frame_data.command_buffer);
+        let command_buffer = self.params.frame_data.command_buffer;
+        let materials = &self.resources.materials;
+        let queue_of = |object_index: usize| {
+            materials.template(&render_objects[object_index].material.template).render_queue
+        };
 
-        unsafe {
-            // bind vertex buffers
-            context.device.cmd_bind_vertex_buffers(
-                self.params.frame_data.command_buffer,
-                0,
-                &[self.resources.render_objects[0].mesh.vertex_buffer.handle],
-                &[0],
+        // opaque and alpha tested objects are batched, the depth test sorts them out
+        for queue in [RenderQueue::Opaque, RenderQueue::AlphaTest] {
+            let batches = batch_by_material(
+                (0..render_objects.len())
+                    .filter(|&object_index| queue_of(object_index) == queue)
+                    .map(|object_index| (object_index, &render_objects[object_index].material)),
             );
+            for batch in batches {
+                let template = materials.template(batch.template);
+                template.bind(context, command_buffer);
+
+                for (instance, object_indices) in batch.instances {
+                    instance.bind(context, command_buffer, template);
+
+                    for object_index in object_indices {
+                        let render_object = &render_objects[object_index];
+                        let _occlusion = pass_context.occlusion_query(&render_object.name);
+                        draw_mesh(context, command_buffer, &render_object.mesh, object_index);
+                    }
+                }
+            }
+        }
 
-            let instance_id = 0;
+        // transparent objects have to be blended back to front, so they can't be batched
+        let transparent_objects = sort_back_to_front(
+            self.params.camera.view,
+            (0..render_objects.len())
+                .filter(|&object_index| queue_of(object_index) == RenderQueue::Transparent)
+                .map(|object_index| (object_index, buffer_data[object_index].transform.transform_point3(Vec3::ZERO))),
+        );
+        let mut bound: Option<&MaterialInstance> = None;
+        for object_index in transparent_objects {
+            let material = &render_objects[object_index].material;
+            let template = materials.template(&material.template);
+            if bound.map_or(true, |bound| bound.template != material.template) {
+                template.bind(context, command_buffer);
+                bound = None;
+            }
+            if bound != Some(material) {
+                material.bind(context, command_buffer, template);
+                bound = Some(material);
+            }
 
-            // draw mesh
-            context.device.cmd_draw(
-                self.params.frame_data.command_buffer,
-                mesh.vertex_count as u32,
-                1,
-                0,
-                instance_id,
-            );
+            let _occlusion = pass_context.occlusion_query(&render_objects[object_index].name);
+            draw_mesh(context, command_buffer, &render_objects[object_index].mesh, object_index);
         }
     }
 }
 
+/// Draws a mesh with the object data at `object_index`.
+fn draw_mesh(context: &VkContext, command_buffer: vk::CommandBuffer, mesh: &Mesh, object_index: usize) {
+    unsafe {
+        // bind vertex buffers
+        context.device.cmd_bind_vertex_buffers(
+            command_buffer,
+            0,
+            &[mesh.vertex_buffer.handle],
+            &[0],
+        );
+
+        // draw mesh
+        context.device.cmd_draw(
+            command_buffer,
+            mesh.vertex_count as u32,
+            1,
+            0,
+            object_index as u32,
+        );
+    }
+}
+
 
 
 
@@ -338,30 +541,24 @@ impl<'a> RecordDrawCommands<'a> {
 struct SubmitCommandBufferToGraphicsQueue<'a> {
     command_buffer: vk::CommandBuffer,
     pipeline_wait_stages: &'a [vk::PipelineStageFlags],
-    wait_for_fence: vk::Fence,
+    wait_for: &'a [TimelinePoint],
     wait_for_semaphore: vk::Semaphore,
     signal_semaphore: vk::Semaphore,
 }
 impl<'a> SubmitCommandBufferToGraphicsQueue<'a> {
-    fn exec(self, context: &VkContext) {
-        let wait_semaphores = [self.wait_for_semaphore];
-        let signal_semaphores = [self.signal_semaphore];
-        let command_buffers = [self.command_buffer];
-
-        let submit_info = [vk::SubmitInfo::builder()
-            .wait_dst_stage_mask(self.pipeline_wait_stages)
-            .wait_semaphores(&wait_semaphores)
-            .signal_semaphores(&signal_semaphores)
-            .command_buffers(&command_buffers)
-            .build()];
-
-        unsafe {
-            context.device.queue_submit(
-                context.device.graphics_queue_handle,
-                &submit_info,
-                self.wait_for_fence)
-                .expect("Couldn't submit command buffer to graphics queue");
-        }
+    /// returns the point on the GPU timeline that is reached when rendering finished
+    fn exec(self, context: &VkContext) -> TimelinePoint {
+        let wait_semaphores = self.pipeline_wait_stages
+            .iter()
+            .map(|&stage| (self.wait_for_semaphore, stage))
+            .collect::<Vec<_>>();
+
+        context.submit_graphics(GraphicsSubmission {
+            command_buffers: &[self.command_buffer],
+            wait_semaphores: &wait_semaphores,
+            wait_for: self.wait_for,
+            signal_semaphores: &[self.signal_semaphore],
+        })
     }
 }
 
@@ -396,64 +593,3 @@ impl<'a> PresentImageToScreen<'a> {
 fn aspect_ratio(width: u32, height: u32) -> f32 {
     width as f32 / height as f32
 }
-
-
-struct RecordRenderPass<'a> {
-    command_buffer: vk::CommandBuffer,
-    render_pass: &'a RenderPass,
-    frame_buffer: vk::Framebuffer,
-    swapchain_extent: vk::Extent2D,
-}
-impl<'a> RecordRenderPass<'a> {
-    fn exec<F: FnOnce()>(self, context: &VkContext, draw: F) {
-        self.begin(context);
-
-        draw();
-
-        self.end(context);
-    }
-
-    const fn clear_values() -> [vk::ClearValue; 2] {
-        let color = [0.0_f32, 0., 0., 1.];
-        let color_clear = vk::ClearColorValue { float32: color };
-
-        let depth_clear = vk::ClearDepthStencilValue {
-            depth: 1.0_f32,
-            stencil: 0
-        };
-
-        [
-            vk::ClearValue { color: color_clear },
-            vk::ClearValue {
-                depth_stencil: depth_clear,
-            },
-        ]
-    }
-
-    fn begin(&self, context: &VkContext) {
-        const CLEAR_VALUES: [vk::ClearValue; 2] = RecordRenderPass::clear_values();
-
-        let render_pass_begin_info = vk::RenderPassBeginInfo::builder()
-            .render_pass(self.render_pass.handle)
-            .framebuffer(self.frame_buffer)
-            .render_area(vk::Rect2D {
-                offset: vk::Offset2D { x: 0, y: 0 },
-                extent: self.swapchain_extent,
-            })
-            .clear_values(&CLEAR_VALUES);
-
-        unsafe {
-            context.device.cmd_begin_render_pass(
-                self.command_buffer,
-                &render_pass_begin_info,
-                vk::SubpassContents::INLINE,
-            );
-        }
-    }
-
-    fn end(&self, context: &VkContext) {
-        unsafe {
-            context.device.cmd_end_render_pass(self.command_buffer);
-        }
-    }
-}
@@ -1,29 +1,204 @@
+use crate::renderer::memory::util::as_u8_slice;
+use crate::renderer::render_objects::{PbrMaterialBinding, RenderQueue};
 use crate::renderer::vk_types::{Pipeline, VkContext};
 use ash::vk;
 
-#[derive(Clone)]
-pub struct Material {
+/// What the parameters of a template's instances look like to its pipeline.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct MaterialParameterLayout {
+    /// layout of the instances' texture set, bound at `MaterialTemplate::TEXTURE_SET`
+    pub texture_set_layout: vk::DescriptorSetLayout,
+    /// size of the parameters pushed as fragment shader push constants
+    pub parameters_size: u32,
+}
+
+/// A pipeline shared by every instance made from it. The instances only differ in their
+/// parameters and textures.
+pub struct MaterialTemplate {
     pub pipeline: Pipeline,
-    //pub descriptor_set: DescriptorSetContainer,
-    //pub pipeline_layout: vk::PipelineLayout,
+    pub parameter_layout: MaterialParameterLayout,
+    /// the pipeline's blend and depth state have to fit the queue
+    pub render_queue: RenderQueue,
 }
-impl PartialEq for Material {
+impl PartialEq for MaterialTemplate {
     fn eq(&self, other: &Self) -> bool {
         self.pipeline == other.pipeline
     }
 }
-impl Eq for Material {}
+impl Eq for MaterialTemplate {}
 
-impl Material {
-    pub fn destroy(&mut self, context: &VkContext) {
-        self.pipeline.destroy(&context);
+impl MaterialTemplate {
+    /// Set the lit shaders read an instance's textures from.
+    pub const TEXTURE_SET: u32 = 2;
+
+    pub fn new(
+        pipeline: Pipeline,
+        parameter_layout: MaterialParameterLayout,
+        render_queue: RenderQueue,
+    ) -> Self {
+        Self {
+            pipeline,
+            parameter_layout,
+            render_queue,
+        }
     }
 
-    pub fn from_pipeline(pipeline: Pipeline) -> Self {
-        Self { pipeline }
+    pub fn destroy(&mut self, context: &VkContext) {
+        self.pipeline.destroy(&context);
     }
 
     pub fn bind(&self, context: &VkContext, command_buffer: vk::CommandBuffer) {
         context.bind_pipeline(&self.pipeline, command_buffer);
     }
 }
+
+/// Parameter values and textures for one material template. Cheap to clone, every render object
+/// has its own copy.
+#[derive(Clone)]
+pub struct MaterialInstance {
+    pub name: String,
+    /// name of the template in the `MaterialsResource`
+    pub template: String,
+    pub pbr: PbrMaterialBinding,
+}
+impl PartialEq for MaterialInstance {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.template == other.template
+    }
+}
+impl Eq for MaterialInstance {}
+
+impl MaterialInstance {
+    /// Binds the instance's textures and pushes its parameters. The template's pipeline has to
+    /// be bound already.
+    pub fn bind(
+        &self,
+        context: &VkContext,
+        command_buffer: vk::CommandBuffer,
+        template: &MaterialTemplate,
+    ) {
+        unsafe {
+            context.device.cmd_bind_descriptor_sets(
+                command_buffer,
+                template.pipeline.pipeline_bind_point,
+                template.pipeline.pipeline_layout,
+                MaterialTemplate::TEXTURE_SET,
+                &[self.pbr.texture_set.handle],
+                &[],
+            );
+            context.device.cmd_push_constants(
+                command_buffer,
+                template.pipeline.pipeline_layout,
+                vk::ShaderStageFlags::FRAGMENT,
+                0,
+                as_u8_slice(&self.pbr.parameters),
+            );
+        }
+    }
+}
+
+/// The objects drawn with one template, grouped by instance.
+pub struct TemplateBatch<'a> {
+    pub template: &'a str,
+    /// every instance with the indices of the objects that use it
+    pub instances: Vec<(&'a MaterialInstance, Vec<usize>)>,
+}
+
+/// Groups objects, given by their index and material, so that each template's pipeline and each
+/// instance's parameters only need to be bound once. Batches and the objects in them keep the
+/// order in which they first appear.
+pub fn batch_by_material<'a>(
+    objects: impl IntoIterator<Item = (usize, &'a MaterialInstance)>,
+) -> Vec<TemplateBatch<'a>> {
+    let mut batches: Vec<TemplateBatch> = Vec::new();
+
+    for (object_index, material) in objects {
+        let batch_index = match batches
+            .iter()
+            .position(|batch| batch.template == material.template)
+        {
+            Some(batch_index) => batch_index,
+            None => {
+                batches.push(TemplateBatch {
+                    template: &material.template,
+                    instances: Vec::new(),
+                });
+                batches.len() - 1
+            }
+        };
+
+        let instances = &mut batches[batch_index].instances;
+        match instances
+            .iter_mut()
+            .find(|(instance, _)| *instance == material)
+        {
+            Some((_, objects)) => objects.push(object_index),
+            None => instances.push((material, vec![object_index])),
+        }
+    }
+
+    batches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::gpu_data::GPUMaterialData;
+    use crate::renderer::vk_types::DescriptorSet;
+
+    fn instance(template: &str, name: &str) -> MaterialInstance {
+        MaterialInstance {
+            name: name.to_owned(),
+            template: template.to_owned(),
+            pbr: PbrMaterialBinding {
+                parameters: GPUMaterialData::default(),
+                texture_set: DescriptorSet::default(),
+            },
+        }
+    }
+
+    #[test]
+    fn objects_are_grouped_by_template_and_instance() {
+        let materials = [
+            instance("lit", "stone"),
+            instance("unlit", "sky"),
+            instance("lit", "wood"),
+            instance("lit", "stone"),
+            instance("unlit", "sky"),
+        ];
+
+        let batches = batch_by_material(materials.iter().enumerate());
+
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[0].template, "lit");
+        let lit = batches[0]
+            .instances
+            .iter()
+            .map(|(instance, objects)| (instance.name.as_str(), objects.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(lit, [("stone", vec![0, 3]), ("wood", vec![2])]);
+
+        assert_eq!(batches[1].template, "unlit");
+        assert_eq!(batches[1].instances.len(), 1);
+        assert_eq!(batches[1].instances[0].1, [1, 4]);
+    }
+
+    #[test]
+    fn instances_with_the_same_name_in_different_templates_are_separate() {
+        let materials = [instance("lit", "default"), instance("unlit", "default")];
+
+        let batches = batch_by_material(materials.iter().enumerate());
+
+        assert_eq!(batches.len(), 2);
+        assert!(batches.iter().all(|batch| batch.instances.len() == 1));
+    }
+
+    #[test]
+    fn batches_keep_the_object_indices() {
+        let materials = [instance("lit", "stone"), instance("lit", "stone")];
+
+        let batches = batch_by_material([(4, &materials[0]), (9, &materials[1])]);
+
+        assert_eq!(batches[0].instances[0].1, [4, 9]);
+    }
+}
@@ -2,7 +2,8 @@ use crate::math_vk_format::{Vec2, Vec3};
 use crate::renderer::memory::{
     AllocatedBuffer, AllocatedBufferCreateInfo, DeviceMemoryWriteInfo, MemoryUsage, UploadContext,
 };
-use crate::renderer::render_objects::Vertex;
+use crate::renderer::render_objects::{generate_tangents, Vertex};
+use crate::renderer::sync::ResourceUsage;
 use crate::renderer::vk_types::VkContext;
 use ash::vk;
 
@@ -54,7 +55,7 @@ impl Mesh {
             },
         );
 
-        let gpu_buffer = AllocatedBuffer::create_buffer(
+        let mut gpu_buffer = AllocatedBuffer::create_buffer(
             context,
             AllocatedBufferCreateInfo::<Vertex> {
                 buffer_size: size as _,
@@ -67,7 +68,9 @@ impl Mesh {
         );
 
         upload_context.immediate_submit(context, |cmd_buffer| {
+            gpu_buffer.transition_to(context, cmd_buffer, ResourceUsage::TransferWrite);
             context.copy_buffer(cmd_buffer, staging_buffer.handle, gpu_buffer.handle, size);
+            gpu_buffer.transition_to(context, cmd_buffer, ResourceUsage::VertexBuffer);
         });
 
         staging_buffer.destroy(context);
@@ -109,31 +112,31 @@ impl Mesh {
                 (a, b, c)
             };
 
-            let uv = tri[0].uv().expect("mesh has no UVs");
-            verts.push(Vertex {
-                position: a,
-                color: red,
-                normal: red,
-                uv: Vec2::new(uv[0], -uv[1]),
-            });
-
-            let uv = tri[1].uv().expect("mesh has no UVs");
-            verts.push(Vertex {
-                position: b,
-                color: green,
-                normal: green,
-                uv: Vec2::new(uv[0], -uv[1]),
-            });
-
-            let uv = tri[2].uv().expect("mesh has no UVs");
-            verts.push(Vertex {
-                position: c,
-                color: blue,
-                normal: blue,
-                uv: Vec2::new(uv[0], -uv[1]),
-            });
+            // counter-clockwise winding, like the OBJ format uses
+            let face_normal = (b - a).cross(c - a).normalize_or_zero();
+
+            let corners = [(&tri[0], a, red), (&tri[1], b, green), (&tri[2], c, blue)];
+            for (vertex, position, color) in corners {
+                let uv = vertex.uv().expect("mesh has no UVs");
+                // meshes without normals get flat shading
+                let normal = vertex
+                    .normal()
+                    .map(|normal| Vec3::new(normal[0], normal[1], normal[2]).normalize_or_zero())
+                    .unwrap_or(face_normal);
+
+                verts.push(Vertex {
+                    position,
+                    color,
+                    normal,
+                    uv: Vec2::new(uv[0], -uv[1]),
+                    ..Default::default()
+                });
+            }
         }
 
+        // OBJ files have no tangents
+        generate_tangents(&mut verts);
+
         let verts_count = verts.len();
 
         (verts, verts_count)
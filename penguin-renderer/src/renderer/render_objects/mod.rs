@@ -1,15 +1,24 @@
 mod material;
 pub use material::*;
 
+mod pbr_material;
+pub use pbr_material::*;
+
 mod mesh;
 pub use mesh::*;
 
 mod render_object;
 pub use render_object::*;
 
+mod render_queue;
+pub use render_queue::*;
+
 mod vertex;
 pub use vertex::*;
 
+mod tangents;
+pub use tangents::*;
+
 mod texture;
 mod transform;
 mod voxel;
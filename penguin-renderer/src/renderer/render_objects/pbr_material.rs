@@ -0,0 +1,200 @@
+use crate::math_vk_format::{Vec3, Vec4};
+use crate::renderer::gpu_data::GPUMaterialData;
+use crate::renderer::memory::UploadContext;
+use crate::renderer::render_objects::Texture;
+use crate::renderer::resources::TexturesResource;
+use crate::renderer::vk_types::{
+    BindlessTexturesResource, DescriptorBuilderBindParams, DescriptorSet, DescriptorSetsResource,
+    VkContext,
+};
+use ash::vk;
+
+pub const PBR_MAP_COUNT: usize = 5;
+
+/// Texture of materials without an albedo, metallic-roughness, emissive or occlusion map.
+/// Multiplying with it leaves the factors as they are.
+pub const PBR_WHITE_TEXTURE: &str = "pbr_white";
+/// Texture of materials without a normal map, it keeps the vertex normals.
+pub const PBR_FLAT_NORMAL_TEXTURE: &str = "pbr_flat_normal";
+
+/// The texture maps of a pbr material, in the order of their bindings in the material set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PbrMap {
+    /// rgb base color, alpha coverage
+    Albedo,
+    /// tangent space normals
+    Normal,
+    /// roughness in the green channel, metallic in the blue one, like glTF
+    MetallicRoughness,
+    Emissive,
+    /// ambient occlusion in the red channel
+    Occlusion,
+}
+impl PbrMap {
+    pub const ALL: [PbrMap; PBR_MAP_COUNT] = [
+        PbrMap::Albedo,
+        PbrMap::Normal,
+        PbrMap::MetallicRoughness,
+        PbrMap::Emissive,
+        PbrMap::Occlusion,
+    ];
+
+    pub fn binding(self) -> u32 {
+        self as u32
+    }
+
+    fn fallback_texture(self) -> &'static str {
+        match self {
+            PbrMap::Normal => PBR_FLAT_NORMAL_TEXTURE,
+            _ => PBR_WHITE_TEXTURE,
+        }
+    }
+}
+
+/// A physically based metallic-roughness material. The factors are multiplied with the texels of
+/// their maps, materials without a map use the factor alone.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PbrMaterial {
+    /// linear rgba
+    pub base_color: Vec4,
+    pub metallic: f32,
+    pub roughness: f32,
+    /// linear rgb light the surface gives off by itself
+    pub emissive: Vec3,
+    /// how much the occlusion map darkens the ambient light, 0 ignores the map
+    pub occlusion_strength: f32,
+    /// scales the tangent space xy of the normal map
+    pub normal_scale: f32,
+    /// alpha below which texels are discarded, if the material's template is in the alpha test
+    /// queue
+    pub alpha_cutoff: f32,
+    /// names of the maps in the `TexturesResource`, indexed by `PbrMap`
+    pub maps: [Option<String>; PBR_MAP_COUNT],
+}
+impl Default for PbrMaterial {
+    fn default() -> Self {
+        Self {
+            base_color: Vec4::ONE,
+            metallic: 0.0,
+            roughness: 1.0,
+            emissive: Vec3::ZERO,
+            occlusion_strength: 1.0,
+            normal_scale: 1.0,
+            alpha_cutoff: 0.5,
+            maps: Default::default(),
+        }
+    }
+}
+
+/// Where the lit shaders find a pbr material's parameters and textures.
+#[derive(Clone)]
+pub struct PbrMaterialBinding {
+    /// pushed as fragment shader push constants
+    pub parameters: GPUMaterialData,
+    /// bound at set 2, either the material's own set with one texture per map or the bindless
+    /// texture array
+    pub texture_set: DescriptorSet,
+}
+
+impl PbrMaterial {
+    pub fn with_map(mut self, map: PbrMap, texture_name: &str) -> Self {
+        self.maps[map as usize] = Some(texture_name.to_owned());
+        self
+    }
+
+    /// Name of the texture the map is read from.
+    pub fn texture_name(&self, map: PbrMap) -> &str {
+        self.maps[map as usize]
+            .as_deref()
+            .unwrap_or_else(|| map.fallback_texture())
+    }
+
+    /// Writes the material's maps into a descriptor set, or registers them in the bindless
+    /// texture array if it's enabled.
+    pub fn bind_textures(
+        &self,
+        context: &VkContext,
+        textures: &TexturesResource,
+        bindless_textures: &mut BindlessTexturesResource,
+        descriptor_sets: &mut DescriptorSetsResource,
+        sampler: vk::Sampler,
+    ) -> PbrMaterialBinding {
+        let image_views = PbrMap::ALL.map(|map| textures.get(self.texture_name(map)).image_view);
+
+        if bindless_textures.is_enabled() {
+            let texture_indices = image_views
+                .map(|image_view| bindless_textures.register(context, image_view, sampler));
+
+            return PbrMaterialBinding {
+                parameters: self.gpu_data(texture_indices),
+                texture_set: bindless_textures.set().clone(),
+            };
+        }
+
+        let image_infos = image_views.map(|image_view| {
+            vk::DescriptorImageInfo::builder()
+                .sampler(sampler)
+                .image_view(image_view)
+                .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                .build()
+        });
+
+        let texture_set = PbrMap::ALL
+            .iter()
+            .fold(descriptor_sets.builder(), |builder, &map| {
+                builder.bind_image(
+                    std::slice::from_ref(&image_infos[map as usize]),
+                    DescriptorBuilderBindParams {
+                        binding: map.binding(),
+                        ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                        shader_stages: vk::ShaderStageFlags::FRAGMENT,
+                    },
+                )
+            })
+            .build(context)
+            .expect("couldn't build pbr material set");
+
+        PbrMaterialBinding {
+            parameters: self.gpu_data([0; PBR_MAP_COUNT]),
+            texture_set,
+        }
+    }
+
+    fn gpu_data(&self, texture_indices: [u32; PBR_MAP_COUNT]) -> GPUMaterialData {
+        GPUMaterialData {
+            base_color: self.base_color,
+            emissive: self.emissive,
+            metallic: self.metallic,
+            roughness: self.roughness,
+            occlusion_strength: self.occlusion_strength,
+            normal_scale: self.normal_scale,
+            texture_indices,
+            alpha_cutoff: self.alpha_cutoff,
+            ..Default::default()
+        }
+    }
+}
+
+impl TexturesResource {
+    /// Adds the 1x1 textures that stand in for the maps a pbr material doesn't have.
+    pub fn insert_pbr_fallbacks(&mut self, context: &VkContext, upload_context: &UploadContext) {
+        let fallbacks: [(&str, [u8; 4]); 2] = [
+            (PBR_WHITE_TEXTURE, [255, 255, 255, 255]),
+            (PBR_FLAT_NORMAL_TEXTURE, [128, 128, 255, 255]),
+        ];
+
+        for (name, pixel) in fallbacks {
+            self.insert(
+                context,
+                name,
+                Texture::from_rgba_pixels(
+                    context,
+                    upload_context,
+                    (1, 1),
+                    vk::Format::R8G8B8A8_UNORM,
+                    &pixel,
+                ),
+            );
+        }
+    }
+}
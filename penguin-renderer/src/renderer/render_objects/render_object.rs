@@ -1,6 +1,6 @@
 use macaw::Affine3A;
 use crate::math_vk_format::Vec3;
-use crate::renderer::render_objects::{Material, Mesh};
+use crate::renderer::render_objects::{MaterialInstance, Mesh};
 
 pub struct ModelMatrix {
     matrix: Affine3A,
@@ -17,7 +17,7 @@ pub struct ProjectionMatrix {
 
 
 pub struct RenderObject {
-    pub material: Material,
+    pub material: MaterialInstance,
     pub mesh: Mesh,
     pub translation: Vec3,
     pub name: String,
@@ -0,0 +1,76 @@
+use crate::math_vk_format::{Mat4, Vec3};
+
+/// When the objects of a material are drawn. Queues are drawn in the order they're declared in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RenderQueue {
+    Opaque,
+    /// Opaque, but texels below the material's alpha cutoff are discarded, like for foliage.
+    AlphaTest,
+    /// Alpha blended over everything opaque, back to front, without writing depth.
+    Transparent,
+}
+impl RenderQueue {
+    pub const ALL: [RenderQueue; 3] = [
+        RenderQueue::Opaque,
+        RenderQueue::AlphaTest,
+        RenderQueue::Transparent,
+    ];
+}
+
+/// Distance of a world space position in front of the camera with the given view matrix.
+pub fn view_depth(view: Mat4, position: Vec3) -> f32 {
+    // right handed, the camera looks along -z
+    -view.transform_point3(position).z
+}
+
+/// Orders objects, given by their index and world position, from the farthest to the closest,
+/// so that blending composes them correctly. Objects at the same depth keep their order.
+pub fn sort_back_to_front(
+    view: Mat4,
+    objects: impl IntoIterator<Item = (usize, Vec3)>,
+) -> Vec<usize> {
+    let mut objects = objects
+        .into_iter()
+        .map(|(index, position)| (index, view_depth(view, position)))
+        .collect::<Vec<_>>();
+
+    objects.sort_by(|(_, depth_a), (_, depth_b)| depth_b.total_cmp(depth_a));
+    objects.into_iter().map(|(index, _)| index).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn view() -> Mat4 {
+        Mat4::look_at_rh(Vec3::new(0.0, 0.0, 5.0), Vec3::ZERO, Vec3::Y)
+    }
+
+    #[test]
+    fn depth_grows_away_from_the_camera() {
+        assert!((view_depth(view(), Vec3::ZERO) - 5.0).abs() < 1e-5);
+        assert!(view_depth(view(), Vec3::new(0.0, 0.0, -5.0)) > view_depth(view(), Vec3::ZERO));
+        assert!(view_depth(view(), Vec3::new(0.0, 0.0, 10.0)) < 0.0);
+    }
+
+    #[test]
+    fn farthest_objects_come_first() {
+        let objects = [
+            (3, Vec3::new(0.0, 0.0, 2.0)),
+            (7, Vec3::new(1.0, 0.0, -20.0)),
+            (1, Vec3::new(0.0, 4.0, -3.0)),
+        ];
+
+        assert_eq!(sort_back_to_front(view(), objects), [7, 1, 3]);
+    }
+
+    #[test]
+    fn equally_deep_objects_keep_their_order() {
+        let objects = [
+            (0, Vec3::new(-1.0, 0.0, 0.0)),
+            (1, Vec3::new(1.0, 0.0, 0.0)),
+        ];
+
+        assert_eq!(sort_back_to_front(view(), objects), [0, 1]);
+    }
+}
@@ -0,0 +1,131 @@
+use crate::math_vk_format::{Vec2, Vec3, Vec4};
+use crate::renderer::render_objects::Vertex;
+
+/// Fills in the tangents of a triangle list from its positions and UVs. Vertices that already
+/// have a tangent, because the file they're imported from has them, keep it.
+///
+///     Every vertex gets the tangent of its triangle, made orthogonal to the vertex normal.
+///     Triangles without usable UVs get an arbitrary tangent, so normal mapping them does
+///     nothing surprising.
+pub fn generate_tangents(vertices: &mut [Vertex]) {
+    for triangle in vertices.chunks_exact_mut(3) {
+        let (tangent, bitangent) = triangle_tangent_space(
+            [
+                triangle[0].position,
+                triangle[1].position,
+                triangle[2].position,
+            ],
+            [triangle[0].uv, triangle[1].uv, triangle[2].uv],
+        )
+        .unwrap_or((Vec3::ZERO, Vec3::ZERO));
+
+        for vertex in triangle
+            .iter_mut()
+            .filter(|vertex| vertex.tangent == Vec4::ZERO)
+        {
+            vertex.tangent = vertex_tangent(vertex.normal, tangent, bitangent);
+        }
+    }
+}
+
+/// Directions in which u and v increase along the triangle, or `None` if its UVs are degenerate.
+fn triangle_tangent_space(positions: [Vec3; 3], uvs: [Vec2; 3]) -> Option<(Vec3, Vec3)> {
+    let (edge1, edge2) = (positions[1] - positions[0], positions[2] - positions[0]);
+    let (delta_uv1, delta_uv2) = (uvs[1] - uvs[0], uvs[2] - uvs[0]);
+
+    let determinant = delta_uv1.x * delta_uv2.y - delta_uv2.x * delta_uv1.y;
+    if determinant.abs() < f32::EPSILON {
+        return None;
+    }
+
+    let tangent = (edge1 * delta_uv2.y - edge2 * delta_uv1.y) / determinant;
+    let bitangent = (edge2 * delta_uv1.x - edge1 * delta_uv2.x) / determinant;
+    Some((tangent, bitangent))
+}
+
+fn vertex_tangent(normal: Vec3, tangent: Vec3, bitangent: Vec3) -> Vec4 {
+    // Gram-Schmidt, the normal stays as it is
+    let orthogonal = (tangent - normal * normal.dot(tangent)).normalize_or_zero();
+    if orthogonal == Vec3::ZERO {
+        return any_orthogonal(normal).extend(1.0);
+    }
+
+    // mirrored UVs flip the bitangent
+    let handedness = if normal.cross(orthogonal).dot(bitangent) < 0.0 {
+        -1.0
+    } else {
+        1.0
+    };
+    orthogonal.extend(handedness)
+}
+
+fn any_orthogonal(normal: Vec3) -> Vec3 {
+    let helper = if normal.x.abs() < 0.9 {
+        Vec3::X
+    } else {
+        Vec3::Y
+    };
+    normal.cross(helper).normalize_or_zero()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn triangle(uvs: [Vec2; 3]) -> Vec<Vertex> {
+        let positions = [Vec3::ZERO, Vec3::X, Vec3::Y];
+        positions
+            .into_iter()
+            .zip(uvs)
+            .map(|(position, uv)| Vertex {
+                position,
+                normal: Vec3::Z,
+                uv,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn tangent_follows_u() {
+        let mut vertices = triangle([Vec2::ZERO, Vec2::X, Vec2::Y]);
+        generate_tangents(&mut vertices);
+
+        for vertex in vertices {
+            assert!((vertex.tangent - Vec4::new(1.0, 0.0, 0.0, 1.0)).length() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn mirrored_uvs_flip_the_handedness() {
+        let mut vertices = triangle([Vec2::X, Vec2::ZERO, Vec2::new(1.0, 1.0)]);
+        generate_tangents(&mut vertices);
+
+        for vertex in vertices {
+            assert!((vertex.tangent - Vec4::new(-1.0, 0.0, 0.0, -1.0)).length() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn degenerate_uvs_still_give_a_tangent() {
+        let mut vertices = triangle([Vec2::ZERO; 3]);
+        generate_tangents(&mut vertices);
+
+        for vertex in vertices {
+            let tangent = vertex.tangent.truncate();
+            assert!((tangent.length() - 1.0).abs() < 1e-5);
+            assert!(tangent.dot(vertex.normal).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn imported_tangents_are_kept() {
+        let mut vertices = triangle([Vec2::ZERO, Vec2::X, Vec2::Y]);
+        let imported = Vec4::new(0.0, 1.0, 0.0, -1.0);
+        vertices[1].tangent = imported;
+        generate_tangents(&mut vertices);
+
+        assert_eq!(vertices[1].tangent, imported);
+        assert!((vertices[0].tangent - Vec4::new(1.0, 0.0, 0.0, 1.0)).length() < 1e-5);
+    }
+}
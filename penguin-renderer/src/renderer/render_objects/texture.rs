@@ -2,7 +2,7 @@ use crate::renderer::memory::{
     AllocatedBuffer, AllocatedBufferCreateInfo, AllocatedImage, AllocatedImageCreateInfo,
     MemoryUsage, UploadContext,
 };
-use crate::renderer::sync::PipelineBarrierBuilder;
+use crate::renderer::sync::ResourceUsage;
 use crate::renderer::vk_types::VkContext;
 use ash::vk;
 
@@ -20,17 +20,76 @@ impl Texture {
 }
 
 impl Texture {
+    /// Loads a color texture, whose texels are sRGB encoded.
     pub fn from_image_file(
         context: &VkContext,
         upload_context: &UploadContext,
         image_file_name: &str,
     ) -> Self {
-        let (allocated_image, image_format, subresource_range) = AllocatedImage::from_image_file(
+        Self::from_image_file_with_format(
+            context,
+            upload_context,
+            image_file_name,
+            vk::Format::R8G8B8A8_SRGB,
+        )
+    }
+
+    /// Loads a texture holding data rather than colors, like a normal map, whose texels are
+    /// read as they are.
+    pub fn linear_from_image_file(
+        context: &VkContext,
+        upload_context: &UploadContext,
+        image_file_name: &str,
+    ) -> Self {
+        Self::from_image_file_with_format(
+            context,
+            upload_context,
+            image_file_name,
+            vk::Format::R8G8B8A8_UNORM,
+        )
+    }
+
+    /// A texture from RGBA pixels in memory, `format` has to have four 8 bit channels.
+    pub fn from_rgba_pixels(
+        context: &VkContext,
+        upload_context: &UploadContext,
+        (width, height): (u32, u32),
+        format: vk::Format,
+        pixels: &[u8],
+    ) -> Self {
+        let (allocated_image, subresource_range) = AllocatedImage::from_rgba_pixels(
+            context,
+            upload_context,
+            (width, height),
+            format,
+            pixels,
+        );
+
+        Self::with_image_view(context, allocated_image, format, subresource_range)
+    }
+
+    fn from_image_file_with_format(
+        context: &VkContext,
+        upload_context: &UploadContext,
+        image_file_name: &str,
+        format: vk::Format,
+    ) -> Self {
+        let (allocated_image, subresource_range) = AllocatedImage::from_image_file(
             context,
             upload_context,
             &(IMAGES_FOLDER_PATH.to_owned() + image_file_name),
+            format,
         );
 
+        Self::with_image_view(context, allocated_image, format, subresource_range)
+    }
+
+    fn with_image_view(
+        context: &VkContext,
+        allocated_image: AllocatedImage,
+        image_format: vk::Format,
+        subresource_range: vk::ImageSubresourceRange,
+    ) -> Self {
         let image_view_create_info = vk::ImageViewCreateInfo::builder()
             .image(allocated_image.handle)
             .format(image_format)
@@ -51,27 +110,226 @@ impl Texture {
     }
 }
 
+impl Texture {
+    /// Loads a color grading lookup table stored as `size` slices of `size` x `size` pixels,
+    /// laid out side by side. Red increases along x within a slice, green along y and blue from
+    /// slice to slice.
+    pub fn color_lut_from_image_file(
+        context: &VkContext,
+        upload_context: &UploadContext,
+        image_file_name: &str,
+    ) -> Self {
+        let file_path = IMAGES_FOLDER_PATH.to_owned() + image_file_name;
+        let file =
+            std::fs::File::open(&file_path).expect(&format!("couldn't open file: {}", file_path));
+        let mut reader = std::io::BufReader::new(file);
+
+        let (image_info, pixels) =
+            stb::image::stbi_load_from_reader(&mut reader, stb::image::Channels::RgbAlpha)
+                .expect(&format!("couldn't read image {} as RBGA", file_path));
+
+        let size = image_info.height as u32;
+        assert_eq!(
+            image_info.width as u32,
+            size * size,
+            "color lut {} isn't {} slices of {}x{} pixels",
+            file_path,
+            size,
+            size,
+            size
+        );
+
+        Self::color_lut_from_pixels(context, upload_context, size, pixels.as_slice())
+    }
+
+    /// A color grading lookup table that maps every color to itself.
+    pub fn identity_color_lut(
+        context: &VkContext,
+        upload_context: &UploadContext,
+        size: u32,
+    ) -> Self {
+        let max = (size - 1) as f32;
+        let channel = |value: u32| (value as f32 / max * 255.0).round() as u8;
+
+        let pixels = (0..size)
+            .flat_map(|green| {
+                (0..size * size).flat_map(move |x| {
+                    let (blue, red) = (x / size, x % size);
+                    [channel(red), channel(green), channel(blue), 255]
+                })
+            })
+            .collect::<Vec<u8>>();
+
+        Self::color_lut_from_pixels(context, upload_context, size, &pixels)
+    }
+
+    /// Uploads the slices of a lookup table laid out like in `color_lut_from_image_file` into
+    /// a 3D image.
+    fn color_lut_from_pixels(
+        context: &VkContext,
+        upload_context: &UploadContext,
+        size: u32,
+        pixels: &[u8],
+    ) -> Self {
+        let format = vk::Format::R8G8B8A8_UNORM;
+        let texel_size = 4;
+
+        let mut staging_buffer = AllocatedBuffer::create_buffer(
+            context,
+            AllocatedBufferCreateInfo::<u8> {
+                initial_data: pixels,
+                buffer_size: pixels.len() as _,
+                buffer_usage: vk::BufferUsageFlags::TRANSFER_SRC,
+                memory_usage: MemoryUsage::CpuMemGpuVisible,
+                sharing_mode: vk::SharingMode::EXCLUSIVE,
+                ..Default::default()
+            },
+        );
+
+        let mut allocated_image = AllocatedImage::create(
+            context,
+            AllocatedImageCreateInfo {
+                image_create_info: vk::ImageCreateInfo::builder()
+                    .image_type(vk::ImageType::TYPE_3D)
+                    .format(format)
+                    .extent(vk::Extent3D {
+                        width: size,
+                        height: size,
+                        depth: size,
+                    })
+                    .mip_levels(1)
+                    .array_layers(1)
+                    .samples(vk::SampleCountFlags::TYPE_1)
+                    .tiling(vk::ImageTiling::OPTIMAL)
+                    .usage(vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST)
+                    .sharing_mode(vk::SharingMode::EXCLUSIVE),
+                memory_usage: MemoryUsage::GpuOnly,
+            },
+        );
+
+        // every slice starts `size` texels further along the first row of the strip
+        let copy_regions = (0..size)
+            .map(|slice| {
+                vk::BufferImageCopy::builder()
+                    .buffer_offset((slice * size * texel_size) as _)
+                    .buffer_row_length(size * size)
+                    .buffer_image_height(size)
+                    .image_subresource(vk::ImageSubresourceLayers {
+                        aspect_mask: vk::ImageAspectFlags::COLOR,
+                        mip_level: 0,
+                        base_array_layer: 0,
+                        layer_count: 1,
+                    })
+                    .image_offset(vk::Offset3D {
+                        x: 0,
+                        y: 0,
+                        z: slice as _,
+                    })
+                    .image_extent(vk::Extent3D {
+                        width: size,
+                        height: size,
+                        depth: 1,
+                    })
+                    .build()
+            })
+            .collect::<Vec<_>>();
+
+        upload_context.immediate_submit(context, |cmd_buffer| {
+            allocated_image.transition_to(
+                context,
+                cmd_buffer,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                ResourceUsage::TransferWrite,
+            );
+
+            unsafe {
+                context.device.cmd_copy_buffer_to_image(
+                    cmd_buffer,
+                    staging_buffer.handle,
+                    allocated_image.handle,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    &copy_regions,
+                )
+            }
+
+            allocated_image.transition_to(
+                context,
+                cmd_buffer,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                ResourceUsage::FragmentShaderRead,
+            );
+        });
+
+        staging_buffer.destroy(context);
+
+        let image_view_create_info = vk::ImageViewCreateInfo::builder()
+            .image(allocated_image.handle)
+            .format(format)
+            .subresource_range(allocated_image.state.full_range())
+            .view_type(vk::ImageViewType::TYPE_3D);
+
+        let image_view = unsafe {
+            context
+                .device
+                .create_image_view(&image_view_create_info, None)
+        }
+        .expect("couldn't create color lut image view");
+
+        Self {
+            image: allocated_image,
+            image_view,
+        }
+    }
+}
+
 impl AllocatedImage {
     fn from_image_file(
         context: &VkContext,
         upload_context: &UploadContext,
         file_path: &str,
-    ) -> (Self, vk::Format, vk::ImageSubresourceRange) {
+        vk_format: vk::Format,
+    ) -> (Self, vk::ImageSubresourceRange) {
         let file =
             std::fs::File::open(file_path).expect(&format!("couldn't open file: {}", file_path));
         let mut reader = std::io::BufReader::new(file);
 
-        let vk_format = vk::Format::R8G8B8A8_SRGB;
         let (image_info, pixels) =
             stb::image::stbi_load_from_reader(&mut reader, stb::image::Channels::RgbAlpha)
                 .expect(&format!("couldn't read image {} as RBGA", file_path));
 
-        let size = pixels.size(); // aka pixel count / height * width * channel_count)
+        let image = Self::from_rgba_pixels(
+            context,
+            upload_context,
+            (image_info.width as _, image_info.height as _),
+            vk_format,
+            pixels.as_slice(),
+        );
+
+        log::trace!("Image {} loaded successfully!", file_path);
+
+        image
+    }
+
+    fn from_rgba_pixels(
+        context: &VkContext,
+        upload_context: &UploadContext,
+        (width, height): (u32, u32),
+        vk_format: vk::Format,
+        pixels: &[u8],
+    ) -> (Self, vk::ImageSubresourceRange) {
+        let size = pixels.len(); // aka pixel count / height * width * channel_count)
+        assert_eq!(
+            size,
+            (width * height * 4) as usize,
+            "expected {}x{} RGBA pixels",
+            width,
+            height
+        );
 
         let mut staging_buffer = AllocatedBuffer::create_buffer(
             context,
             AllocatedBufferCreateInfo::<u8> {
-                initial_data: pixels.as_slice(),
+                initial_data: pixels,
                 buffer_size: size as _,
                 buffer_usage: vk::BufferUsageFlags::TRANSFER_SRC,
                 memory_usage: MemoryUsage::CpuMemGpuVisible,
@@ -81,12 +339,12 @@ impl AllocatedImage {
         );
 
         let image_extent = vk::Extent3D::builder()
-            .height(image_info.height as _)
-            .width(image_info.width as _)
+            .height(height)
+            .width(width)
             .depth(1)
             .build();
 
-        let allocated_image = AllocatedImage::create(
+        let mut allocated_image = AllocatedImage::create(
             context,
             AllocatedImageCreateInfo {
                 image_create_info: {
@@ -124,19 +382,13 @@ impl AllocatedImage {
         upload_context.immediate_submit(context, |cmd_buffer| {
             // perform layout transition to prepare image to be ready to be a destination
             // for memory transfers
-            PipelineBarrierBuilder::builder()
-                .src_stage_mask(vk::PipelineStageFlags::TOP_OF_PIPE)
-                .dst_stage_mask(vk::PipelineStageFlags::TRANSFER)
-                .image_memory_barriers(&[vk::ImageMemoryBarrier::builder()
-                    .image(allocated_image.handle)
-                    .old_layout(vk::ImageLayout::UNDEFINED)
-                    .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
-                    .subresource_range(subresource_range)
-                    // prepare image layout to be ready to be a destination for memory transfers
-                    .src_access_mask(vk::AccessFlags::empty())
-                    .dst_access_mask(vk::AccessFlags::TRANSFER_WRITE)
-                    .build()])
-                .build_exec(context, cmd_buffer);
+            allocated_image.transition_range_to(
+                context,
+                cmd_buffer,
+                subresource_range,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                ResourceUsage::TransferWrite,
+            );
 
             {
                 let copy_region = vk::BufferImageCopy::builder()
@@ -148,6 +400,9 @@ impl AllocatedImage {
                     .image_extent(image_extent)
                     .build();
 
+                allocated_image
+                    .expect_layout(subresource_range, vk::ImageLayout::TRANSFER_DST_OPTIMAL);
+
                 unsafe {
                     context.device.cmd_copy_buffer_to_image(
                         cmd_buffer,
@@ -160,24 +415,17 @@ impl AllocatedImage {
             }
 
             //// perform layout transition to prepare image to be ready to read from shaders
-            PipelineBarrierBuilder::builder()
-                .src_stage_mask(vk::PipelineStageFlags::TRANSFER)
-                .dst_stage_mask(vk::PipelineStageFlags::FRAGMENT_SHADER)
-                .image_memory_barriers(&[vk::ImageMemoryBarrier::builder()
-                    .image(allocated_image.handle)
-                    .subresource_range(subresource_range)
-                    .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
-                    .new_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
-                    .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
-                    .dst_access_mask(vk::AccessFlags::SHADER_READ)
-                    .build()])
-                .build_exec(context, cmd_buffer);
+            allocated_image.transition_range_to(
+                context,
+                cmd_buffer,
+                subresource_range,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                ResourceUsage::FragmentShaderRead,
+            );
         });
 
         staging_buffer.destroy(context);
 
-        log::trace!("Image {} loaded successfully!", file_path);
-
-        (allocated_image, vk_format, subresource_range)
+        (allocated_image, subresource_range)
     }
 }
@@ -1,12 +1,17 @@
-use crate::math_vk_format::{Vec2, Vec3, VkFormat};
+use crate::math_vk_format::{Vec2, Vec3, Vec4, VkFormat};
+use crate::renderer::memory::util::align_up;
 use ash::vk;
 
 #[derive(Clone, Copy, Default)]
+#[repr(C)]
 pub struct Vertex {
     pub position: Vec3,
     pub normal: Vec3,
     pub color: Vec3,
     pub uv: Vec2,
+    /// xyz points along the direction in which u increases, w is the handedness of the
+    /// bitangent. Zero until imported or generated.
+    pub tangent: Vec4,
 }
 impl Vertex {
     pub fn create_binding_descriptions(binding: u32) -> [vk::VertexInputBindingDescription; 1] {
@@ -17,11 +22,15 @@ impl Vertex {
         }]
     }
 
-    pub fn create_attribute_descriptions(binding: u32) -> [vk::VertexInputAttributeDescription; 4] {
+    pub fn create_attribute_descriptions(binding: u32) -> [vk::VertexInputAttributeDescription; 5] {
         let offset0 = 0;
         let offset1 = std::mem::size_of::<Vec3>();
         let offset2 = offset1 + std::mem::size_of::<Vec3>();
-        let offset3 = offset2 + std::mem::size_of::<Vec2>();
+        let offset3 = offset2 + std::mem::size_of::<Vec3>();
+        let offset4 = align_up(
+            (offset3 + std::mem::size_of::<Vec2>()) as u64,
+            std::mem::align_of::<Vec4>() as u64,
+        );
 
         [
             vk::VertexInputAttributeDescription {
@@ -48,6 +57,12 @@ impl Vertex {
                 format: Vec2::vk_format(),
                 offset: offset3 as u32,
             },
+            vk::VertexInputAttributeDescription {
+                binding,
+                location: 4,
+                format: Vec4::vk_format(),
+                offset: offset4 as u32,
+            },
         ]
     }
 }
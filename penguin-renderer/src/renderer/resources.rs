@@ -1,12 +1,33 @@
 // ----------------- RESOURCES -----------------
 use crate::renderer::memory::UploadContext;
-use crate::renderer::render_objects::{Material, Mesh, RenderObject, Texture};
-use crate::renderer::vk_types::{Pipeline, VkContext};
+use crate::renderer::render_objects::{
+    MaterialInstance, MaterialParameterLayout, MaterialTemplate, Mesh, PbrMaterialBinding,
+    RenderObject, RenderQueue, Texture,
+};
+use crate::renderer::vk_types::{BindlessTexturesResource, Pipeline, VkContext};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 #[derive(Default)]
 pub struct RenderObjectsResource {
     pub render_objects: Vec<RenderObject>,
+    warned_about_overflow: AtomicBool,
+}
+impl RenderObjectsResource {
+    /// The objects that fit into the object buffer, the rest aren't drawn.
+    pub fn drawn(&self) -> &[RenderObject] {
+        let max_objects = crate::config::MAX_OBJECTS;
+        if self.render_objects.len() <= max_objects {
+            return &self.render_objects;
+        }
+        if !self.warned_about_overflow.swap(true, Ordering::Relaxed) {
+            log::warn!(
+                "More than {} render objects, the rest aren't drawn.",
+                max_objects
+            );
+        }
+        &self.render_objects[..max_objects]
+    }
 }
 impl std::ops::Deref for RenderObjectsResource {
     type Target = Vec<RenderObject>;
@@ -33,12 +54,36 @@ impl TexturesResource {
         upload_context: &UploadContext,
         (name, file_name): (&str, &str),
     ) {
-        self.textures.insert(
-            name.to_owned(),
+        self.insert(
+            context,
+            name,
             Texture::from_image_file(context, upload_context, file_name),
         );
     }
 
+    /// Like `insert_from_file`, for textures that hold data instead of colors, like normal or
+    /// metallic-roughness maps.
+    pub fn insert_linear_from_file(
+        &mut self,
+        context: &VkContext,
+        upload_context: &UploadContext,
+        (name, file_name): (&str, &str),
+    ) {
+        self.insert(
+            context,
+            name,
+            Texture::linear_from_image_file(context, upload_context, file_name),
+        );
+    }
+
+    pub fn insert(&mut self, context: &VkContext, name: &str, texture: Texture) {
+        let debug_name = format!("texture:{}", name);
+        context.set_object_name(texture.image.handle, &debug_name);
+        context.set_object_name(texture.image_view, &debug_name);
+
+        self.textures.insert(name.to_owned(), texture);
+    }
+
     pub fn get(&self, name: &str) -> &Texture {
         let name = name.to_owned();
         self.textures
@@ -64,10 +109,10 @@ impl MeshesResource {
         upload_context: &UploadContext,
         (name, file_name): (&str, &str),
     ) {
-        self.meshes.insert(
-            name.to_owned(),
-            Mesh::from_obj(context, upload_context, file_name),
-        );
+        let mesh = Mesh::from_obj(context, upload_context, file_name);
+        context.set_object_name(mesh.vertex_buffer.handle, &format!("mesh:{}", name));
+
+        self.meshes.insert(name.to_owned(), mesh);
     }
 
     pub fn get(&self, name: &str) -> &Mesh {
@@ -78,27 +123,88 @@ impl MeshesResource {
     }
 }
 
+/// Material templates and the instances made from them, both by name.
 #[derive(Default)]
 pub struct MaterialsResource {
-    materials: HashMap<String, Material>,
+    templates: HashMap<String, MaterialTemplate>,
+    instances: HashMap<String, MaterialInstance>,
 }
 impl MaterialsResource {
     pub fn destroy(&mut self, context: &VkContext) {
-        self.materials
+        self.templates
             .iter_mut()
-            .for_each(|(_name, material)| material.destroy(context));
+            .for_each(|(_name, template)| template.destroy(context));
     }
 
-    pub fn insert(&mut self, (name, pipeline): (&str, Pipeline)) {
-        self.materials
-            .insert(name.to_owned(), Material::from_pipeline(pipeline));
+    pub fn insert_template(
+        &mut self,
+        context: &VkContext,
+        (name, pipeline): (&str, Pipeline),
+        parameter_layout: MaterialParameterLayout,
+        render_queue: RenderQueue,
+    ) {
+        let debug_name = format!("material:{}", name);
+        context.set_object_name(pipeline.handle, &debug_name);
+        context.set_object_name(pipeline.pipeline_layout, &debug_name);
+
+        self.templates.insert(
+            name.to_owned(),
+            MaterialTemplate::new(pipeline, parameter_layout, render_queue),
+        );
     }
 
-    pub fn get(&self, name: &str) -> &Material {
-        let name = name.to_owned();
-        self.materials
-            .get(&name)
-            .expect(&format!("no material called {}", name))
+    /// Adds an instance of the template called `template`. Its texture set has to have the
+    /// template's layout.
+    pub fn insert_instance(
+        &mut self,
+        context: &VkContext,
+        (name, template): (&str, &str),
+        mut pbr: PbrMaterialBinding,
+    ) {
+        let MaterialTemplate {
+            parameter_layout,
+            render_queue,
+            ..
+        } = self.template(template);
+        assert!(
+            parameter_layout.texture_set_layout == pbr.texture_set.layout.handle,
+            "material instance {} doesn't match the texture set layout of template {}",
+            name,
+            template
+        );
+        // only alpha tested materials discard texels
+        if *render_queue != RenderQueue::AlphaTest {
+            pbr.parameters.alpha_cutoff = 0.0;
+        }
+
+        // with bindless textures, every instance uses the texture array's set
+        if !context
+            .enabled_features()
+            .is_enabled(BindlessTexturesResource::FEATURE_REQUEST.subsystem)
+        {
+            context.set_object_name(pbr.texture_set.handle, &format!("material:{}", name));
+        }
+
+        self.instances.insert(
+            name.to_owned(),
+            MaterialInstance {
+                name: name.to_owned(),
+                template: template.to_owned(),
+                pbr,
+            },
+        );
+    }
+
+    pub fn template(&self, name: &str) -> &MaterialTemplate {
+        self.templates
+            .get(name)
+            .expect(&format!("no material template called {}", name))
+    }
+
+    pub fn instance(&self, name: &str) -> &MaterialInstance {
+        self.instances
+            .get(name)
+            .expect(&format!("no material instance called {}", name))
     }
 }
 
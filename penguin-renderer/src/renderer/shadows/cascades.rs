@@ -0,0 +1,227 @@
+use crate::math_vk_format::{Mat4, Vec3, Vec4};
+
+/// How far behind a cascade's bounding sphere, in radii, geometry still casts shadows into it.
+const CASTER_DEPTH_EXTENSION: f32 = 4.0;
+
+/// One slice of the camera frustum and the light's view of it.
+#[derive(Debug, Clone, Copy)]
+pub struct Cascade {
+    /// world space to the clip space of the cascade's shadow map tile
+    pub view_proj: Mat4,
+    /// view distance at which the cascade ends
+    pub split_distance: f32,
+}
+
+/// View distances at which each cascade ends, blending logarithmic and uniform splits.
+///
+///     `lambda` 1 gives fully logarithmic splits, which keep the texel density even but make the
+///     first cascades tiny, 0 gives uniform ones.
+pub fn cascade_splits(z_near: f32, z_far: f32, cascade_count: usize, lambda: f32) -> Vec<f32> {
+    (1..=cascade_count)
+        .map(|index| {
+            let fraction = index as f32 / cascade_count as f32;
+            let logarithmic = z_near * (z_far / z_near).powf(fraction);
+            let uniform = z_near + (z_far - z_near) * fraction;
+            lambda * logarithmic + (1.0 - lambda) * uniform
+        })
+        .collect()
+}
+
+/// Corners of the part of the frustum between the view distances `slice_near` and `slice_far`.
+/// The frustum is the one of `proj_view`, with its planes at `z_near` and `z_far`.
+pub fn frustum_slice_corners(
+    proj_view: Mat4,
+    (z_near, z_far): (f32, f32),
+    (slice_near, slice_far): (f32, f32),
+) -> [Vec3; 8] {
+    let inverse = proj_view.inverse();
+
+    let mut corners = [Vec3::ZERO; 8];
+    for (index, (x, y)) in [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)]
+        .into_iter()
+        .enumerate()
+    {
+        let near = inverse.project_point3(Vec3::new(x, y, 0.0));
+        let far = inverse.project_point3(Vec3::new(x, y, 1.0));
+
+        // the view distance grows linearly along the rays from the near to the far corners
+        let ray = far - near;
+        corners[index * 2] = near + ray * ((slice_near - z_near) / (z_far - z_near));
+        corners[index * 2 + 1] = near + ray * ((slice_far - z_near) / (z_far - z_near));
+    }
+    corners
+}
+
+/// Orthographic light view and projection that contain the given frustum slice.
+///
+///     The slice is bounded by a sphere instead of a box, so the projection's size doesn't change
+///     when the camera rotates, and the projection is moved in whole texels, so that shadow edges
+///     don't shimmer when the camera moves.
+pub fn fit_cascade(corners: &[Vec3; 8], light_direction: Vec3, resolution: u32) -> Mat4 {
+    let center = corners.iter().fold(Vec3::ZERO, |sum, &corner| sum + corner) / 8.0;
+    let radius = corners
+        .iter()
+        .map(|&corner| corner.distance(center))
+        .fold(0.0_f32, f32::max);
+    // rounded so that floating point noise doesn't change the size every frame
+    let radius = (radius * 16.0).ceil() / 16.0;
+
+    let light_direction = light_direction.normalize();
+    let up = if light_direction.y.abs() > 0.99 {
+        Vec3::Z
+    } else {
+        Vec3::Y
+    };
+    let view = Mat4::look_at_rh(center, center + light_direction, up);
+    let mut projection = Mat4::orthographic_rh(
+        -radius,
+        radius,
+        -radius,
+        radius,
+        -radius * (1.0 + CASTER_DEPTH_EXTENSION),
+        radius,
+    );
+
+    // snap the world origin to a texel, which snaps everything else as well
+    let texels_per_unit = resolution as f32 / 2.0;
+    let origin = (projection * view) * Vec4::W;
+    let offset_x =
+        ((origin.x * texels_per_unit).round() - origin.x * texels_per_unit) / texels_per_unit;
+    let offset_y =
+        ((origin.y * texels_per_unit).round() - origin.y * texels_per_unit) / texels_per_unit;
+    projection.w_axis.x += offset_x;
+    projection.w_axis.y += offset_y;
+
+    projection * view
+}
+
+/// Splits the camera frustum up to `max_distance` into cascades and fits the light's
+/// projection to each of them.
+#[allow(clippy::too_many_arguments)]
+pub fn compute_cascades(
+    view: Mat4,
+    projection: Mat4,
+    (z_near, z_far): (f32, f32),
+    max_distance: f32,
+    cascade_count: usize,
+    split_lambda: f32,
+    light_direction: Vec3,
+    resolution: u32,
+) -> Vec<Cascade> {
+    let shadow_far = z_far.min(max_distance);
+    let proj_view = projection * view;
+
+    let splits = cascade_splits(z_near, shadow_far, cascade_count, split_lambda);
+    std::iter::once(z_near)
+        .chain(splits.iter().copied())
+        .zip(splits.iter().copied())
+        .map(|(slice_near, slice_far)| {
+            let corners =
+                frustum_slice_corners(proj_view, (z_near, z_far), (slice_near, slice_far));
+            Cascade {
+                view_proj: fit_cascade(&corners, light_direction, resolution),
+                split_distance: slice_far,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn camera() -> (Mat4, Mat4) {
+        let view = Mat4::look_at_rh(
+            Vec3::new(0.0, 10.0, -2.0),
+            Vec3::new(0.0, 10.0, -1.0),
+            Vec3::Y,
+        );
+        let projection = Mat4::perspective_rh(70.0_f32.to_radians(), 16.0 / 9.0, 0.1, 200.0);
+        (view, projection)
+    }
+
+    #[test]
+    fn splits_increase_up_to_the_far_plane() {
+        let splits = cascade_splits(0.1, 100.0, 4, 0.75);
+
+        assert_eq!(splits.len(), 4);
+        assert!(splits.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!((splits[3] - 100.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn uniform_splits() {
+        let splits = cascade_splits(0.0, 100.0, 4, 0.0);
+        for (split, expected) in splits.into_iter().zip([25.0, 50.0, 75.0, 100.0]) {
+            assert!((split - expected).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn cascade_contains_its_frustum_slice() {
+        let (view, projection) = camera();
+        let light_direction = Vec3::new(-0.4, -1.0, -0.3);
+
+        for (slice_near, slice_far) in [(0.1, 5.0), (5.0, 30.0), (30.0, 100.0)] {
+            let corners =
+                frustum_slice_corners(projection * view, (0.1, 200.0), (slice_near, slice_far));
+            let view_proj = fit_cascade(&corners, light_direction, 2048);
+
+            for corner in corners {
+                let clip = view_proj.project_point3(corner);
+                assert!(
+                    clip.x.abs() <= 1.0 + 1e-3,
+                    "{:?} outside of the cascade",
+                    clip
+                );
+                assert!(
+                    clip.y.abs() <= 1.0 + 1e-3,
+                    "{:?} outside of the cascade",
+                    clip
+                );
+                assert!(
+                    (0.0..=1.0).contains(&clip.z),
+                    "{:?} outside of the cascade",
+                    clip
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn cascade_moves_in_whole_texels() {
+        let resolution = 1024;
+        let corners = frustum_slice_corners(camera().1 * camera().0, (0.1, 200.0), (0.1, 10.0));
+        let moved = corners.map(|corner| corner + Vec3::new(0.013, 0.0, 0.007));
+
+        let origin =
+            |view_proj: Mat4| view_proj.project_point3(Vec3::ZERO) * resolution as f32 / 2.0;
+        let light_direction = Vec3::new(-0.4, -1.0, -0.3);
+        for view_proj in [
+            fit_cascade(&corners, light_direction, resolution),
+            fit_cascade(&moved, light_direction, resolution),
+        ] {
+            let origin = origin(view_proj);
+            assert!((origin.x - origin.x.round()).abs() < 1e-2);
+            assert!((origin.y - origin.y.round()).abs() < 1e-2);
+        }
+    }
+
+    #[test]
+    fn cascades_end_at_the_max_distance() {
+        let (view, projection) = camera();
+        let cascades = compute_cascades(
+            view,
+            projection,
+            (0.1, 200.0),
+            50.0,
+            3,
+            0.5,
+            Vec3::new(0.0, -1.0, 0.0),
+            2048,
+        );
+
+        assert_eq!(cascades.len(), 3);
+        assert!((cascades[2].split_distance - 50.0).abs() < 1e-3);
+    }
+}
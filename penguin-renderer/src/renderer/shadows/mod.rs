@@ -0,0 +1,5 @@
+mod cascades;
+pub use cascades::*;
+
+mod shadow_map;
+pub use shadow_map::*;
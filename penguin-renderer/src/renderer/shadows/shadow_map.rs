@@ -0,0 +1,457 @@
+use crate::math_vk_format::{Mat4, Vec3};
+use crate::renderer::gpu_data::GPUShadowData;
+use crate::renderer::memory::util::as_u8_slice;
+use crate::renderer::memory::UniformRingBuffer;
+use crate::renderer::render_graph::{ImageDesc, PassContext};
+use crate::renderer::render_objects::Vertex;
+use crate::renderer::resources::RenderObjectsResource;
+use crate::renderer::shadows::compute_cascades;
+use crate::renderer::vk_types::{
+    DescriptorPool, DescriptorSetLayout, Pipeline, PipelineLayout, VkContext,
+};
+use ash::vk;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ShadowSettings {
+    /// Width and height in texels of each cascade's part of the shadow map.
+    pub resolution: u32,
+    pub cascade_count: u32,
+    /// View distance up to which shadows are drawn.
+    pub max_distance: f32,
+    /// How the cascades split the view distance, from 0 (evenly) to 1 (logarithmically).
+    pub split_lambda: f32,
+    /// Depth bias added to every shadow caster, in units of the smallest depth difference.
+    /// Only read at startup.
+    pub depth_bias_constant: f32,
+    /// Depth bias scaled by the slope of the shadow caster. Only read at startup.
+    pub depth_bias_slope: f32,
+    /// Texels sampled in each direction around a pixel's shadow map position. 0 only uses the
+    /// sampler's bilinear filtering.
+    pub pcf_radius: u32,
+}
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self {
+            resolution: 2048,
+            cascade_count: 4,
+            max_distance: 100.0,
+            split_lambda: 0.75,
+            depth_bias_constant: 1.25,
+            depth_bias_slope: 1.75,
+            pcf_radius: 1,
+        }
+    }
+}
+impl ShadowSettings {
+    /// Clamps the settings to values the renderer supports.
+    pub fn validated(mut self) -> Self {
+        let cascade_count = self
+            .cascade_count
+            .clamp(1, crate::config::MAX_SHADOW_CASCADES as u32);
+        if cascade_count != self.cascade_count {
+            log::warn!(
+                "shadow cascade_count {} is out of range, using {}",
+                self.cascade_count,
+                cascade_count
+            );
+            self.cascade_count = cascade_count;
+        }
+
+        let pcf_radius = self.pcf_radius.min(crate::config::MAX_SHADOW_PCF_RADIUS);
+        if pcf_radius != self.pcf_radius {
+            log::warn!(
+                "shadow pcf_radius {} is out of range, using {}",
+                self.pcf_radius,
+                pcf_radius
+            );
+            self.pcf_radius = pcf_radius;
+        }
+
+        self.resolution = self.resolution.max(1);
+        self.split_lambda = self.split_lambda.clamp(0.0, 1.0);
+        self
+    }
+}
+
+struct ShadowMapObjects {
+    pipeline: Pipeline,
+    /// only used to create the pipeline, the graph's render passes are compatible with it
+    render_pass: vk::RenderPass,
+    set_layout: DescriptorSetLayout,
+    descriptor_pool: DescriptorPool,
+    /// one per frame in flight
+    sets: Vec<vk::DescriptorSet>,
+    sampler: vk::Sampler,
+}
+
+/// Cascaded shadow map of the directional light.
+///
+///     The cascades are tiles next to each other in one depth image, rendered in a single pass
+///     with a viewport per tile. The forward pass reads the shadow map through set 3, with the
+///     cascades' matrices at binding 0 and the shadow map with a comparison sampler at binding 1.
+#[derive(Default)]
+pub struct ShadowResource {
+    pub settings: ShadowSettings,
+    /// Direction the light shines in, set from the shadow casting `Light` every frame.
+    pub light_direction: Vec3,
+    objects: Option<ShadowMapObjects>,
+}
+
+impl ShadowResource {
+    /// `object_set_layout` is the layout of the per frame object buffer set, which the shadow
+    /// pass binds at set 0.
+    pub fn init(
+        &mut self,
+        context: &VkContext,
+        object_set_layout: vk::DescriptorSetLayout,
+        uniform_ring_buffer: &UniformRingBuffer,
+        frames_in_flight: usize,
+        settings: ShadowSettings,
+    ) {
+        self.settings = settings;
+        // replaced by the direction of the shadow casting light once there is one
+        self.light_direction = Vec3::new(-0.4, -1.0, -0.3).normalize();
+
+        // the tiles are next to each other horizontally
+        let max_image_dimension = context.pd_device_properties().limits.max_image_dimension2_d;
+        let max_resolution = max_image_dimension / settings.cascade_count;
+        if settings.resolution > max_resolution {
+            log::warn!(
+                "shadow resolution {} doesn't fit {} cascades into one image, using {}",
+                settings.resolution,
+                settings.cascade_count,
+                max_resolution
+            );
+            self.settings.resolution = max_resolution;
+        }
+
+        let render_pass = depth_render_pass(context, crate::config::SHADOW_MAP_FORMAT);
+
+        let pipeline_layout = PipelineLayout::builder()
+            .add_layout(object_set_layout)
+            .push_constant_range(
+                vk::ShaderStageFlags::VERTEX,
+                0,
+                std::mem::size_of::<Mat4>() as _,
+            )
+            .build(context);
+
+        // the extent is ignored with a dynamic viewport
+        let pipeline = Pipeline::builder(
+            context,
+            vk::Extent2D {
+                width: 1,
+                height: 1,
+            },
+            render_pass,
+            vk::PipelineBindPoint::GRAPHICS,
+        )
+        .shaders(&["shadow.vert"])
+        .vertex_input(
+            &Vertex::create_binding_descriptions(0),
+            &Vertex::create_attribute_descriptions(0),
+        )
+        .pipeline_layout(pipeline_layout.handle)
+        .cull_mode(vk::CullModeFlags::NONE)
+        .depth_bias(settings.depth_bias_constant, settings.depth_bias_slope)
+        .depth_only()
+        .dynamic_viewport()
+        .build();
+
+        let set_layout = DescriptorSetLayout::builder()
+            .layout_binding(
+                vk::DescriptorSetLayoutBinding::builder()
+                    .binding(0)
+                    .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC)
+                    .descriptor_count(1)
+                    .stage_flags(vk::ShaderStageFlags::FRAGMENT),
+            )
+            .layout_binding(
+                vk::DescriptorSetLayoutBinding::builder()
+                    .binding(1)
+                    .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                    .descriptor_count(1)
+                    .stage_flags(vk::ShaderStageFlags::FRAGMENT),
+            )
+            .build(context);
+
+        let set_count = frames_in_flight as u32;
+        let descriptor_pool = DescriptorPool::from_sizes(
+            context,
+            set_count,
+            vk::DescriptorPoolCreateFlags::empty(),
+            &[
+                vk::DescriptorPoolSize::builder()
+                    .ty(vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC)
+                    .descriptor_count(set_count)
+                    .build(),
+                vk::DescriptorPoolSize::builder()
+                    .ty(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                    .descriptor_count(set_count)
+                    .build(),
+            ],
+        );
+
+        let set_layouts = vec![set_layout.handle; set_count as usize];
+        let allocate_info = vk::DescriptorSetAllocateInfo::builder()
+            .descriptor_pool(descriptor_pool.handle)
+            .set_layouts(&set_layouts);
+        let sets = unsafe { context.device.allocate_descriptor_sets(&allocate_info) }
+            .expect("Couldn't allocate shadow sets");
+
+        // the shadow data is pushed into the ring buffer every frame, only its offset changes
+        let buffer_info = uniform_ring_buffer.descriptor_buffer_info::<GPUShadowData>();
+        let writes = sets
+            .iter()
+            .map(|&set| {
+                vk::WriteDescriptorSet::builder()
+                    .dst_set(set)
+                    .dst_binding(0)
+                    .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC)
+                    .buffer_info(std::slice::from_ref(&buffer_info))
+                    .build()
+            })
+            .collect::<Vec<_>>();
+        unsafe { context.device.update_descriptor_sets(&writes, &[]) };
+
+        self.objects = Some(ShadowMapObjects {
+            pipeline,
+            render_pass,
+            set_layout,
+            descriptor_pool,
+            sets,
+            sampler: comparison_sampler(context),
+        });
+    }
+
+    pub fn destroy(&mut self, context: &VkContext) {
+        if let Some(mut objects) = self.objects.take() {
+            objects.pipeline.destroy(context);
+            unsafe {
+                context
+                    .device
+                    .destroy_render_pass(objects.render_pass, None);
+                context.device.destroy_sampler(objects.sampler, None);
+            }
+            objects.descriptor_pool.destroy(context);
+            objects.set_layout.destroy(context);
+        }
+    }
+
+    fn objects(&self) -> &ShadowMapObjects {
+        self.objects.as_ref().expect("shadows aren't initialized")
+    }
+
+    /// Layout of the set the forward pass reads the shadow map through.
+    pub fn set_layout(&self) -> vk::DescriptorSetLayout {
+        self.objects().set_layout.handle
+    }
+
+    /// The shadow map, with the cascades' tiles next to each other.
+    pub fn shadow_map_desc(&self) -> ImageDesc {
+        ImageDesc::new_2d(
+            crate::config::SHADOW_MAP_FORMAT,
+            vk::Extent2D {
+                width: self.settings.resolution * self.settings.cascade_count,
+                height: self.settings.resolution,
+            },
+        )
+    }
+
+    /// Fits the cascades to the camera. `z_near` and `z_far` are the planes of `projection`.
+    pub fn gpu_data(
+        &self,
+        view: Mat4,
+        projection: Mat4,
+        (z_near, z_far): (f32, f32),
+    ) -> GPUShadowData {
+        let cascades = compute_cascades(
+            view,
+            projection,
+            (z_near, z_far),
+            self.settings.max_distance,
+            self.settings.cascade_count as _,
+            self.settings.split_lambda,
+            self.light_direction,
+            self.settings.resolution,
+        );
+
+        let mut data = GPUShadowData {
+            cascade_count: cascades.len() as _,
+            pcf_radius: self.settings.pcf_radius,
+            ..Default::default()
+        };
+        for (view_proj, cascade) in data.cascade_view_proj.iter_mut().zip(cascades) {
+            *view_proj = cascade.view_proj;
+        }
+        data
+    }
+
+    /// Draws every render object into each cascade's tile. Records into a pass with the shadow
+    /// map as its depth attachment.
+    pub fn draw(
+        &self,
+        pass_context: &PassContext,
+        object_set: vk::DescriptorSet,
+        shadow_data: &GPUShadowData,
+        render_objects: &RenderObjectsResource,
+    ) {
+        let objects = self.objects();
+        let context = pass_context.context;
+        let command_buffer = pass_context.command_buffer;
+        let resolution = self.settings.resolution;
+
+        unsafe {
+            context.device.cmd_bind_pipeline(
+                command_buffer,
+                objects.pipeline.pipeline_bind_point,
+                objects.pipeline.handle,
+            );
+            context.device.cmd_bind_descriptor_sets(
+                command_buffer,
+                objects.pipeline.pipeline_bind_point,
+                objects.pipeline.pipeline_layout,
+                0,
+                &[object_set],
+                &[],
+            );
+
+            for (cascade_index, view_proj) in shadow_data
+                .cascade_view_proj
+                .iter()
+                .take(shadow_data.cascade_count as usize)
+                .enumerate()
+            {
+                let x = cascade_index as u32 * resolution;
+                let viewport = vk::Viewport {
+                    x: x as f32,
+                    y: 0.0,
+                    width: resolution as f32,
+                    height: resolution as f32,
+                    min_depth: 0.0,
+                    max_depth: 1.0,
+                };
+                let scissor = vk::Rect2D {
+                    offset: vk::Offset2D { x: x as _, y: 0 },
+                    extent: vk::Extent2D {
+                        width: resolution,
+                        height: resolution,
+                    },
+                };
+                context
+                    .device
+                    .cmd_set_viewport(command_buffer, 0, &[viewport]);
+                context
+                    .device
+                    .cmd_set_scissor(command_buffer, 0, &[scissor]);
+                context.device.cmd_push_constants(
+                    command_buffer,
+                    objects.pipeline.pipeline_layout,
+                    vk::ShaderStageFlags::VERTEX,
+                    0,
+                    as_u8_slice(view_proj),
+                );
+
+                // the instance index selects the object's data in the object buffer
+                for (object_index, render_object) in render_objects.drawn().iter().enumerate() {
+                    context.device.cmd_bind_vertex_buffers(
+                        command_buffer,
+                        0,
+                        &[render_object.mesh.vertex_buffer.handle],
+                        &[0],
+                    );
+                    context.device.cmd_draw(
+                        command_buffer,
+                        render_object.mesh.vertex_count as u32,
+                        1,
+                        0,
+                        object_index as u32,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Points the frame's set at this frame's shadow map and returns the set. The shadow map
+    /// has to be in SHADER_READ_ONLY_OPTIMAL when the set is used.
+    pub fn write_shadow_map(
+        &self,
+        context: &VkContext,
+        frame_index: usize,
+        shadow_map: vk::ImageView,
+    ) -> vk::DescriptorSet {
+        let objects = self.objects();
+        let set = objects.sets[frame_index];
+
+        // the frame's previous use of the set finished when its fence was waited on
+        let image_info = vk::DescriptorImageInfo::builder()
+            .sampler(objects.sampler)
+            .image_view(shadow_map)
+            .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+            .build();
+        let write = vk::WriteDescriptorSet::builder()
+            .dst_set(set)
+            .dst_binding(1)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .image_info(std::slice::from_ref(&image_info))
+            .build();
+        unsafe { context.device.update_descriptor_sets(&[write], &[]) };
+
+        set
+    }
+}
+
+/// Depth only render pass the pipeline is created against.
+fn depth_render_pass(context: &VkContext, format: vk::Format) -> vk::RenderPass {
+    let attachments = [vk::AttachmentDescription::builder()
+        .format(format)
+        .samples(vk::SampleCountFlags::TYPE_1)
+        .load_op(vk::AttachmentLoadOp::CLEAR)
+        .store_op(vk::AttachmentStoreOp::STORE)
+        .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+        .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+        .initial_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+        .final_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+        .build()];
+
+    let depth_attachment_ref = vk::AttachmentReference {
+        attachment: 0,
+        layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+    };
+
+    let subpasses = [vk::SubpassDescription::builder()
+        .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+        .depth_stencil_attachment(&depth_attachment_ref)
+        .build()];
+
+    let render_pass_create_info = vk::RenderPassCreateInfo::builder()
+        .attachments(&attachments)
+        .subpasses(&subpasses);
+
+    unsafe {
+        context
+            .device
+            .create_render_pass(&render_pass_create_info, None)
+    }
+    .expect("Couldn't create shadow render pass")
+}
+
+/// Compares the depth it's sampled with against the shadow map, and filters the results of the
+/// four nearest texels.
+fn comparison_sampler(context: &VkContext) -> vk::Sampler {
+    let filter = vk::Filter::LINEAR;
+    let address_mode = vk::SamplerAddressMode::CLAMP_TO_EDGE;
+    let sampler_create_info = vk::SamplerCreateInfo::builder()
+        .mag_filter(filter)
+        .min_filter(filter)
+        .address_mode_u(address_mode)
+        .address_mode_v(address_mode)
+        .address_mode_w(address_mode)
+        .compare_enable(true)
+        .compare_op(vk::CompareOp::LESS_OR_EQUAL);
+
+    unsafe { context.device.create_sampler(&sampler_create_info, None) }
+        .expect("couldn't create shadow comparison sampler")
+}
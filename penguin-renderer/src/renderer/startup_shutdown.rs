@@ -1,15 +1,26 @@
+use crate::config::RendererConfig;
 use crate::math_vk_format::Vec3;
 use crate::renderer::frame_data::{FrameData, FrameDataContainer};
-use crate::renderer::gpu_data::{GPUCameraData, GPUObjectData};
+use crate::renderer::gpu_data::{
+    GPUCameraData, GPUFrameData, GPULight, GPUMaterialData, GPUObjectData,
+};
+use crate::renderer::lights::Light;
 use crate::renderer::memory::{
-    AllocatedBuffer, AllocatedBufferCreateInfo, MemoryUsage, UploadContext,
+    AllocatedBuffer, AllocatedBufferCreateInfo, MemoryUsage, UniformRingBuffer, UploadContext,
+};
+use crate::renderer::post_process::PostProcessResource;
+use crate::renderer::profiling::GpuProfilerResource;
+use crate::renderer::render_graph::RenderGraphResources;
+use crate::renderer::render_objects::{
+    MaterialParameterLayout, PbrMap, PbrMaterial, RenderObject, RenderQueue, Vertex,
 };
-use crate::renderer::render_objects::{RenderObject, Vertex};
 use crate::renderer::resources::{
     MaterialsResource, MeshesResource, RenderObjectsResource, TexturesResource,
 };
+use crate::renderer::shadows::ShadowResource;
+use crate::renderer::sync::TimelinePoint;
 use crate::renderer::vk_types::descriptor_sets::DescriptorSetContainer;
-use crate::renderer::vk_types::resources::DescriptorSetsResource;
+use crate::renderer::vk_types::resources::{BindlessTexturesResource, DescriptorSetsResource};
 use crate::renderer::vk_types::*;
 use ash::vk;
 use penguin_app::ecs::*;
@@ -19,93 +30,42 @@ use uniform_buffer::*;
 mod uniform_buffer {
     use super::*;
 
-    struct UniformBuffer<T> {
-
-        marker: std::marker::PhantomData<T>,
-    }
-    impl<T> UniformBuffer<T> {
-        fn create_descriptor_set(
-            context: &VkContext,
-            descriptor_pool: &DescriptorPool,
-            binding: u32,
-        ) -> DescriptorSet {
-            DescriptorSet::builder()
-                .layout(
-                    DescriptorSetLayout::builder()
-                        .layout_binding(
-                            vk::DescriptorSetLayoutBinding::builder()
-                                .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
-                                .descriptor_count(1)
-                                .binding(binding)
-                                .stage_flags(vk::ShaderStageFlags::VERTEX),
-                        )
-                        .build(context),
-                )
-                .build(context, descriptor_pool)
-                .expect("couldn't alloc uniform buffer set")
-        }
-    }
-
-    pub fn uniform_buffer_desc_set(context: &VkContext, pool: &DescriptorPool) -> DescriptorSet {
-        DescriptorSet::builder()
-            .layout(
-                DescriptorSetLayout::builder()
-                    .layout_binding(
-                        vk::DescriptorSetLayoutBinding::builder()
-                            .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
-                            .descriptor_count(1)
-                            .binding(0)
-                            .stage_flags(vk::ShaderStageFlags::VERTEX),
-                    )
-                    .build(context),
+    /// Camera data at binding 0, frame data at binding 1 and the lights at binding 2, all read
+    /// from the uniform ring buffer through dynamic offsets. The fragment shaders read the
+    /// camera position for specular lighting.
+    pub fn uniform_buffer_desc_set(
+        context: &VkContext,
+        resource: &mut DescriptorSetsResource,
+        [camera_buffer_info, frame_buffer_info, lights_buffer_info]: &[vk::DescriptorBufferInfo; 3],
+    ) -> DescriptorSet {
+        resource
+            .builder()
+            .bind_buffer(
+                std::slice::from_ref(camera_buffer_info),
+                DescriptorBuilderBindParams {
+                    binding: 0,
+                    ty: vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC,
+                    shader_stages: vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+                },
             )
-            .build(context, pool)
-            .expect("couldn't alloc uniform buffer set")
-    }
-
-    //pub fn update_ubuffer_desc_info(
-    //    context: &VkContext,
-    //    desc_buffer_info: &mut vk::DescriptorBufferInfo,
-    //    frame_index: usize,
-    //) {
-    //    desc_buffer_info.offset =
-    //        (context.packed_uniform_buffer_range::<GPUCameraData>() * (frame_index as u64)) as _;
-    //}
-
-    pub fn uniform_buffer_write_set(
-        resource: &DescriptorSetsResource,
-        desc_buffer_info: &[vk::DescriptorBufferInfo],
-    ) -> vk::WriteDescriptorSet {
-        vk::WriteDescriptorSet::builder()
-            .dst_set(resource.get_set(0).handle())
-            .dst_binding(0)
-            .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
-            .buffer_info(&desc_buffer_info)
-            .build()
-    }
-
-    pub fn uniform_buffer(context: &VkContext) -> (AllocatedBuffer, vk::DescriptorBufferInfo) {
-        let packed_range = context.packed_uniform_buffer_range::<GPUCameraData>();
-        let size = packed_range * (crate::config::MAX_FRAMES_COUNT) as u64;
-        let buffer = AllocatedBuffer::create_buffer(
-            &context,
-            AllocatedBufferCreateInfo {
-                initial_data: &(0..size).map(|_| 0_u8).collect::<Vec<u8>>(),
-                buffer_size: size,
-                buffer_usage: vk::BufferUsageFlags::UNIFORM_BUFFER,
-                memory_usage: MemoryUsage::GpuMemCpuWritable,
-                sharing_mode: vk::SharingMode::EXCLUSIVE,
-                memory_map_flags: vk::MemoryMapFlags::empty(),
-            },
-        );
-
-        let desc_buffer_info = vk::DescriptorBufferInfo::builder()
-            .buffer(buffer.handle)
-            .range(std::mem::size_of::<GPUCameraData>() as _)
-            // the offset depends on the frame, see when initiating the frame data
-            .build();
-
-        (buffer, desc_buffer_info)
+            .bind_buffer(
+                std::slice::from_ref(frame_buffer_info),
+                DescriptorBuilderBindParams {
+                    binding: 1,
+                    ty: vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC,
+                    shader_stages: vk::ShaderStageFlags::FRAGMENT,
+                },
+            )
+            .bind_buffer(
+                std::slice::from_ref(lights_buffer_info),
+                DescriptorBuilderBindParams {
+                    binding: 2,
+                    ty: vk::DescriptorType::STORAGE_BUFFER_DYNAMIC,
+                    shader_stages: vk::ShaderStageFlags::FRAGMENT,
+                },
+            )
+            .build(context)
+            .expect("couldn't build uniform buffer set")
     }
 }
 
@@ -113,33 +73,23 @@ use storage_buffer::*;
 mod storage_buffer {
     use super::*;
 
-    pub fn storage_buffer_desc_set(context: &VkContext, pool: &DescriptorPool) -> DescriptorSet {
-        DescriptorSet::builder()
-            .layout(
-                DescriptorSetLayout::builder()
-                    .layout_binding(
-                        vk::DescriptorSetLayoutBinding::builder()
-                            .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
-                            .descriptor_count(1)
-                            .binding(0)
-                            .stage_flags(vk::ShaderStageFlags::VERTEX),
-                    )
-                    .build(context),
+    pub fn storage_buffer_desc_set(
+        context: &VkContext,
+        resource: &mut DescriptorSetsResource,
+        desc_buffer_info: &vk::DescriptorBufferInfo,
+    ) -> DescriptorSet {
+        resource
+            .builder()
+            .bind_buffer(
+                std::slice::from_ref(desc_buffer_info),
+                DescriptorBuilderBindParams {
+                    binding: 0,
+                    ty: vk::DescriptorType::STORAGE_BUFFER,
+                    shader_stages: vk::ShaderStageFlags::VERTEX,
+                },
             )
-            .build(context, pool)
-            .expect("couldn't allo storage set")
-    }
-
-    pub fn storage_buffer_write_set(
-        resource: &DescriptorSetsResource,
-        desc_buffer_info: &[vk::DescriptorBufferInfo],
-    ) -> vk::WriteDescriptorSet {
-        vk::WriteDescriptorSet::builder()
-            .dst_set(resource.get_set(1).handle())
-            .dst_binding(0)
-            .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
-            .buffer_info(desc_buffer_info)
-            .build()
+            .build(context)
+            .expect("couldn't build storage buffer set")
     }
 
     pub fn storage_buffer(context: &VkContext) -> (AllocatedBuffer, vk::DescriptorBufferInfo) {
@@ -170,23 +120,6 @@ use image_sampler::*;
 mod image_sampler {
     use super::*;
 
-    pub fn single_texture_desc_set(context: &VkContext, pool: &DescriptorPool) -> DescriptorSet {
-        DescriptorSet::builder()
-            .layout(
-                DescriptorSetLayout::builder()
-                    .layout_binding(
-                        vk::DescriptorSetLayoutBinding::builder()
-                            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
-                            .descriptor_count(1)
-                            .binding(0)
-                            .stage_flags(vk::ShaderStageFlags::FRAGMENT),
-                    )
-                    .build(context),
-            )
-            .build(context, pool)
-            .expect("couldn't alloc single texture set")
-    }
-
     pub fn blocky_sampler(context: &VkContext) -> vk::Sampler {
         let filter = vk::Filter::NEAREST;
         let address_mode = vk::SamplerAddressMode::REPEAT;
@@ -207,19 +140,28 @@ fn textured_pipeline(
     swapchain: &Swapchain,
     render_pass: &RenderPass,
     pipeline_layout: &PipelineLayout,
+    fragment_shader: &str,
+    render_queue: RenderQueue,
 ) -> Pipeline {
-    Pipeline::builder(
+    let builder = Pipeline::builder(
         &context,
         swapchain.extent,
         render_pass.handle,
         vk::PipelineBindPoint::GRAPHICS,
     )
-    .shaders(&["simple.vert", "textured.frag"])
+    .shaders(&["simple.vert", fragment_shader])
     .vertex_input(
         &Vertex::create_binding_descriptions(0),
         &Vertex::create_attribute_descriptions(0),
     )
     .pipeline_layout(pipeline_layout.handle)
+    .samples(render_pass.samples)
+    .depth_compare_op(render_pass.depth_compare_op());
+
+    match render_queue {
+        RenderQueue::Opaque | RenderQueue::AlphaTest => builder,
+        RenderQueue::Transparent => builder.alpha_blending().no_depth_write(),
+    }
     .build()
 }
 
@@ -232,10 +174,20 @@ pub fn renderer_startup(
     #[resource] render_objects: &mut RenderObjectsResource,
     #[resource] descriptor_sets_resource: &mut DescriptorSetsResource,
     #[resource] textures: &mut TexturesResource,
+    #[resource] bindless_textures: &mut BindlessTexturesResource,
+    #[resource] post_process: &mut PostProcessResource,
+    #[resource] shadows: &mut ShadowResource,
+    #[resource] gpu_profiler: &mut GpuProfilerResource,
+    #[resource] renderer_config: &RendererConfig,
 ) {
     log::trace!("RENDERER STARTUP STARTED!");
     // /------------------ CONTEXT  -----------------------------------------------------
-    let context = VkContext::init(window, window.logger_level);
+    let device_selector =
+        DeviceSelector::from_env_or(renderer_config.physical_device.as_deref());
+    let context = VkContext::init(window, window.logger_level, device_selector.as_ref());
+    context
+        .validation_report()
+        .configure(&renderer_config.validation);
     // ///////////////////////////////////////
 
     let upload_context = UploadContext::init(&context);
@@ -244,116 +196,53 @@ pub fn renderer_startup(
 
     let VkComponents {
         swapchain,
-        depth_image,
         render_pass,
-        frame_buffers,
         //descriptor_pool,
-    } = init_vk_components(window, &context);
-    // ///////////////////////////////////////
-
-    descriptor_sets_resource.init_pool(DescriptorPool::from_sizes(
+    } = init_vk_components(
+        window,
         &context,
-        20,
-        vk::DescriptorPoolCreateFlags::empty(),
-        &[
-            vk::DescriptorPoolSize {
-                descriptor_count: 10,
-                ty: vk::DescriptorType::UNIFORM_BUFFER,
-            },
-            vk::DescriptorPoolSize {
-                descriptor_count: 10,
-                ty: vk::DescriptorType::STORAGE_BUFFER,
-            },
-            vk::DescriptorPoolSize {
-                descriptor_count: 10,
-                ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
-            },
-        ],
-    ));
-    let descriptor_pool = descriptor_sets_resource.pool;
-
-    /////////////////////
-
-    // * descriptor set layouts
-    //
-    let uniform_buffer_desc_set = uniform_buffer_desc_set(&context, &descriptor_pool);
-    let storage_buffer_desc_set = storage_buffer_desc_set(&context, &descriptor_pool);
-    let image_sampler_desc_set = single_texture_desc_set(&context, &descriptor_pool);
-
-    let pipeline_layout = PipelineLayout::builder()
-        .add_layout(uniform_buffer_desc_set.layout.handle)
-        .add_layout(storage_buffer_desc_set.layout.handle)
-        .add_layout(image_sampler_desc_set.layout.handle)
-        .build(&context);
-
-    // textured pipeline
-    let pipeline = textured_pipeline(&context, &swapchain, &render_pass, &pipeline_layout);
-
-    ////////////////////////////////////////////
-    let (uniform_buffer, uniform_desc_buffer_info) = uniform_buffer(&context);
-
-    let uniform_buffer_descriptor_set_container = DescriptorSetContainer {
-        set: uniform_buffer_desc_set.clone(),
-        pipeline_layout: pipeline_layout.handle,
-        allocated_buffers: vec![uniform_buffer],
-        pipeline_bind_point: vk::PipelineBindPoint::GRAPHICS,
-    };
-
-    ////////////////////////////////////////////
-    let (storage_buffer, storage_buffer_desc_info) = storage_buffer(&context);
-
-    let storage_buffer_descriptor_set_container = DescriptorSetContainer {
-        set: storage_buffer_desc_set.clone(),
-        pipeline_layout: pipeline_layout.handle,
-        allocated_buffers: vec![storage_buffer],
-        pipeline_bind_point: vk::PipelineBindPoint::GRAPHICS,
-    };
-
-    ////////////////////////////////////////////
+        context.supported_sample_count(renderer_config.msaa_samples),
+        renderer_config.depth,
+    );
+    // ///////////////////////////////////////
 
     // /------------------ RESOURCES  -----------------------------------------------------
     meshes.insert_from_file(&context, &upload_context, ("monkey", "lost_empire.obj"));
-    materials.insert(("default", pipeline));
     textures.insert_from_file(&context, &upload_context, ("lost_emp", "dusk.jpeg"));
+    textures.insert_pbr_fallbacks(&context, &upload_context);
 
-    let sampler = blocky_sampler(&context);
-    let single_texture_desc_set = single_texture_desc_set(&context, &descriptor_pool);
-    let texture = textures.get("lost_emp");
-
-    let desc_image_info = vk::DescriptorImageInfo::builder()
-        .sampler(sampler)
-        .image_view(texture.image_view)
-        .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
-        .build();
-
-    let single_texture_write_set = vk::WriteDescriptorSet::builder()
-        .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
-        .dst_set(single_texture_desc_set.handle)
-        .image_info(&[desc_image_info])
-        .dst_binding(0)
-        .build();
-
-    // todo make better
-    let single_texture_desc_set_container = DescriptorSetContainer {
-        set: single_texture_desc_set.clone(),
-        pipeline_layout: pipeline_layout.handle,
-        allocated_buffers: vec![],
-        pipeline_bind_point: vk::PipelineBindPoint::GRAPHICS,
-    };
+    // * per frame resources
+    //
+    let frames_in_flight = renderer_config.frames_in_flight;
 
-    descriptor_sets_resource.sets = vec![
-        uniform_buffer_descriptor_set_container, // 0
-        storage_buffer_descriptor_set_container, // 1
-        single_texture_desc_set_container,       // 2
+    let uniform_ring_buffer = UniformRingBuffer::new(
+        &context,
+        crate::config::UNIFORM_RING_BUFFER_SIZE,
+        frames_in_flight,
+    );
+    let uniform_desc_buffer_infos = [
+        uniform_ring_buffer.descriptor_buffer_info::<GPUCameraData>(),
+        uniform_ring_buffer.descriptor_buffer_info::<GPUFrameData>(),
+        uniform_ring_buffer.descriptor_buffer_info::<[GPULight; crate::config::MAX_LIGHTS]>(),
     ];
 
+    let render_graph_resources = RenderGraphResources::new(frames_in_flight);
+
+    post_process.init(
+        &context,
+        &upload_context,
+        &swapchain,
+        frames_in_flight,
+        renderer_config.tonemap_settings(),
+        &renderer_config.post_process,
+    );
+
     let command_pool = context.alloc_command_pool(
         context.physical_device.graphics_queue_index,
         vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER,
     );
 
-    let command_buffers =
-        context.allocate_command_buffers(command_pool, crate::config::MAX_FRAMES_COUNT as _);
+    let command_buffers = context.allocate_command_buffers(command_pool, frames_in_flight as _);
 
     let frame_datas = FrameDataContainer::new(
         command_pool,
@@ -361,68 +250,168 @@ pub fn renderer_startup(
             .into_iter()
             .enumerate()
             .map(|(frame_index, command_buffer)| {
-                // fence --------------
-                let render_fence = context.create_fence(vk::FenceCreateFlags::SIGNALED);
-
                 // semaphores --------------
                 let rendering_complete_semaphore =
                     context.create_semaphore(vk::SemaphoreCreateFlags::empty());
                 let presenting_complete_semaphore =
                     context.create_semaphore(vk::SemaphoreCreateFlags::empty());
 
-                // write sets
-                // *
-                //update_ubuffer_desc_info(&context, &mut uniform_desc_buffer_info, frame_index);
-
-                let write_sets = [
-                    // set 0, binding 0
-                    uniform_buffer_write_set(
-                        &descriptor_sets_resource,
-                        &[uniform_desc_buffer_info],
-                    ),
-                    // set 1, binding 0
-                    storage_buffer_write_set(
-                        &descriptor_sets_resource,
-                        &[storage_buffer_desc_info],
-                    ),
-                    single_texture_write_set.clone(),
-                ];
-
-                unsafe {
-                    context.device.update_descriptor_sets(&write_sets, &[]);
-                }
+                // descriptor sets --------------
+                let camera_descriptor_set = uniform_buffer_desc_set(
+                    &context,
+                    descriptor_sets_resource,
+                    &uniform_desc_buffer_infos,
+                );
+
+                let (object_buffer, object_buffer_desc_info) = storage_buffer(&context);
+                let object_descriptor_set = storage_buffer_desc_set(
+                    &context,
+                    descriptor_sets_resource,
+                    &object_buffer_desc_info,
+                );
+
+                context.set_object_name(command_buffer, &format!("frame {}", frame_index));
+                context.set_object_name(
+                    camera_descriptor_set.handle,
+                    &format!("frame {}:camera", frame_index),
+                );
+                context.set_object_name(object_buffer.handle, &format!("frame {}:objects", frame_index));
+                context.set_object_name(
+                    object_descriptor_set.handle,
+                    &format!("frame {}:objects", frame_index),
+                );
 
                 FrameData {
                     command_buffer,
-                    render_complete_fence: render_fence,
+                    render_complete: TimelinePoint::default(),
                     rendering_complete_semaphore,
                     presenting_complete_semaphore,
-                    uniform_buffer_descriptor_set: uniform_buffer_desc_set.handle,
+                    camera_descriptor_set,
+                    object_buffer,
+                    object_descriptor_set,
+                    descriptor_allocator: DescriptorAllocator::new(),
                     frame_index: frame_index as _,
                 }
             })
             .collect::<Vec<FrameData>>(),
     );
 
+    let sampler = blocky_sampler(&context);
+
+    // either one global texture array indexed through the material, or one set per material
+    bindless_textures.init(&context);
+    let fragment_shader = if bindless_textures.is_enabled() {
+        "lit_bindless.frag"
+    } else {
+        "lit.frag"
+    };
+
+    let lost_empire_material = PbrMaterial {
+        roughness: 0.8,
+        ..Default::default()
+    }
+    .with_map(PbrMap::Albedo, "lost_emp");
+    let material_binding = lost_empire_material.bind_textures(
+        &context,
+        textures,
+        bindless_textures,
+        descriptor_sets_resource,
+        sampler,
+    );
+
+    // the layouts are cached, so they're the same for every frame
+    let frame_data = frame_datas.get_current();
+
+    shadows.init(
+        &context,
+        frame_data.object_descriptor_set.layout.handle,
+        &uniform_ring_buffer,
+        frames_in_flight,
+        renderer_config.shadows,
+    );
+    gpu_profiler.init(&context, frames_in_flight, renderer_config.profiling);
+
+    // every pbr material instance has a texture set with this layout
+    let lit_parameter_layout = MaterialParameterLayout {
+        texture_set_layout: material_binding.texture_set.layout.handle,
+        parameters_size: std::mem::size_of::<GPUMaterialData>() as _,
+    };
+
+    // one template per render queue, they only differ in their blend and depth state
+    for (template_name, render_queue) in [
+        ("lit", RenderQueue::Opaque),
+        ("lit_alpha_test", RenderQueue::AlphaTest),
+        ("lit_transparent", RenderQueue::Transparent),
+    ] {
+        // every pipeline owns its layout
+        let pipeline_layout = PipelineLayout::builder()
+            .add_layout(frame_data.camera_descriptor_set.layout.handle)
+            .add_layout(frame_data.object_descriptor_set.layout.handle)
+            .add_layout(lit_parameter_layout.texture_set_layout)
+            .add_layout(shadows.set_layout())
+            .push_constant_range(
+                vk::ShaderStageFlags::FRAGMENT,
+                0,
+                lit_parameter_layout.parameters_size,
+            )
+            .build(&context);
+
+        let pipeline = textured_pipeline(
+            &context,
+            &swapchain,
+            &render_pass,
+            &pipeline_layout,
+            fragment_shader,
+            render_queue,
+        );
+        materials.insert_template(
+            &context,
+            (template_name, pipeline),
+            lit_parameter_layout,
+            render_queue,
+        );
+    }
+    materials.insert_instance(&context, ("lost_empire", "lit"), material_binding.clone());
+
+    ////////////////////////////////////////////
+    // todo make better
+    let texture_desc_set_container = DescriptorSetContainer {
+        set: material_binding.texture_set,
+        // the lit pipeline layouts are all compatible
+        pipeline_layout: materials.template("lit").pipeline.pipeline_layout,
+        allocated_buffers: vec![],
+        pipeline_bind_point: vk::PipelineBindPoint::GRAPHICS,
+    };
+
+    descriptor_sets_resource.sets = vec![
+        texture_desc_set_container, // 0
+    ];
+
     let render_object = RenderObject {
-        material: materials.get("default").clone(),
+        material: materials.instance("lost_empire").clone(),
         mesh: meshes.get("monkey").clone(),
         translation: Vec3::new(0., 0., 0.),
         name: "bunny".to_owned(),
     };
     render_objects.render_objects.push(render_object);
 
+    // sun
+    cmd.push((Light::directional(
+        Vec3::new(-0.4, -1.0, -0.3),
+        Vec3::new(1.0, 0.95, 0.9),
+        3.0,
+    ),));
+
     let _renderer_entity: Entity = cmd.push((
         context,
         swapchain,
-        depth_image,
         render_pass,
-        frame_buffers,
-        descriptor_pool,
         //
         frame_datas,
         //
         upload_context,
+        uniform_ring_buffer,
+        render_graph_resources,
     ));
 }
 
@@ -432,17 +421,21 @@ pub fn renderer_shutdown(
     query: &mut Query<(
         &mut VkContext,
         &mut Swapchain,
-        &mut FrameBuffers,
         &mut RenderPass,
-        &mut DepthImage,
         //
         &mut FrameDataContainer,
         &mut UploadContext,
+        &mut UniformRingBuffer,
+        &mut RenderGraphResources,
     )>,
     #[resource] meshes: &mut MeshesResource,
     #[resource] materials: &mut MaterialsResource,
     #[resource] descriptor_sets: &mut DescriptorSetsResource,
     #[resource] textures: &mut TexturesResource,
+    #[resource] bindless_textures: &mut BindlessTexturesResource,
+    #[resource] post_process: &mut PostProcessResource,
+    #[resource] shadows: &mut ShadowResource,
+    #[resource] gpu_profiler: &mut GpuProfilerResource,
 ) {
     log::info!("RENDERER SHUTDOWN STARTED!");
 
@@ -450,42 +443,45 @@ pub fn renderer_shutdown(
         |(
             context,
             swapchain,
-            frame_buffers,
             render_pass,
-            depth_image,
             //descriptor_pool,
             frame_datas,
             upload_context,
+            uniform_ring_buffer,
+            render_graph_resources,
         ): (
             &mut VkContext,
             &mut Swapchain,
-            &mut FrameBuffers,
             &mut RenderPass,
-            &mut DepthImage,
             &mut FrameDataContainer,
             //
             &mut UploadContext,
+            &mut UniformRingBuffer,
+            &mut RenderGraphResources,
         )| {
             // wait for device idle..
             context.wait_for_device_idle();
 
             frame_datas.destroy(context);
 
-            frame_buffers.destroy(context);
+            render_graph_resources.destroy(context);
+            post_process.destroy(context);
+            shadows.destroy(context);
+            gpu_profiler.destroy(context);
 
             render_pass.destroy(context);
 
             swapchain.destroy(context);
 
-            depth_image.destroy(context);
-
             // ------------- RESOURCES ----------
             meshes.destroy(context);
             materials.destroy(context);
             descriptor_sets.destroy(context);
+            bindless_textures.destroy(context);
             textures.destroy(context);
             // ------------- END OF RESOURCES ----------
 
+            uniform_ring_buffer.destroy(context);
             upload_context.destroy(context);
 
             context.destroy();
@@ -0,0 +1,296 @@
+use crate::renderer::sync::ResourceUsage;
+use ash::vk;
+
+/// Layout of an image subresource and how it was last accessed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubresourceState {
+    pub layout: vk::ImageLayout,
+    pub usage: ResourceUsage,
+}
+impl SubresourceState {
+    pub const UNDEFINED: Self = Self {
+        layout: vk::ImageLayout::UNDEFINED,
+        usage: ResourceUsage::Undefined,
+    };
+
+    /// Going from `self` to `next` needs no barrier if neither access writes and the layout
+    /// stays the same.
+    fn needs_barrier(&self, next: &Self) -> bool {
+        self.layout != next.layout || self.usage.is_write() || next.usage.is_write()
+    }
+}
+
+/// Current state of every mip level and array layer of an image.
+#[derive(Debug, Clone)]
+pub struct ImageStateTracker {
+    aspect_mask: vk::ImageAspectFlags,
+    mip_levels: u32,
+    array_layers: u32,
+    /// indexed by `mip_level * array_layers + array_layer`
+    states: Vec<SubresourceState>,
+}
+
+impl ImageStateTracker {
+    pub fn new(aspect_mask: vk::ImageAspectFlags, mip_levels: u32, array_layers: u32) -> Self {
+        Self {
+            aspect_mask,
+            mip_levels,
+            array_layers,
+            states: vec![SubresourceState::UNDEFINED; (mip_levels * array_layers) as usize],
+        }
+    }
+
+    pub fn full_range(&self) -> vk::ImageSubresourceRange {
+        vk::ImageSubresourceRange {
+            aspect_mask: self.aspect_mask,
+            base_mip_level: 0,
+            level_count: self.mip_levels,
+            base_array_layer: 0,
+            layer_count: self.array_layers,
+        }
+    }
+
+    pub fn state(&self, mip_level: u32, array_layer: u32) -> SubresourceState {
+        self.states[self.index(mip_level, array_layer)]
+    }
+
+    /// Records a state change that happened without `transition`, like a render pass' final
+    /// layout.
+    pub fn set_state(&mut self, range: vk::ImageSubresourceRange, state: SubresourceState) {
+        let (mip_levels, array_layers) = self.resolve(&range);
+        for mip_level in mip_levels {
+            for array_layer in array_layers.clone() {
+                let index = self.index(mip_level, array_layer);
+                self.states[index] = state;
+            }
+        }
+    }
+
+    /// Panics in debug builds if any subresource in the range isn't in `layout`.
+    pub fn expect_layout(&self, range: vk::ImageSubresourceRange, layout: vk::ImageLayout) {
+        if cfg!(debug_assertions) {
+            let (mip_levels, array_layers) = self.resolve(&range);
+            for mip_level in mip_levels {
+                for array_layer in array_layers.clone() {
+                    let actual = self.state(mip_level, array_layer).layout;
+                    assert_eq!(
+                        actual, layout,
+                        "image mip {} layer {} is in layout {:?}, expected {:?}",
+                        mip_level, array_layer, actual, layout
+                    );
+                }
+            }
+        }
+    }
+
+    /// Updates the tracked state and returns the stage masks and barriers needed to get there,
+    /// or None if the range already is in a compatible state. Layers of a mip level that share
+    /// the same previous state are covered by a single barrier.
+    pub fn transition(
+        &mut self,
+        image: vk::Image,
+        range: vk::ImageSubresourceRange,
+        next: SubresourceState,
+    ) -> Option<(
+        vk::PipelineStageFlags,
+        vk::PipelineStageFlags,
+        Vec<vk::ImageMemoryBarrier>,
+    )> {
+        let mut src_stage_mask = vk::PipelineStageFlags::empty();
+        let mut dst_stage_mask = vk::PipelineStageFlags::empty();
+        let mut barriers = Vec::new();
+
+        let (mip_levels, array_layers) = self.resolve(&range);
+        for mip_level in mip_levels {
+            let mut array_layer = array_layers.start;
+            while array_layer < array_layers.end {
+                let previous = self.state(mip_level, array_layer);
+
+                // layers after this one in the same state
+                let run_end = (array_layer..array_layers.end)
+                    .find(|&layer| self.state(mip_level, layer) != previous)
+                    .unwrap_or(array_layers.end);
+
+                if previous.needs_barrier(&next) {
+                    src_stage_mask |= previous.usage.stage_mask();
+                    dst_stage_mask |= next.usage.stage_mask();
+                    barriers.push(
+                        vk::ImageMemoryBarrier::builder()
+                            .image(image)
+                            .subresource_range(vk::ImageSubresourceRange {
+                                aspect_mask: range.aspect_mask,
+                                base_mip_level: mip_level,
+                                level_count: 1,
+                                base_array_layer: array_layer,
+                                layer_count: run_end - array_layer,
+                            })
+                            .old_layout(previous.layout)
+                            .new_layout(next.layout)
+                            // only writes need to be made available
+                            .src_access_mask(if previous.usage.is_write() {
+                                previous.usage.access_mask()
+                            } else {
+                                vk::AccessFlags::empty()
+                            })
+                            .dst_access_mask(next.usage.access_mask())
+                            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                            .build(),
+                    );
+                }
+
+                for layer in array_layer..run_end {
+                    let index = self.index(mip_level, layer);
+                    self.states[index] = next;
+                }
+                array_layer = run_end;
+            }
+        }
+
+        if barriers.is_empty() {
+            None
+        } else {
+            Some((src_stage_mask, dst_stage_mask, barriers))
+        }
+    }
+
+    fn index(&self, mip_level: u32, array_layer: u32) -> usize {
+        debug_assert!(mip_level < self.mip_levels && array_layer < self.array_layers);
+        (mip_level * self.array_layers + array_layer) as usize
+    }
+
+    /// Mip levels and array layers covered by the range, with the REMAINING constants resolved.
+    fn resolve(
+        &self,
+        range: &vk::ImageSubresourceRange,
+    ) -> (std::ops::Range<u32>, std::ops::Range<u32>) {
+        let level_count = match range.level_count {
+            vk::REMAINING_MIP_LEVELS => self.mip_levels - range.base_mip_level,
+            level_count => level_count,
+        };
+        let layer_count = match range.layer_count {
+            vk::REMAINING_ARRAY_LAYERS => self.array_layers - range.base_array_layer,
+            layer_count => layer_count,
+        };
+
+        (
+            range.base_mip_level..range.base_mip_level + level_count,
+            range.base_array_layer..range.base_array_layer + layer_count,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRANSFER_DST: SubresourceState = SubresourceState {
+        layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+        usage: ResourceUsage::TransferWrite,
+    };
+    const SHADER_READ: SubresourceState = SubresourceState {
+        layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        usage: ResourceUsage::FragmentShaderRead,
+    };
+
+    fn tracker() -> ImageStateTracker {
+        ImageStateTracker::new(vk::ImageAspectFlags::COLOR, 2, 4)
+    }
+
+    #[test]
+    fn transition_from_undefined() {
+        let mut tracker = tracker();
+        let range = tracker.full_range();
+
+        let (src, dst, barriers) = tracker
+            .transition(vk::Image::null(), range, TRANSFER_DST)
+            .unwrap();
+
+        assert_eq!(src, vk::PipelineStageFlags::TOP_OF_PIPE);
+        assert_eq!(dst, vk::PipelineStageFlags::TRANSFER);
+        // one barrier per mip level
+        assert_eq!(barriers.len(), 2);
+        assert!(barriers.iter().all(|barrier| {
+            barrier.old_layout == vk::ImageLayout::UNDEFINED
+                && barrier.subresource_range.layer_count == 4
+                && barrier.src_access_mask.is_empty()
+        }));
+        assert_eq!(tracker.state(1, 3), TRANSFER_DST);
+    }
+
+    #[test]
+    fn read_after_read_needs_no_barrier() {
+        let mut tracker = tracker();
+        let range = tracker.full_range();
+        tracker.set_state(range, SHADER_READ);
+
+        assert!(tracker
+            .transition(vk::Image::null(), range, SHADER_READ)
+            .is_none());
+    }
+
+    #[test]
+    fn write_after_write_with_same_layout_still_synchronizes() {
+        let mut tracker = tracker();
+        let range = tracker.full_range();
+        tracker.set_state(range, TRANSFER_DST);
+
+        let (src, _, barriers) = tracker
+            .transition(vk::Image::null(), range, TRANSFER_DST)
+            .unwrap();
+
+        assert_eq!(src, vk::PipelineStageFlags::TRANSFER);
+        assert_eq!(barriers[0].src_access_mask, vk::AccessFlags::TRANSFER_WRITE);
+    }
+
+    #[test]
+    fn only_layers_in_a_different_state_are_split() {
+        let mut tracker = tracker();
+        let range = vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: vk::REMAINING_ARRAY_LAYERS,
+        };
+        tracker.set_state(
+            vk::ImageSubresourceRange {
+                base_array_layer: 1,
+                layer_count: 2,
+                ..range
+            },
+            TRANSFER_DST,
+        );
+
+        let (_, _, barriers) = tracker
+            .transition(vk::Image::null(), range, SHADER_READ)
+            .unwrap();
+
+        let layers = barriers
+            .iter()
+            .map(|barrier| {
+                (
+                    barrier.subresource_range.base_array_layer,
+                    barrier.subresource_range.layer_count,
+                    barrier.old_layout,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            layers,
+            vec![
+                (0, 1, vk::ImageLayout::UNDEFINED),
+                (1, 2, vk::ImageLayout::TRANSFER_DST_OPTIMAL),
+                (3, 1, vk::ImageLayout::UNDEFINED),
+            ]
+        );
+        tracker.expect_layout(range, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL);
+    }
+
+    #[test]
+    #[should_panic]
+    fn unexpected_layout_panics() {
+        let tracker = tracker();
+        tracker.expect_layout(tracker.full_range(), vk::ImageLayout::GENERAL);
+    }
+}
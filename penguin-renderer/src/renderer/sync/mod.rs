@@ -1,5 +1,11 @@
 mod context;
+mod image_state;
 mod pipeline_barrier;
+mod resource_usage;
+mod timeline;
 
 pub use context::*;
+pub use image_state::*;
 pub use pipeline_barrier::*;
+pub use resource_usage::*;
+pub use timeline::*;
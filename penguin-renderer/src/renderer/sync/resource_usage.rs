@@ -0,0 +1,296 @@
+use ash::vk;
+
+// https://github.com/Tobski/simple_vulkan_synchronization
+
+/// How a resource is accessed at a point in a frame. Maps to the image layout, pipeline stages
+/// and access flags needed for that access, so that barriers can be derived from two usages
+/// instead of being written out by hand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResourceUsage {
+    /// Contents don't matter, for example a freshly created image.
+    Undefined,
+    /// A swapchain image that was just acquired. Waited on at the color attachment output stage.
+    SwapchainAcquire,
+    /// The image is handed over to the presentation engine.
+    Present,
+
+    ColorAttachmentWrite,
+    DepthAttachmentWrite,
+    /// Depth testing without writing depth.
+    DepthAttachmentRead,
+
+    /// Sampled or read as an input in a fragment shader.
+    FragmentShaderRead,
+    /// Sampled or read in a vertex shader.
+    VertexShaderRead,
+    ComputeShaderRead,
+    ComputeShaderWrite,
+
+    TransferRead,
+    TransferWrite,
+
+    VertexBuffer,
+    IndexBuffer,
+    IndirectBuffer,
+    /// Written by the CPU through mapped memory.
+    HostWrite,
+}
+
+impl ResourceUsage {
+    pub fn image_layout(self) -> vk::ImageLayout {
+        match self {
+            Self::Undefined | Self::SwapchainAcquire => vk::ImageLayout::UNDEFINED,
+            Self::Present => vk::ImageLayout::PRESENT_SRC_KHR,
+            Self::ColorAttachmentWrite => vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+            Self::DepthAttachmentWrite => vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+            Self::DepthAttachmentRead => vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL,
+            Self::FragmentShaderRead | Self::VertexShaderRead | Self::ComputeShaderRead => {
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL
+            }
+            Self::ComputeShaderWrite => vk::ImageLayout::GENERAL,
+            Self::TransferRead => vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            Self::TransferWrite => vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            Self::VertexBuffer | Self::IndexBuffer | Self::IndirectBuffer | Self::HostWrite => {
+                vk::ImageLayout::GENERAL
+            }
+        }
+    }
+
+    pub fn stage_mask(self) -> vk::PipelineStageFlags {
+        match self {
+            Self::Undefined => vk::PipelineStageFlags::TOP_OF_PIPE,
+            Self::SwapchainAcquire | Self::ColorAttachmentWrite => {
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+            }
+            Self::Present => vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+            Self::DepthAttachmentWrite | Self::DepthAttachmentRead => {
+                vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS
+                    | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS
+            }
+            Self::FragmentShaderRead => vk::PipelineStageFlags::FRAGMENT_SHADER,
+            Self::VertexShaderRead => vk::PipelineStageFlags::VERTEX_SHADER,
+            Self::ComputeShaderRead | Self::ComputeShaderWrite => {
+                vk::PipelineStageFlags::COMPUTE_SHADER
+            }
+            Self::TransferRead | Self::TransferWrite => vk::PipelineStageFlags::TRANSFER,
+            Self::VertexBuffer | Self::IndexBuffer => vk::PipelineStageFlags::VERTEX_INPUT,
+            Self::IndirectBuffer => vk::PipelineStageFlags::DRAW_INDIRECT,
+            Self::HostWrite => vk::PipelineStageFlags::HOST,
+        }
+    }
+
+    pub fn access_mask(self) -> vk::AccessFlags {
+        match self {
+            Self::Undefined | Self::SwapchainAcquire | Self::Present => vk::AccessFlags::empty(),
+            Self::ColorAttachmentWrite => {
+                vk::AccessFlags::COLOR_ATTACHMENT_READ | vk::AccessFlags::COLOR_ATTACHMENT_WRITE
+            }
+            Self::DepthAttachmentWrite => {
+                vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ
+                    | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE
+            }
+            Self::DepthAttachmentRead => vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ,
+            Self::FragmentShaderRead | Self::VertexShaderRead | Self::ComputeShaderRead => {
+                vk::AccessFlags::SHADER_READ
+            }
+            Self::ComputeShaderWrite => vk::AccessFlags::SHADER_WRITE,
+            Self::TransferRead => vk::AccessFlags::TRANSFER_READ,
+            Self::TransferWrite => vk::AccessFlags::TRANSFER_WRITE,
+            Self::VertexBuffer => vk::AccessFlags::VERTEX_ATTRIBUTE_READ,
+            Self::IndexBuffer => vk::AccessFlags::INDEX_READ,
+            Self::IndirectBuffer => vk::AccessFlags::INDIRECT_COMMAND_READ,
+            Self::HostWrite => vk::AccessFlags::HOST_WRITE,
+        }
+    }
+
+    pub fn is_write(self) -> bool {
+        matches!(
+            self,
+            Self::ColorAttachmentWrite
+                | Self::DepthAttachmentWrite
+                | Self::ComputeShaderWrite
+                | Self::TransferWrite
+                | Self::HostWrite
+        )
+    }
+
+    /// The image usage flags an image needs to be accessed like this.
+    pub fn image_usage_flags(self) -> vk::ImageUsageFlags {
+        match self {
+            Self::ColorAttachmentWrite => vk::ImageUsageFlags::COLOR_ATTACHMENT,
+            Self::DepthAttachmentWrite | Self::DepthAttachmentRead => {
+                vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT
+            }
+            Self::FragmentShaderRead | Self::VertexShaderRead | Self::ComputeShaderRead => {
+                vk::ImageUsageFlags::SAMPLED
+            }
+            Self::ComputeShaderWrite => vk::ImageUsageFlags::STORAGE,
+            Self::TransferRead => vk::ImageUsageFlags::TRANSFER_SRC,
+            Self::TransferWrite => vk::ImageUsageFlags::TRANSFER_DST,
+            _ => vk::ImageUsageFlags::empty(),
+        }
+    }
+}
+
+/// Stage masks and the image barrier for going from one usage of an image to another.
+pub fn image_barrier(
+    image: vk::Image,
+    subresource_range: vk::ImageSubresourceRange,
+    (previous, next): (ResourceUsage, ResourceUsage),
+) -> (
+    vk::PipelineStageFlags,
+    vk::PipelineStageFlags,
+    vk::ImageMemoryBarrier,
+) {
+    let barrier = vk::ImageMemoryBarrier::builder()
+        .image(image)
+        .subresource_range(subresource_range)
+        .old_layout(previous.image_layout())
+        .new_layout(next.image_layout())
+        // only writes need to be made available
+        .src_access_mask(if previous.is_write() {
+            previous.access_mask()
+        } else {
+            vk::AccessFlags::empty()
+        })
+        .dst_access_mask(next.access_mask())
+        .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .build();
+
+    (previous.stage_mask(), next.stage_mask(), barrier)
+}
+
+/// Stage masks and the buffer barrier for going from one usage of a buffer to another, or None
+/// if the usages don't need to be synchronized (read after read, or nothing to wait for).
+pub fn buffer_barrier(
+    buffer: vk::Buffer,
+    (previous, next): (ResourceUsage, ResourceUsage),
+) -> Option<(
+    vk::PipelineStageFlags,
+    vk::PipelineStageFlags,
+    vk::BufferMemoryBarrier,
+)> {
+    // buffers have no layout, so there's nothing to do before their first access
+    if previous == ResourceUsage::Undefined || (!previous.is_write() && !next.is_write()) {
+        return None;
+    }
+
+    let barrier = vk::BufferMemoryBarrier::builder()
+        .buffer(buffer)
+        .offset(0)
+        .size(vk::WHOLE_SIZE)
+        .src_access_mask(if previous.is_write() {
+            previous.access_mask()
+        } else {
+            vk::AccessFlags::empty()
+        })
+        .dst_access_mask(next.access_mask())
+        .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .build();
+
+    Some((previous.stage_mask(), next.stage_mask(), barrier))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_after_read_needs_no_buffer_barrier() {
+        let buffer = vk::Buffer::null();
+
+        assert!(buffer_barrier(
+            buffer,
+            (ResourceUsage::VertexBuffer, ResourceUsage::VertexBuffer)
+        )
+        .is_none());
+        assert!(buffer_barrier(
+            buffer,
+            (ResourceUsage::TransferRead, ResourceUsage::IndexBuffer)
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn first_buffer_access_needs_no_barrier() {
+        assert!(buffer_barrier(
+            vk::Buffer::null(),
+            (ResourceUsage::Undefined, ResourceUsage::TransferWrite)
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn read_after_write_makes_the_write_available() {
+        let (src, dst, barrier) = buffer_barrier(
+            vk::Buffer::null(),
+            (ResourceUsage::TransferWrite, ResourceUsage::VertexBuffer),
+        )
+        .unwrap();
+
+        assert_eq!(src, vk::PipelineStageFlags::TRANSFER);
+        assert_eq!(dst, vk::PipelineStageFlags::VERTEX_INPUT);
+        assert_eq!(barrier.src_access_mask, vk::AccessFlags::TRANSFER_WRITE);
+        assert_eq!(
+            barrier.dst_access_mask,
+            vk::AccessFlags::VERTEX_ATTRIBUTE_READ
+        );
+    }
+
+    #[test]
+    fn write_after_read_only_waits_for_the_read() {
+        let (src, _, barrier) = buffer_barrier(
+            vk::Buffer::null(),
+            (ResourceUsage::VertexBuffer, ResourceUsage::TransferWrite),
+        )
+        .unwrap();
+
+        assert_eq!(src, vk::PipelineStageFlags::VERTEX_INPUT);
+        assert!(barrier.src_access_mask.is_empty());
+    }
+
+    #[test]
+    fn image_barrier_transitions_between_layouts() {
+        let (src, dst, barrier) = image_barrier(
+            vk::Image::null(),
+            vk::ImageSubresourceRange::default(),
+            (
+                ResourceUsage::ColorAttachmentWrite,
+                ResourceUsage::FragmentShaderRead,
+            ),
+        );
+
+        assert_eq!(src, vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT);
+        assert_eq!(dst, vk::PipelineStageFlags::FRAGMENT_SHADER);
+        assert_eq!(
+            barrier.old_layout,
+            vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL
+        );
+        assert_eq!(
+            barrier.new_layout,
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL
+        );
+        assert_eq!(
+            barrier.src_access_mask,
+            vk::AccessFlags::COLOR_ATTACHMENT_READ | vk::AccessFlags::COLOR_ATTACHMENT_WRITE
+        );
+        assert_eq!(barrier.dst_access_mask, vk::AccessFlags::SHADER_READ);
+    }
+
+    #[test]
+    fn image_read_after_read_has_no_write_to_make_available() {
+        let (_, _, barrier) = image_barrier(
+            vk::Image::null(),
+            vk::ImageSubresourceRange::default(),
+            (
+                ResourceUsage::FragmentShaderRead,
+                ResourceUsage::ComputeShaderRead,
+            ),
+        );
+
+        assert_eq!(barrier.old_layout, barrier.new_layout);
+        assert!(barrier.src_access_mask.is_empty());
+    }
+}
@@ -0,0 +1,362 @@
+use crate::renderer::vk_types::{
+    Device, DeviceFeature, EnabledFeatures, FeatureRequest, VkContext,
+};
+use ash::vk;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// A value on the GPU timeline, reached once the submission that signals it has finished.
+/// Waiting for a point waits for every submission before it as well.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct TimelinePoint(pub u64);
+
+/// Orders the submissions to the graphics queue. Every submission signals the next value of the
+/// timeline, so render and upload submissions can wait for each other by value.
+///
+///     Uses a timeline semaphore where the device supports them. Otherwise every submission gets
+///     a fence, and waiting for a point on the GPU becomes waiting for it on the CPU before
+///     submitting.
+pub struct GpuTimeline {
+    backend: TimelineBackend,
+    last_submitted: AtomicU64,
+    /// keeps values in submission order and the queue externally synchronized
+    submit_lock: Mutex<()>,
+}
+
+enum TimelineBackend {
+    Semaphore(vk::Semaphore),
+    Fences(Mutex<FenceTimeline>),
+}
+
+/// One submission to the graphics queue.
+#[derive(Default)]
+pub struct GraphicsSubmission<'a> {
+    pub command_buffers: &'a [vk::CommandBuffer],
+    /// binary semaphores, like the swapchain's, and the stages that wait for them
+    pub wait_semaphores: &'a [(vk::Semaphore, vk::PipelineStageFlags)],
+    /// submissions to wait for before any command runs
+    pub wait_for: &'a [TimelinePoint],
+    pub signal_semaphores: &'a [vk::Semaphore],
+}
+
+impl GpuTimeline {
+    /// Timeline semaphores are core in Vulkan 1.2.
+    pub const FEATURE_REQUEST: FeatureRequest = FeatureRequest {
+        subsystem: "timeline semaphores",
+        required: false,
+        extensions: &[],
+        features: &[DeviceFeature::TimelineSemaphore],
+    };
+
+    pub(crate) fn init(device: &ash::Device, enabled_features: &EnabledFeatures) -> Self {
+        let backend = if enabled_features.is_enabled(Self::FEATURE_REQUEST.subsystem) {
+            let mut type_create_info = vk::SemaphoreTypeCreateInfo::builder()
+                .semaphore_type(vk::SemaphoreType::TIMELINE)
+                .initial_value(0);
+            let create_info = vk::SemaphoreCreateInfo::builder().push_next(&mut type_create_info);
+
+            TimelineBackend::Semaphore(
+                unsafe { device.create_semaphore(&create_info, None) }
+                    .expect("Couldn't create timeline semaphore"),
+            )
+        } else {
+            log::info!("Timeline semaphores aren't supported, the GPU timeline uses fences.");
+            TimelineBackend::Fences(Mutex::new(FenceTimeline::default()))
+        };
+
+        Self {
+            backend,
+            last_submitted: AtomicU64::new(0),
+            submit_lock: Mutex::new(()),
+        }
+    }
+
+    pub(crate) fn destroy(&mut self, device: &ash::Device) {
+        match &mut self.backend {
+            TimelineBackend::Semaphore(semaphore) => unsafe {
+                device.destroy_semaphore(*semaphore, None)
+            },
+            TimelineBackend::Fences(fences) => {
+                let fences = fences.get_mut().expect("fence timeline lock was poisoned");
+                for fence in fences.all_fences() {
+                    unsafe { device.destroy_fence(fence, None) };
+                }
+            }
+        }
+    }
+
+    pub fn uses_timeline_semaphore(&self) -> bool {
+        matches!(self.backend, TimelineBackend::Semaphore(_))
+    }
+
+    /// Point of the latest submission, waiting for it waits for all submitted work.
+    pub fn last_submitted(&self) -> TimelinePoint {
+        TimelinePoint(self.last_submitted.load(Ordering::Acquire))
+    }
+
+    /// The latest point the GPU has reached.
+    pub fn completed(&self, device: &ash::Device) -> TimelinePoint {
+        match &self.backend {
+            TimelineBackend::Semaphore(semaphore) => TimelinePoint(
+                unsafe { device.get_semaphore_counter_value(*semaphore) }
+                    .expect("Couldn't get timeline semaphore value"),
+            ),
+            TimelineBackend::Fences(fences) => {
+                let mut fences = fences.lock().expect("fence timeline lock was poisoned");
+                let signaled = fences
+                    .pending
+                    .iter()
+                    .take_while(|(_, fence)| {
+                        unsafe { device.get_fence_status(*fence) }.unwrap_or(false)
+                    })
+                    .last()
+                    .map(|&(value, _)| value);
+                if let Some(value) = signaled {
+                    fences.retire(device, value);
+                }
+                TimelinePoint(fences.completed)
+            }
+        }
+    }
+
+    /// Blocks until the GPU reached the point.
+    pub fn wait(&self, device: &ash::Device, point: TimelinePoint, timeout: std::time::Duration) {
+        match &self.backend {
+            TimelineBackend::Semaphore(semaphore) => {
+                let semaphores = [*semaphore];
+                let values = [point.0];
+                let wait_info = vk::SemaphoreWaitInfo::builder()
+                    .semaphores(&semaphores)
+                    .values(&values);
+                unsafe { device.wait_semaphores(&wait_info, timeout_nanos(timeout)) }
+                    .expect("Couldn't wait for timeline semaphore. Timed out?");
+            }
+            TimelineBackend::Fences(fences) => {
+                let mut fences = fences.lock().expect("fence timeline lock was poisoned");
+                let waited = fences.fences_up_to(point.0);
+                if waited.is_empty() {
+                    return;
+                }
+                unsafe { device.wait_for_fences(&waited, true, timeout_nanos(timeout)) }
+                    .expect("Couldn't wait for fences. Timed out?");
+                fences.retire(device, point.0);
+            }
+        }
+    }
+
+    /// Submits to the graphics queue and returns the point the submission signals.
+    pub fn submit(&self, device: &Device, submission: GraphicsSubmission) -> TimelinePoint {
+        let wait_for = submission.wait_for.iter().max().copied();
+
+        let (mut wait_semaphores, mut wait_stages): (Vec<_>, Vec<_>) =
+            submission.wait_semaphores.iter().copied().unzip();
+        let mut signal_semaphores = submission.signal_semaphores.to_vec();
+
+        match &self.backend {
+            TimelineBackend::Semaphore(semaphore) => {
+                // binary semaphores ignore their values
+                let mut wait_values = vec![0; wait_semaphores.len()];
+                if let Some(wait_for) = wait_for {
+                    wait_semaphores.push(*semaphore);
+                    wait_stages.push(vk::PipelineStageFlags::ALL_COMMANDS);
+                    wait_values.push(wait_for.0);
+                }
+
+                let _submit_lock = self.submit_lock.lock().expect("submit lock was poisoned");
+                let value = self.last_submitted.load(Ordering::Acquire) + 1;
+                let mut signal_values = vec![0; signal_semaphores.len()];
+                signal_semaphores.push(*semaphore);
+                signal_values.push(value);
+
+                let mut timeline_info = vk::TimelineSemaphoreSubmitInfo::builder()
+                    .wait_semaphore_values(&wait_values)
+                    .signal_semaphore_values(&signal_values);
+                let submit_info = vk::SubmitInfo::builder()
+                    .command_buffers(submission.command_buffers)
+                    .wait_semaphores(&wait_semaphores)
+                    .wait_dst_stage_mask(&wait_stages)
+                    .signal_semaphores(&signal_semaphores)
+                    .push_next(&mut timeline_info)
+                    .build();
+
+                unsafe {
+                    device.queue_submit(
+                        device.graphics_queue_handle,
+                        &[submit_info],
+                        vk::Fence::null(),
+                    )
+                }
+                .expect("Couldn't submit command buffer to graphics queue");
+                // published after the submit, so it never names a point that isn't submitted yet
+                self.last_submitted.store(value, Ordering::Release);
+                TimelinePoint(value)
+            }
+            TimelineBackend::Fences(fences) => {
+                if let Some(wait_for) = wait_for {
+                    self.wait(device, wait_for, std::time::Duration::MAX);
+                }
+
+                let _submit_lock = self.submit_lock.lock().expect("submit lock was poisoned");
+                let mut fences = fences.lock().expect("fence timeline lock was poisoned");
+                let fence = fences.free.pop().unwrap_or_else(|| {
+                    unsafe { device.create_fence(&vk::FenceCreateInfo::default(), None) }
+                        .expect("failed to create fence")
+                });
+
+                let submit_info = vk::SubmitInfo::builder()
+                    .command_buffers(submission.command_buffers)
+                    .wait_semaphores(&wait_semaphores)
+                    .wait_dst_stage_mask(&wait_stages)
+                    .signal_semaphores(&signal_semaphores)
+                    .build();
+
+                unsafe { device.queue_submit(device.graphics_queue_handle, &[submit_info], fence) }
+                    .expect("Couldn't submit command buffer to graphics queue");
+
+                let value = self.last_submitted.load(Ordering::Acquire) + 1;
+                fences.pending.push_back((value, fence));
+                self.last_submitted.store(value, Ordering::Release);
+                TimelinePoint(value)
+            }
+        }
+    }
+}
+
+/// Vulkan takes timeouts as u64 nanoseconds, longer ones wait forever.
+fn timeout_nanos(timeout: std::time::Duration) -> u64 {
+    u64::try_from(timeout.as_nanos()).unwrap_or(u64::MAX)
+}
+
+/// Fences of the submissions the CPU hasn't seen finish yet.
+#[derive(Default)]
+struct FenceTimeline {
+    /// in submission order
+    pending: VecDeque<(u64, vk::Fence)>,
+    /// unsignaled fences to reuse
+    free: Vec<vk::Fence>,
+    completed: u64,
+}
+
+impl FenceTimeline {
+    fn fences_up_to(&self, value: u64) -> Vec<vk::Fence> {
+        self.pending
+            .iter()
+            .take_while(|&&(pending, _)| pending <= value)
+            .map(|&(_, fence)| fence)
+            .collect()
+    }
+
+    /// Marks the submissions up to the value as finished and resets their fences for reuse.
+    fn retire(&mut self, device: &ash::Device, value: u64) {
+        let retired = self.take_up_to(value);
+        if !retired.is_empty() {
+            unsafe { device.reset_fences(&retired) }.expect("Couldn't reset fences.");
+            self.free.extend(retired);
+        }
+    }
+
+    fn take_up_to(&mut self, value: u64) -> Vec<vk::Fence> {
+        let count = self
+            .pending
+            .iter()
+            .take_while(|&&(pending, _)| pending <= value)
+            .count();
+        self.completed = self.completed.max(value);
+        self.pending
+            .drain(..count)
+            .map(|(_, fence)| fence)
+            .collect()
+    }
+
+    fn all_fences(&mut self) -> Vec<vk::Fence> {
+        self.pending
+            .drain(..)
+            .map(|(_, fence)| fence)
+            .chain(self.free.drain(..))
+            .collect()
+    }
+}
+
+impl VkContext {
+    /// Submits to the graphics queue on the GPU timeline.
+    pub fn submit_graphics(&self, submission: GraphicsSubmission) -> TimelinePoint {
+        self.timeline.submit(&self.device, submission)
+    }
+
+    /// Blocks until the GPU finished the submission of the point and everything before it.
+    pub fn wait_for_timeline(&self, point: TimelinePoint, timeout: std::time::Duration) {
+        self.timeline.wait(&self.device, point, timeout)
+    }
+
+    pub fn is_timeline_complete(&self, point: TimelinePoint) -> bool {
+        self.timeline.completed(&self.device) >= point
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ash::vk::Handle;
+
+    fn fence(raw: u64) -> vk::Fence {
+        vk::Fence::from_raw(raw)
+    }
+
+    fn timeline(values: &[u64]) -> FenceTimeline {
+        FenceTimeline {
+            pending: values.iter().map(|&value| (value, fence(value))).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn waiting_for_a_point_waits_for_everything_before_it() {
+        let timeline = timeline(&[3, 4, 5]);
+
+        assert_eq!(timeline.fences_up_to(4), [fence(3), fence(4)]);
+        assert!(timeline.fences_up_to(2).is_empty());
+    }
+
+    #[test]
+    fn retired_points_are_complete() {
+        let mut timeline = timeline(&[1, 2, 3]);
+
+        assert_eq!(timeline.take_up_to(2), [fence(1), fence(2)]);
+        assert_eq!(timeline.completed, 2);
+        assert_eq!(timeline.fences_up_to(3), [fence(3)]);
+    }
+
+    #[test]
+    fn completed_value_never_goes_back() {
+        let mut timeline = timeline(&[1, 2]);
+
+        timeline.take_up_to(2);
+        assert!(timeline.take_up_to(1).is_empty());
+        assert_eq!(timeline.completed, 2);
+    }
+
+    #[test]
+    fn long_timeouts_wait_forever() {
+        assert_eq!(timeout_nanos(std::time::Duration::MAX), u64::MAX);
+        assert_eq!(
+            timeout_nanos(std::time::Duration::from_secs(u64::MAX / 2)),
+            u64::MAX
+        );
+        assert_eq!(
+            timeout_nanos(std::time::Duration::from_millis(2)),
+            2_000_000
+        );
+    }
+
+    #[test]
+    fn points_are_ordered_by_value() {
+        assert!(TimelinePoint(2) > TimelinePoint(1));
+        assert_eq!(
+            [TimelinePoint(4), TimelinePoint(9), TimelinePoint(1)]
+                .iter()
+                .max(),
+            Some(&TimelinePoint(9))
+        );
+    }
+}
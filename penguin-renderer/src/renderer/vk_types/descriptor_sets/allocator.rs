@@ -0,0 +1,146 @@
+use crate::renderer::vk_types::{DescriptorPool, DescriptorSetLayout, VkContext};
+use ash::prelude::VkResult;
+use ash::vk;
+
+/// Allocator for descriptor sets.
+///
+///     Keeps an amount of VkDescriptorPools for allocating VkDescriptorSets.
+///     Reuses pools if possible and creates new, bigger pools if the current one runs out of
+///     memory.
+#[derive(Default)]
+pub struct DescriptorAllocator {
+    /// pool that new sets are allocated from
+    current_pool: Option<DescriptorPool>,
+    /// pools with allocated descriptor sets
+    pools_allocated: Vec<DescriptorPool>,
+    /// available, created but reset pools
+    free_pools: Vec<DescriptorPool>,
+    /// max set count of the next pool that gets created
+    next_pool_set_count: u32,
+    pool_create_flags: vk::DescriptorPoolCreateFlags,
+}
+impl DescriptorAllocator {
+    /// For each 0..descriptor_count to allocate per pool, multiply descriptor_count with with the
+    ///     corresponding multiplier.
+    const POOL_DESCRIPTOR_MULTIPLIERS: [(vk::DescriptorType, f32); 5] = [
+        (vk::DescriptorType::UNIFORM_BUFFER, 2.),
+        (vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC, 1.),
+        (vk::DescriptorType::STORAGE_BUFFER, 2.),
+        (vk::DescriptorType::STORAGE_BUFFER_DYNAMIC, 1.),
+        (vk::DescriptorType::COMBINED_IMAGE_SAMPLER, 4.),
+    ];
+
+    /// Set count of the first pool. Every new pool doubles the count, up to MAX_POOL_SET_COUNT.
+    const INITIAL_POOL_SET_COUNT: u32 = 32;
+    const MAX_POOL_SET_COUNT: u32 = 4096;
+
+    fn pool_sizes(set_count: u32) -> Vec<vk::DescriptorPoolSize> {
+        Self::POOL_DESCRIPTOR_MULTIPLIERS
+            .iter()
+            .map(|&(ty, multiplier)| {
+                vk::DescriptorPoolSize::builder()
+                    .ty(ty)
+                    .descriptor_count((set_count as f32 * multiplier) as u32)
+                    .build()
+            })
+            .collect()
+    }
+}
+
+impl DescriptorAllocator {
+    pub fn new() -> Self {
+        Self::with_flags(vk::DescriptorPoolCreateFlags::empty())
+    }
+
+    pub fn with_flags(pool_create_flags: vk::DescriptorPoolCreateFlags) -> Self {
+        Self {
+            next_pool_set_count: Self::INITIAL_POOL_SET_COUNT,
+            pool_create_flags,
+            ..Default::default()
+        }
+    }
+
+    pub fn destroy(&mut self, context: &VkContext) {
+        self.free_pools
+            .drain(..)
+            .chain(self.pools_allocated.drain(..))
+            .for_each(|pool| pool.destroy(context));
+        self.current_pool = None;
+    }
+
+    /// Resets all allocated pools, freeing every set allocated through the allocator.
+    ///
+    /// Only call this once the GPU is done with the sets, for example after waiting for
+    ///     the fence of the frame the allocator belongs to.
+    pub fn reset_pools(&mut self, context: &VkContext) {
+        self.pools_allocated.iter().for_each(|pool| {
+            unsafe {
+                context
+                    .device
+                    .reset_descriptor_pool(pool.handle, vk::DescriptorPoolResetFlags::empty())
+            }
+            .expect("couldn't reset descriptor pool")
+        });
+
+        self.free_pools.extend(self.pools_allocated.drain(..));
+        self.current_pool = None;
+    }
+
+    fn take_pool(&mut self, context: &VkContext) -> DescriptorPool {
+        let new_pool = if let Some(pool) = self.free_pools.pop() {
+            pool
+        } else {
+            let set_count = self.next_pool_set_count;
+            self.next_pool_set_count = (set_count * 2).min(Self::MAX_POOL_SET_COUNT);
+
+            log::debug!("Creating descriptor pool with room for {} sets.", set_count);
+            DescriptorPool::from_sizes(
+                context,
+                set_count,
+                self.pool_create_flags,
+                &Self::pool_sizes(set_count),
+            )
+        };
+        self.pools_allocated.push(new_pool);
+        new_pool
+    }
+
+    pub fn allocate_set(
+        &mut self,
+        context: &VkContext,
+        layout: DescriptorSetLayout,
+    ) -> VkResult<vk::DescriptorSet> {
+        let current_pool = {
+            // get current pool if any
+            if let Some(pool) = self.current_pool {
+                pool
+            } else {
+                // if no current pool, get a new one
+                let new_pool = self.take_pool(context);
+                self.current_pool = Some(new_pool);
+                new_pool
+            }
+        };
+
+        // try to allocate set in pool
+        match context.alloc_descriptor_set(current_pool, layout.handle) {
+            // if success, use the set
+            VkResult::Ok(allocated_set) => Ok(allocated_set),
+            // if memory error, grow by allocating from a new pool
+            VkResult::Err(err)
+                if err == vk::Result::ERROR_FRAGMENTED_POOL
+                    || err == vk::Result::ERROR_OUT_OF_POOL_MEMORY =>
+            {
+                let new_pool = self.take_pool(context);
+                self.current_pool = Some(new_pool);
+
+                context.alloc_descriptor_set(new_pool, layout.handle)
+            }
+            // any other error is passed on to the caller
+            VkResult::Err(err) => {
+                log::error!("couldn't allocate descriptor set: {:?}", err);
+                Err(err)
+            }
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use crate::renderer::vk_types::{
+    DescriptorAllocator, DescriptorLayoutCache, DescriptorSet, DescriptorSetLayout,
+    DescriptorSetLayoutBuilder, VkContext,
+};
+use anyhow::*;
+use ash::vk;
+
+pub struct DescriptorBuilderBindParams {
+    pub binding: u32,
+    pub ty: vk::DescriptorType,
+    pub shader_stages: vk::ShaderStageFlags,
+}
+impl DescriptorBuilderBindParams {
+    fn descriptor_set_layout_binding(
+        &self,
+        descriptor_count: u32,
+    ) -> vk::DescriptorSetLayoutBindingBuilder<'static> {
+        vk::DescriptorSetLayoutBinding::builder()
+            .descriptor_count(descriptor_count)
+            .descriptor_type(self.ty)
+            .binding(self.binding)
+            .stage_flags(self.shader_stages)
+    }
+}
+
+enum DescriptorWriteInfo<'a> {
+    Buffer(&'a [vk::DescriptorBufferInfo]),
+    Image(&'a [vk::DescriptorImageInfo]),
+}
+
+struct DescriptorWrite<'a> {
+    binding: u32,
+    ty: vk::DescriptorType,
+    info: DescriptorWriteInfo<'a>,
+}
+
+/// Builds a descriptor set together with its layout in one go.
+///
+///     The layout is fetched from the layout cache, the set is allocated from the given allocator
+///     and all bound buffers and images are written into the set before it's returned.
+///
+/// # Examples
+/// ```ignore
+/// let set = DescriptorBuilder::builder(&mut layout_cache, &mut allocator)
+///     .bind_buffer(
+///         &[camera_buffer_info],
+///         DescriptorBuilderBindParams {
+///             binding: 0,
+///             ty: vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC,
+///             shader_stages: vk::ShaderStageFlags::VERTEX,
+///         },
+///     )
+///     .build(context)?;
+/// ```
+pub struct DescriptorBuilder<'a> {
+    layout_cache: &'a mut DescriptorLayoutCache,
+    allocator: &'a mut DescriptorAllocator,
+    layout_builder: DescriptorSetLayoutBuilder,
+    writes: Vec<DescriptorWrite<'a>>,
+}
+
+impl<'a> DescriptorBuilder<'a> {
+    pub fn builder(
+        layout_cache: &'a mut DescriptorLayoutCache,
+        allocator: &'a mut DescriptorAllocator,
+    ) -> Self {
+        Self {
+            layout_cache,
+            allocator,
+            layout_builder: DescriptorSetLayout::builder(),
+            writes: Vec::new(),
+        }
+    }
+
+    /// Binds uniform or storage buffers (dynamic or not) to the given binding.
+    pub fn bind_buffer(
+        mut self,
+        buffer_info: &'a [vk::DescriptorBufferInfo],
+        bind_params: DescriptorBuilderBindParams,
+    ) -> Self {
+        debug_assert!(
+            is_buffer_descriptor(bind_params.ty),
+            "{:?} can't be bound as a buffer",
+            bind_params.ty
+        );
+
+        self.layout_builder = self.layout_builder.layout_binding(
+            bind_params.descriptor_set_layout_binding(buffer_info.len() as _),
+        );
+
+        self.writes.push(DescriptorWrite {
+            binding: bind_params.binding,
+            ty: bind_params.ty,
+            info: DescriptorWriteInfo::Buffer(buffer_info),
+        });
+        self
+    }
+
+    /// Binds images, for example combined image samplers, to the given binding.
+    pub fn bind_image(
+        mut self,
+        image_info: &'a [vk::DescriptorImageInfo],
+        bind_params: DescriptorBuilderBindParams,
+    ) -> Self {
+        debug_assert!(
+            !is_buffer_descriptor(bind_params.ty),
+            "{:?} can't be bound as an image",
+            bind_params.ty
+        );
+
+        self.layout_builder = self.layout_builder.layout_binding(
+            bind_params.descriptor_set_layout_binding(image_info.len() as _),
+        );
+
+        self.writes.push(DescriptorWrite {
+            binding: bind_params.binding,
+            ty: bind_params.ty,
+            info: DescriptorWriteInfo::Image(image_info),
+        });
+        self
+    }
+
+    /// Only creates (or fetches) the layout, without allocating a set.
+    pub fn build_layout(self, context: &VkContext) -> DescriptorSetLayout {
+        self.layout_cache
+            .create_descriptor_layout(context, self.layout_builder)
+    }
+
+    /// Allocates the set and writes all bound resources into it.
+    pub fn build(self, context: &VkContext) -> Result<DescriptorSet> {
+        let layout = self
+            .layout_cache
+            .create_descriptor_layout(context, self.layout_builder);
+
+        let handle = self.allocator.allocate_set(context, layout)?;
+
+        let write_sets = self
+            .writes
+            .iter()
+            .map(|write| {
+                let write_set = vk::WriteDescriptorSet::builder()
+                    .dst_set(handle)
+                    .dst_binding(write.binding)
+                    .descriptor_type(write.ty);
+
+                match write.info {
+                    DescriptorWriteInfo::Buffer(buffer_info) => write_set.buffer_info(buffer_info),
+                    DescriptorWriteInfo::Image(image_info) => write_set.image_info(image_info),
+                }
+                .build()
+            })
+            .collect::<Vec<_>>();
+
+        unsafe { context.device.update_descriptor_sets(&write_sets, &[]) };
+
+        Ok(DescriptorSet { handle, layout })
+    }
+}
+
+fn is_buffer_descriptor(ty: vk::DescriptorType) -> bool {
+    matches!(
+        ty,
+        vk::DescriptorType::UNIFORM_BUFFER
+            | vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC
+            | vk::DescriptorType::STORAGE_BUFFER
+            | vk::DescriptorType::STORAGE_BUFFER_DYNAMIC
+    )
+}
@@ -57,6 +57,8 @@ mod sets {
         pub pipeline_layout: vk::PipelineLayout,
         pub first_set: u32,
         pub descriptor_set_handles: &'a [vk::DescriptorSet],
+        /// one offset per dynamic descriptor in the bound sets, in binding order
+        pub dynamic_offsets: &'a [u32],
     }
 
     impl VkContext {
@@ -67,6 +69,7 @@ mod sets {
                 pipeline_layout,
                 first_set,
                 descriptor_set_handles,
+                dynamic_offsets,
             } = bind_info;
 
             unsafe {
@@ -76,7 +79,7 @@ mod sets {
                     pipeline_layout,
                     first_set,
                     &descriptor_set_handles,
-                    &[],
+                    &dynamic_offsets,
                 )
             }
         }
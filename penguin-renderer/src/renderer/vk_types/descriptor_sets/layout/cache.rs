@@ -0,0 +1,42 @@
+use crate::renderer::vk_types::{DescriptorSetLayout, DescriptorSetLayoutBuilder, VkContext};
+use std::collections::HashMap;
+
+/// Caches VkDescriptorSetLayouts to avoid creating a bunch of duplicates.
+///
+///     Layouts are looked up by their bindings, so requesting a layout with the same bindings
+///     twice returns the same handle. The cache owns the layouts it creates.
+#[derive(Default)]
+pub struct DescriptorLayoutCache {
+    layouts: HashMap<DescriptorSetLayoutBuilder, DescriptorSetLayout>,
+}
+
+impl DescriptorLayoutCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn destroy(&mut self, context: &VkContext) {
+        self.layouts
+            .drain()
+            .for_each(|(_bindings, layout)| layout.destroy(context));
+    }
+
+    /// Returns the cached layout for the given bindings, creating it if it doesn't exist yet.
+    pub fn create_descriptor_layout(
+        &mut self,
+        context: &VkContext,
+        layout_builder: DescriptorSetLayoutBuilder,
+    ) -> DescriptorSetLayout {
+        let layout_builder = layout_builder.sorted();
+
+        if let Some(&layout) = self.layouts.get(&layout_builder) {
+            return layout;
+        }
+
+        let layout = layout_builder.clone().build(context);
+        self.layouts.insert(layout_builder, layout);
+
+        log::debug!("Descriptor layout cache: {} layouts", self.layouts.len());
+        layout
+    }
+}
@@ -94,6 +94,13 @@ impl DescriptorSetLayoutBuilder {
         self
     }
 
+    /// Sorts the bindings by binding index, so that builders describing the same layout
+    /// compare and hash equal no matter in which order the bindings were added.
+    pub(super) fn sorted(mut self) -> Self {
+        self.layout_bindings.sort_by_key(|binding| binding.binding);
+        self
+    }
+
     pub fn build_vk_type(self, context: &VkContext) -> vk::DescriptorSetLayout {
         // create layout
         let bindings = self
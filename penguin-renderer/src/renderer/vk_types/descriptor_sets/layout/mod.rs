@@ -1,2 +1,5 @@
 mod layout;
 pub use layout::*;
+
+mod cache;
+pub use cache::*;
@@ -9,8 +9,11 @@ pub use layout::*;
 mod pool;
 pub use pool::*;
 
+mod allocator;
+pub use allocator::*;
+
 mod set;
 pub use set::*;
 
-// todo
-mod todo;
+mod builder;
+pub use builder::*;
@@ -1,26 +1,34 @@
-use crate::renderer::vk_types::{DescriptorPool, DescriptorSetContainer, VkContext};
+use crate::renderer::vk_types::{
+    DescriptorAllocator, DescriptorBuilder, DescriptorLayoutCache, DescriptorPool, DescriptorSet,
+    DescriptorSetContainer, DescriptorSetLayout, DeviceFeature, FeatureRequest, VkContext,
+};
 use ash::vk;
+use std::collections::HashMap;
 
 pub struct DescriptorSetsResource {
-    pub pool: DescriptorPool,
+    pub allocator: DescriptorAllocator,
+    pub layout_cache: DescriptorLayoutCache,
     pub sets: Vec<DescriptorSetContainer>,
 }
 impl Default for DescriptorSetsResource {
     fn default() -> Self {
         Self {
-            pool: DescriptorPool::default(),
+            allocator: DescriptorAllocator::new(),
+            layout_cache: DescriptorLayoutCache::new(),
             sets: Vec::with_capacity(4),
         }
     }
 }
 impl DescriptorSetsResource {
-    pub fn init_pool(&mut self, pool: DescriptorPool) {
-        self.pool = pool;
-    }
-
     pub fn destroy(&mut self, context: &VkContext) {
         self.sets.iter_mut().for_each(|set| set.destroy(context));
-        self.pool.destroy(context);
+        self.allocator.destroy(context);
+        self.layout_cache.destroy(context);
+    }
+
+    /// Starts building a descriptor set allocated from the resource's allocator.
+    pub fn builder(&mut self) -> DescriptorBuilder {
+        DescriptorBuilder::builder(&mut self.layout_cache, &mut self.allocator)
     }
 
     pub fn get_set_handles(&self, set_ids: &[usize]) -> Vec<vk::DescriptorSet> {
@@ -34,3 +42,160 @@ impl DescriptorSetsResource {
         &self.sets[set]
     }
 }
+
+/// One global, partially bound array of combined image samplers that every texture gets
+/// registered into. Shaders index it with the texture index passed per object.
+///
+///     The set is allocated from its own UPDATE_AFTER_BIND pool, so textures can be registered
+///     while command buffers using the set are still in flight.
+#[derive(Default)]
+pub struct BindlessTexturesResource {
+    pool: DescriptorPool,
+    set: DescriptorSet,
+    texture_count: u32,
+    /// index of every registered texture, so textures shared by materials take one slot
+    registered: HashMap<(vk::ImageView, vk::Sampler), u32>,
+    enabled: bool,
+}
+impl BindlessTexturesResource {
+    const BINDING: u32 = 0;
+
+    /// The descriptor indexing features the texture array relies on.
+    pub const FEATURE_REQUEST: FeatureRequest = FeatureRequest {
+        subsystem: "bindless textures",
+        required: false,
+        extensions: &[],
+        features: &[
+            DeviceFeature::ShaderSampledImageArrayNonUniformIndexing,
+            DeviceFeature::DescriptorBindingSampledImageUpdateAfterBind,
+            DeviceFeature::DescriptorBindingPartiallyBound,
+            DeviceFeature::DescriptorBindingVariableDescriptorCount,
+            DeviceFeature::RuntimeDescriptorArray,
+        ],
+    };
+
+    /// Creates the texture array if the device has descriptor indexing enabled.
+    pub fn init(&mut self, context: &VkContext) {
+        if !context
+            .enabled_features()
+            .is_enabled(Self::FEATURE_REQUEST.subsystem)
+        {
+            log::info!("Bindless textures disabled.");
+            return;
+        }
+
+        let max_textures = crate::config::MAX_BINDLESS_TEXTURES;
+
+        let bindings = [vk::DescriptorSetLayoutBinding::builder()
+            .binding(Self::BINDING)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .descriptor_count(max_textures)
+            .stage_flags(vk::ShaderStageFlags::FRAGMENT)
+            .build()];
+
+        let binding_flags = [vk::DescriptorBindingFlags::PARTIALLY_BOUND
+            | vk::DescriptorBindingFlags::UPDATE_AFTER_BIND
+            | vk::DescriptorBindingFlags::VARIABLE_DESCRIPTOR_COUNT];
+
+        let mut binding_flags_create_info =
+            vk::DescriptorSetLayoutBindingFlagsCreateInfo::builder().binding_flags(&binding_flags);
+
+        let layout_create_info = vk::DescriptorSetLayoutCreateInfo::builder()
+            .flags(vk::DescriptorSetLayoutCreateFlags::UPDATE_AFTER_BIND_POOL)
+            .bindings(&bindings)
+            .push_next(&mut binding_flags_create_info);
+
+        let layout = DescriptorSetLayout {
+            handle: unsafe {
+                context
+                    .device
+                    .create_descriptor_set_layout(&layout_create_info, None)
+            }
+            .expect("Couldn't create bindless texture set layout"),
+        };
+
+        self.pool = DescriptorPool::from_sizes(
+            context,
+            1,
+            vk::DescriptorPoolCreateFlags::UPDATE_AFTER_BIND,
+            &[vk::DescriptorPoolSize::builder()
+                .ty(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .descriptor_count(max_textures)
+                .build()],
+        );
+
+        let set_layouts = [layout.handle];
+        let descriptor_counts = [max_textures];
+        let mut variable_count_allocate_info =
+            vk::DescriptorSetVariableDescriptorCountAllocateInfo::builder()
+                .descriptor_counts(&descriptor_counts);
+
+        let allocate_info = vk::DescriptorSetAllocateInfo::builder()
+            .descriptor_pool(self.pool.handle)
+            .set_layouts(&set_layouts)
+            .push_next(&mut variable_count_allocate_info);
+
+        let handle = unsafe { context.device.allocate_descriptor_sets(&allocate_info) }
+            .expect("Couldn't allocate bindless texture set")[0];
+        context.set_object_name(handle, "bindless textures");
+
+        self.set = DescriptorSet { handle, layout };
+        self.enabled = true;
+    }
+
+    pub fn destroy(&mut self, context: &VkContext) {
+        if self.enabled {
+            self.pool.destroy(context);
+            self.set.layout.destroy(context);
+            self.enabled = false;
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set(&self) -> &DescriptorSet {
+        &self.set
+    }
+
+    /// Writes the texture into the next free slot of the array and returns its index. Registering
+    /// a texture again returns the index it already has.
+    pub fn register(
+        &mut self,
+        context: &VkContext,
+        image_view: vk::ImageView,
+        sampler: vk::Sampler,
+    ) -> u32 {
+        assert!(self.enabled, "bindless textures aren't enabled");
+        if let Some(&texture_index) = self.registered.get(&(image_view, sampler)) {
+            return texture_index;
+        }
+        assert!(
+            self.texture_count < crate::config::MAX_BINDLESS_TEXTURES,
+            "bindless texture array is full"
+        );
+
+        let texture_index = self.texture_count;
+        self.texture_count += 1;
+
+        let image_info = [vk::DescriptorImageInfo::builder()
+            .sampler(sampler)
+            .image_view(image_view)
+            .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+            .build()];
+
+        let write_set = vk::WriteDescriptorSet::builder()
+            .dst_set(self.set.handle)
+            .dst_binding(Self::BINDING)
+            .dst_array_element(texture_index)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .image_info(&image_info)
+            .build();
+
+        unsafe { context.device.update_descriptor_sets(&[write_set], &[]) };
+        self.registered.insert((image_view, sampler), texture_index);
+
+        texture_index
+    }
+}
@@ -1,5 +1,5 @@
 use crate::renderer::memory::AllocatedBuffer;
-use crate::renderer::vk_types::{DescriptorPool, DescriptorSetLayout, VkContext};
+use crate::renderer::vk_types::{DescriptorAllocator, DescriptorSetLayout, VkContext};
 use ash::vk;
 
 use anyhow::*;
@@ -22,19 +22,13 @@ impl DescriptorSetBuilder {
     pub fn build(
         self,
         context: &VkContext,
-        descriptor_pool: &DescriptorPool,
+        allocator: &mut DescriptorAllocator,
     ) -> Result<DescriptorSet> {
         let layout = self
             .layout
             .expect("trying to build descriptor set without layout");
 
-        let descriptor_set = unsafe {
-            context.device.allocate_descriptor_sets(
-                &vk::DescriptorSetAllocateInfo::builder()
-                    .descriptor_pool(descriptor_pool.handle)
-                    .set_layouts(&[layout.handle]),
-            )
-        }?[0];
+        let descriptor_set = allocator.allocate_set(context, layout)?;
 
         Ok(DescriptorSet {
             handle: descriptor_set,
@@ -70,11 +64,12 @@ impl DescriptorSetContainer {
         self.set.handle
     }
 
+    /// Destroys the buffers owned by the container. The set is freed together with the pool it
+    /// was allocated from and the layout is owned by the layout cache.
     pub fn destroy(&mut self, context: &VkContext) {
         self.allocated_buffers
             .iter_mut()
             .for_each(|buffer| buffer.destroy(context));
-        self.set.destroy(context);
     }
 
     pub fn bind(&self, context: &VkContext, command_buffer: vk::CommandBuffer) {
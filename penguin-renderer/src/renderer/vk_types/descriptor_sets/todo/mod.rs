@@ -1,4 +0,0 @@
-mod allocator;
-mod descriptor_builder;
-mod layout_cache;
-mod resource;
@@ -1,7 +0,0 @@
-use crate::renderer::vk_types::descriptor_sets::todo::allocator::DescriptorAllocator;
-//use crate::renderer::vk_types::DescriptorSetLayoutCache;
-
-pub struct DescriptorResource {
-    allocator: DescriptorAllocator,
-    //layout_cache: DescriptorSetLayoutCache,
-}
@@ -7,9 +7,6 @@ pub use vk_components::init_vk_components;
 pub use vk_components::*;
 // -- end of containers --
 
-mod depth_image;
-pub use depth_image::*;
-
 pub mod descriptor_sets;
 pub use descriptor_sets::*;
 
@@ -32,6 +32,8 @@ pub struct PipelineBuilder<'a> {
     color_blend_attachments: Vec<vk::PipelineColorBlendAttachmentStateBuilder<'a>>,
     color_blending: vk::PipelineColorBlendStateCreateInfoBuilder<'a>,
 
+    dynamic_states: Vec<vk::DynamicState>,
+
     #[allow(unused)] // todo
     vertex_shader_push_constants_byte_offset: Option<u32>,
     #[allow(unused)] // todo
@@ -171,6 +173,7 @@ impl<'a> PipelineBuilder<'a> {
             depth_stencil,
             color_blend_attachments,
             color_blending,
+            dynamic_states: Vec::new(),
             vertex_shader_push_constants_byte_offset: None,
             fragment_shader_push_constants_byte_offset: None,
             pipeline_layout: None,
@@ -195,6 +198,86 @@ impl<'a> PipelineBuilder<'a> {
         self
     }
 
+    /// Rasterization samples, has to match the sample count of the render pass' attachments.
+    pub fn samples(mut self, samples: vk::SampleCountFlags) -> Self {
+        self.multisampling = self.multisampling.rasterization_samples(samples);
+        self
+    }
+
+    pub fn cull_mode(mut self, cull_mode: vk::CullModeFlags) -> Self {
+        self.rasterization = self.rasterization.cull_mode(cull_mode);
+        self
+    }
+
+    /// Disables depth testing and writing, for pipelines used in render passes without a depth
+    /// attachment.
+    pub fn no_depth(mut self) -> Self {
+        self.depth_stencil = self
+            .depth_stencil
+            .depth_test_enable(false)
+            .depth_write_enable(false);
+        self
+    }
+
+    /// How fragments are tested against the depth attachment, has to fit the render pass' depth
+    /// range.
+    pub fn depth_compare_op(mut self, compare_op: vk::CompareOp) -> Self {
+        self.depth_stencil = self.depth_stencil.depth_compare_op(compare_op);
+        self
+    }
+
+    /// Keeps depth testing but doesn't write depth, so that transparent surfaces don't hide what's
+    /// drawn behind them afterwards.
+    pub fn no_depth_write(mut self) -> Self {
+        self.depth_stencil = self.depth_stencil.depth_write_enable(false);
+        self
+    }
+
+    /// Blends the color output over the attachment by its alpha. Alpha itself accumulates the
+    /// coverage of everything drawn.
+    pub fn alpha_blending(mut self) -> Self {
+        self.color_blend_attachments = self
+            .color_blend_attachments
+            .into_iter()
+            .map(|attachment| {
+                attachment
+                    .blend_enable(true)
+                    .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
+                    .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+                    .color_blend_op(vk::BlendOp::ADD)
+                    .src_alpha_blend_factor(vk::BlendFactor::ONE)
+                    .dst_alpha_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+                    .alpha_blend_op(vk::BlendOp::ADD)
+            })
+            .collect();
+        self
+    }
+
+    /// Removes the color blend state, for render passes that only have a depth attachment.
+    pub fn depth_only(mut self) -> Self {
+        self.color_blend_attachments.clear();
+        self
+    }
+
+    /// Offsets the depth written by each polygon by a constant amount plus an amount scaled by
+    /// the polygon's slope, to keep surfaces from shadowing themselves.
+    pub fn depth_bias(mut self, constant_factor: f32, slope_factor: f32) -> Self {
+        self.rasterization = self
+            .rasterization
+            .depth_bias_enable(true)
+            .depth_bias_constant_factor(constant_factor)
+            .depth_bias_slope_factor(slope_factor);
+        self
+    }
+
+    /// Viewport and scissor are set while recording instead of being baked into the pipeline,
+    /// for pipelines that draw into images of different sizes.
+    pub fn dynamic_viewport(mut self) -> Self {
+        self.dynamic_states
+            .extend([vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR]);
+        self
+    }
+
     #[allow(dead_code)]
     pub fn wireframe_mode(mut self) -> Self {
         self.rasterization = self.rasterization.polygon_mode(vk::PolygonMode::LINE);
@@ -294,6 +377,10 @@ impl<'a> PipelineBuilder<'a> {
 
         let color_blending = self.color_blending.attachments(&color_blend_attachments);
 
+        // Dynamic state
+        let dynamic_state =
+            vk::PipelineDynamicStateCreateInfo::builder().dynamic_states(&self.dynamic_states);
+
         // Pipeline layout
         //
 
@@ -378,7 +465,7 @@ impl<'a> PipelineBuilder<'a> {
             .multisample_state(&multisampling)
             .depth_stencil_state(&depth_stencil)
             .color_blend_state(&color_blending)
-            // .dynamic_state()
+            .dynamic_state(&dynamic_state)
             .layout(pipeline_layout)
             //
             .render_pass(render_pass)
@@ -48,6 +48,7 @@ impl PipelineLayout {
 #[derive(Default)]
 pub struct PipelineLayoutBuilder {
     set_layouts: Vec<vk::DescriptorSetLayout>,
+    push_constant_ranges: Vec<vk::PushConstantRange>,
 }
 impl PipelineLayoutBuilder {
     pub fn add_layout(mut self, layout: vk::DescriptorSetLayout) -> Self {
@@ -55,9 +56,24 @@ impl PipelineLayoutBuilder {
         self
     }
 
+    pub fn push_constant_range(
+        mut self,
+        stage_flags: vk::ShaderStageFlags,
+        offset: u32,
+        size: u32,
+    ) -> Self {
+        self.push_constant_ranges.push(vk::PushConstantRange {
+            stage_flags,
+            offset,
+            size,
+        });
+        self
+    }
+
     pub fn build(self, context: &VkContext) -> PipelineLayout {
         let pipeline_layout_create_info = vk::PipelineLayoutCreateInfo::builder()
             .set_layouts(self.set_layouts.as_slice())
+            .push_constant_ranges(self.push_constant_ranges.as_slice())
             .build();
 
         let pipeline_layout = unsafe {
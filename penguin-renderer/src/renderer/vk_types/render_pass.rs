@@ -1,12 +1,53 @@
-use crate::renderer::vk_types::{Swapchain, VkContext};
+use crate::renderer::vk_types::VkContext;
 use ash::vk;
+use serde::Deserialize;
 
 use crate::impl_deref;
 
+/// How the scene's depth buffer is set up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(default)]
+pub struct DepthSettings {
+    /// Maps the near plane to depth 1 and an infinitely far plane to 0. Floating point depth has
+    /// most of its precision close to 0, which reversing spreads evenly over the view distance.
+    pub reversed_z: bool,
+    /// Picks a depth format with a stencil aspect.
+    pub stencil: bool,
+}
+
+/// Depth formats in the order they're preferred, the first one the device supports is used.
+///
+///     32 bit float depth comes first since it's the most precise and reversed-z needs it to
+///     pay off. Formats without stencil are only candidates if no stencil is needed.
+pub fn depth_format_candidates(stencil: bool) -> &'static [vk::Format] {
+    if stencil {
+        &[
+            vk::Format::D32_SFLOAT_S8_UINT,
+            vk::Format::D24_UNORM_S8_UINT,
+            vk::Format::D16_UNORM_S8_UINT,
+        ]
+    } else {
+        &[
+            vk::Format::D32_SFLOAT,
+            vk::Format::D32_SFLOAT_S8_UINT,
+            vk::Format::X8_D24_UNORM_PACK32,
+            vk::Format::D24_UNORM_S8_UINT,
+            vk::Format::D16_UNORM,
+        ]
+    }
+}
+
+/// Render pass that pipelines are created against. Frames are recorded in render passes created
+/// by the render graph, which are compatible with this one as long as the attachment formats and
+/// sample counts match.
 #[derive(Debug)]
 pub struct RenderPass {
     pub handle: vk::RenderPass,
     pub attachment_count: usize,
+    pub depth_format: vk::Format,
+    /// pipelines drawn into the depth attachment have to use `depth_compare_op`
+    pub reversed_z: bool,
+    pub samples: vk::SampleCountFlags,
 }
 impl_deref!(RenderPass, handle, vk::RenderPass);
 
@@ -17,35 +58,98 @@ impl RenderPass {
         }
     }
 
-    pub fn init(context: &VkContext, swapchain: &Swapchain) -> Self {
-        Self::create_default_render_pass(context, swapchain.format)
+    /// With more than one sample, the color and depth attachments are multisampled and the
+    /// color attachment is resolved into a single sampled image of the same format.
+    pub fn init(
+        context: &VkContext,
+        color_format: vk::Format,
+        samples: vk::SampleCountFlags,
+        depth_settings: DepthSettings,
+    ) -> Self {
+        let depth_format = Self::find_depth_image_format(context, depth_settings.stencil);
+        log::debug!("depth format: {:?}", depth_format);
+        log::debug!("reversed z: {}", depth_settings.reversed_z);
+        log::debug!("msaa samples: {:?}", samples);
+
+        Self::create_default_render_pass(
+            context,
+            color_format,
+            (depth_format, depth_settings.reversed_z),
+            samples,
+        )
+    }
+
+    pub fn is_multisampled(&self) -> bool {
+        self.samples != vk::SampleCountFlags::TYPE_1
+    }
+
+    pub fn find_depth_image_format(context: &VkContext, stencil: bool) -> vk::Format {
+        context
+            .find_supported_format(
+                depth_format_candidates(stencil),
+                vk::ImageTiling::OPTIMAL,
+                vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT,
+            )
+            .expect("couldn't find suitable depth format")
+    }
+
+    /// Compare op of pipelines that test against the depth attachment, closer fragments pass.
+    pub fn depth_compare_op(&self) -> vk::CompareOp {
+        if self.reversed_z {
+            vk::CompareOp::GREATER_OR_EQUAL
+        } else {
+            vk::CompareOp::LESS_OR_EQUAL
+        }
+    }
+
+    /// Depth the attachment is cleared to, the farthest possible.
+    pub fn depth_clear_value(&self) -> vk::ClearValue {
+        vk::ClearValue {
+            depth_stencil: vk::ClearDepthStencilValue {
+                depth: if self.reversed_z { 0.0 } else { 1.0 },
+                stencil: 0,
+            },
+        }
     }
 
-    fn create_default_render_pass(context: &VkContext, swapchain_format: vk::Format) -> Self {
+    fn create_default_render_pass(
+        context: &VkContext,
+        color_format: vk::Format,
+        (depth_format, reversed_z): (vk::Format, bool),
+        samples: vk::SampleCountFlags,
+    ) -> Self {
+        let multisampled = samples != vk::SampleCountFlags::TYPE_1;
+
         // description of image for writing render commands into
-        let render_pass_attachments = [
+        let mut render_pass_attachments = vec![
             // color attachment
             vk::AttachmentDescription::builder()
-                .format(swapchain_format)
-                // 1 sample, no MSAA
-                .samples(vk::SampleCountFlags::TYPE_1)
+                .format(color_format)
+                .samples(samples)
                 // clear image on attachment load
                 .load_op(vk::AttachmentLoadOp::CLEAR)
-                // store image for being read later
-                .store_op(vk::AttachmentStoreOp::STORE)
+                // store image for being read later, multisampled images are only resolved
+                .store_op(if multisampled {
+                    vk::AttachmentStoreOp::DONT_CARE
+                } else {
+                    vk::AttachmentStoreOp::STORE
+                })
                 // no stencil
                 .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
                 .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
                 // starting layout doesn't matter
                 .initial_layout(vk::ImageLayout::UNDEFINED)
-                // layout ready for display
-                .final_layout(vk::ImageLayout::PRESENT_SRC_KHR)
+                // layout ready for being sampled by post processing
+                .final_layout(if multisampled {
+                    vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL
+                } else {
+                    vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL
+                })
                 .build(),
             // depth attachment
             vk::AttachmentDescription::builder()
-                //.format(vk::Format::D32_SFLOAT) // TODO: Find supported format
-                .format(vk::Format::D16_UNORM) // TODO: Find supported format
-                .samples(vk::SampleCountFlags::TYPE_1)
+                .format(depth_format)
+                .samples(samples)
                 .load_op(vk::AttachmentLoadOp::CLEAR)
                 .store_op(vk::AttachmentStoreOp::STORE)
                 .stencil_load_op(vk::AttachmentLoadOp::CLEAR)
@@ -55,6 +159,22 @@ impl RenderPass {
                 .build(),
         ];
 
+        if multisampled {
+            // resolve attachment
+            render_pass_attachments.push(
+                vk::AttachmentDescription::builder()
+                    .format(color_format)
+                    .samples(vk::SampleCountFlags::TYPE_1)
+                    .load_op(vk::AttachmentLoadOp::DONT_CARE)
+                    .store_op(vk::AttachmentStoreOp::STORE)
+                    .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+                    .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+                    .initial_layout(vk::ImageLayout::UNDEFINED)
+                    .final_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                    .build(),
+            );
+        }
+
         let color_attachment_ref = [vk::AttachmentReference::builder()
             .attachment(0)
             .layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
@@ -65,11 +185,19 @@ impl RenderPass {
             .layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
             .build();
 
-        let subpass = [vk::SubpassDescription::builder()
+        let resolve_attachment_ref = [vk::AttachmentReference::builder()
+            .attachment(2)
+            .layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+            .build()];
+
+        let mut subpass = vk::SubpassDescription::builder()
             .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
             .color_attachments(&color_attachment_ref)
-            .depth_stencil_attachment(&depth_attachment_ref)
-            .build()];
+            .depth_stencil_attachment(&depth_attachment_ref);
+        if multisampled {
+            subpass = subpass.resolve_attachments(&resolve_attachment_ref);
+        }
+        let subpass = [subpass.build()];
 
         //any dependencies here...
         let dependencies = [vk::SubpassDependency {
@@ -103,6 +231,41 @@ impl RenderPass {
             }
             .expect("Couldn't create render pass!"),
             attachment_count: render_pass_attachments.len(),
+            depth_format,
+            reversed_z,
+            samples,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::memory::util::aspect_mask_from_format;
+
+    #[test]
+    fn stencil_candidates_have_a_stencil_aspect() {
+        for &format in depth_format_candidates(true) {
+            let aspects = aspect_mask_from_format(format);
+            assert!(aspects.contains(vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL));
+        }
+    }
+
+    #[test]
+    fn float_depth_is_preferred() {
+        assert_eq!(depth_format_candidates(false)[0], vk::Format::D32_SFLOAT);
+        assert_eq!(
+            depth_format_candidates(true)[0],
+            vk::Format::D32_SFLOAT_S8_UINT
+        );
+    }
+
+    #[test]
+    fn every_candidate_has_depth() {
+        for stencil in [false, true] {
+            for &format in depth_format_candidates(stencil) {
+                assert!(aspect_mask_from_format(format).contains(vk::ImageAspectFlags::DEPTH));
+            }
         }
     }
 }
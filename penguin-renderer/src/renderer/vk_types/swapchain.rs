@@ -165,7 +165,10 @@ mod init {
     ) -> vk::SurfaceFormatKHR {
         // Use SRG if available
         for surface_format in available_surface_formats {
-            let supports_srgb = surface_format.format == vk::Format::B8G8R8_SRGB;
+            let supports_srgb = matches!(
+                surface_format.format,
+                vk::Format::B8G8R8_SRGB | vk::Format::B8G8R8A8_SRGB
+            );
             let supports_non_linear_color_space =
                 surface_format.color_space == vk::ColorSpaceKHR::SRGB_NONLINEAR;
 
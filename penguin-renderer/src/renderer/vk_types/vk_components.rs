@@ -1,35 +1,33 @@
 use crate::renderer::vk_types::vk_context::VkContext;
 /// ------------------------- VK COMPONENTS ----------------------------------
-use crate::renderer::vk_types::{DepthImage, FrameBuffers, RenderPass, Swapchain};
+use crate::renderer::vk_types::{DepthSettings, RenderPass, Swapchain};
+use ash::vk;
 
 pub struct VkComponents {
     pub swapchain: Swapchain,
-    pub depth_image: DepthImage,
     pub render_pass: RenderPass,
-    pub frame_buffers: FrameBuffers,
 }
 
 pub fn init_vk_components(
     window: &penguin_app::window::Window,
     context: &VkContext,
+    msaa_samples: vk::SampleCountFlags,
+    depth_settings: DepthSettings,
 ) -> VkComponents {
     log::trace!("Creating swapchain.");
     let swapchain = Swapchain::init(window, context);
     // ///////////////////////////////////////
-    log::trace!("Creating depth image.");
-    let depth_image = DepthImage::init(context, &swapchain);
-    // ///////////////////////////////////////
     log::trace!("Creating render pass.");
-    let render_pass = RenderPass::init(context, &swapchain);
-    // ///////////////////////////////////////
-    log::trace!("Creating frame buffers.");
-    let frame_buffers = FrameBuffers::init(context, &swapchain, &depth_image, &render_pass);
+    let render_pass = RenderPass::init(
+        context,
+        crate::config::HDR_COLOR_FORMAT,
+        msaa_samples,
+        depth_settings,
+    );
     // ///////////////////////////////////////
 
     VkComponents {
         swapchain,
-        depth_image,
         render_pass,
-        frame_buffers,
     }
 }
@@ -1,20 +1,26 @@
+use crate::renderer::debug::ValidationReport;
 use crate::renderer::vk_types::vk_context::instance::Instance;
 use anyhow::*;
 use ash::vk;
+use std::sync::Arc;
 
 pub struct DebugMessenger {
     pub debug_utils_loader: ash::extensions::ext::DebugUtils,
     pub handle_option: Option<vk::DebugUtilsMessengerEXT>,
+    /// validation warnings and errors the messenger reported
+    pub report: Arc<ValidationReport>,
 }
 
 impl DebugMessenger {
     pub(crate) fn init(instance: &Instance, log_level_filter: log::LevelFilter) -> Result<Self> {
+        let report = Arc::new(ValidationReport::default());
         let (debug_utils_loader, debug_messenger) =
-            init_vk_debug_messenger(&instance.entry, &instance.handle, log_level_filter)?;
+            init_vk_debug_messenger(&instance.entry, &instance.handle, log_level_filter, &report)?;
 
         Ok(Self {
             debug_utils_loader,
             handle_option: debug_messenger,
+            report,
         })
     }
 }
@@ -23,6 +29,7 @@ fn init_vk_debug_messenger(
     entry: &ash::Entry,
     instance: &ash::Instance,
     debug_log_level: log::LevelFilter,
+    report: &Arc<ValidationReport>,
 ) -> Result<(
     ash::extensions::ext::DebugUtils,
     Option<vk::DebugUtilsMessengerEXT>,
@@ -31,7 +38,7 @@ fn init_vk_debug_messenger(
 
     let debug_utils_loader = ash::extensions::ext::DebugUtils::new(entry, instance);
 
-    let messenger_create_info = init::debug_messenger_create_info(debug_log_level);
+    let messenger_create_info = init::debug_messenger_create_info(debug_log_level, report);
 
     let utils_messenger = if crate::config::VK_VALIDATION.is_enabled {
         unsafe {
@@ -46,18 +53,27 @@ fn init_vk_debug_messenger(
 
 mod init {
     //////////////
+    use crate::renderer::debug::{
+        parse_vk_general_message, parse_vk_validation_error_message, ValidationError,
+        ValidationLogFormat, ValidationReport, ValidationSeverity,
+    };
     use ash::vk;
     use log::LevelFilter;
+    use std::collections::hash_map::DefaultHasher;
     use std::ffi::CStr;
+    use std::hash::{Hash, Hasher};
     use std::os::raw::c_void;
+    use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+    use std::sync::Arc;
 
+    /// The report is passed to the callback as user data, so it has to outlive the messenger.
     pub fn debug_messenger_create_info(
         debug_log_level: log::LevelFilter,
+        report: &Arc<ValidationReport>,
     ) -> vk::DebugUtilsMessengerCreateInfoEXT {
+        // warnings and errors always go into the report, even if they aren't logged
         let message_severity = match debug_log_level {
-            LevelFilter::Off => vk::DebugUtilsMessageSeverityFlagsEXT::empty(),
-            LevelFilter::Error => vk::DebugUtilsMessageSeverityFlagsEXT::ERROR,
-            LevelFilter::Warn => {
+            LevelFilter::Off | LevelFilter::Error | LevelFilter::Warn => {
                 vk::DebugUtilsMessageSeverityFlagsEXT::WARNING
                     | vk::DebugUtilsMessageSeverityFlagsEXT::ERROR
             }
@@ -82,6 +98,7 @@ mod init {
                     | vk::DebugUtilsMessageTypeFlagsEXT::VALIDATION,
             )
             .pfn_user_callback(Some(vulkan_debug_utils_callback))
+            .user_data(Arc::as_ptr(report) as *mut c_void)
             .build()
     }
 
@@ -90,7 +107,7 @@ mod init {
         message_severity: vk::DebugUtilsMessageSeverityFlagsEXT,
         message_type: vk::DebugUtilsMessageTypeFlagsEXT,
         p_callback_data: *const vk::DebugUtilsMessengerCallbackDataEXT,
-        _p_user_data: *mut c_void,
+        p_user_data: *mut c_void,
     ) -> vk::Bool32 {
         let vk_message_type = match message_type {
             vk::DebugUtilsMessageTypeFlagsEXT::GENERAL => "[VULKAN GENERAL]",
@@ -99,42 +116,81 @@ mod init {
             _ => "[VULKAN UNKNOWN]",
         };
 
-        let message = CStr::from_ptr((*p_callback_data).p_message);
+        // the callback runs inside the driver, so nothing in here may panic
+        let message = CStr::from_ptr((*p_callback_data).p_message).to_string_lossy();
+
+        let severity = match message_severity {
+            vk::DebugUtilsMessageSeverityFlagsEXT::ERROR => Some(ValidationSeverity::Error),
+            vk::DebugUtilsMessageSeverityFlagsEXT::WARNING => Some(ValidationSeverity::Warning),
+            _ => None,
+        };
+        let report = (p_user_data as *const ValidationReport).as_ref();
+
+        let val_err = severity.map(|_| {
+            let mut val_err = parse_vk_validation_error_message(&message)
+                .unwrap_or_else(|_| ValidationError::unparsed(&message));
+            // newer layers only pass the id on the side
+            let p_message_id_name = (*p_callback_data).p_message_id_name;
+            if val_err.vuid.is_empty() && !p_message_id_name.is_null() {
+                val_err.set_vuid(&CStr::from_ptr(p_message_id_name).to_string_lossy());
+            }
+            val_err
+        });
 
-        static mut LAST_MSG: &str = "";
-        static mut IDENTICAL_MSG_COUNT: usize = 0;
+        if let Some(report) = report {
+            if let (Some(severity), Some(val_err)) = (severity, &val_err) {
+                if !report.record(severity, val_err) {
+                    return vk::FALSE;
+                }
+                if report.log_format() == ValidationLogFormat::Json {
+                    match severity {
+                        ValidationSeverity::Error => log::error!("{}", val_err.to_json()),
+                        ValidationSeverity::Warning => log::warn!("{}", val_err.to_json()),
+                    }
+                    return vk::FALSE;
+                }
+            }
+        }
 
-        match message_severity {
-            vk::DebugUtilsMessageSeverityFlagsEXT::ERROR => {
-                let msg = message.to_str().expect("Failed to convert &CStr to &str.");
-                let val_err = parser::parse_vk_validation_error_message(msg);
+        static LAST_MSG_HASH: AtomicU64 = AtomicU64::new(0);
+        static IDENTICAL_MSG_COUNT: AtomicUsize = AtomicUsize::new(0);
 
+        match (message_severity, val_err) {
+            (vk::DebugUtilsMessageSeverityFlagsEXT::ERROR, Some(val_err)) => {
                 let mut context_objs: String = String::new();
                 val_err.context_objects.iter().for_each(|obj| {
-                    context_objs +=
-                        &format!("\t| {} | {}: {}\n", obj.index, obj.vk_type, obj.handle);
+                    context_objs += &match &obj.name {
+                        Some(name) => format!(
+                            "\t| {} | {}: {} \"{}\"\n",
+                            obj.index, obj.vk_type, obj.handle, name
+                        ),
+                        None => format!("\t| {} | {}: {}\n", obj.index, obj.vk_type, obj.handle),
+                    };
                 });
 
                 // don't spam the identical error message over and over, just provide what's new
-                let same_msg = LAST_MSG == msg;
-
-                if IDENTICAL_MSG_COUNT > 3 {
-                    return vk::FALSE;
-                }
+                let msg_hash = {
+                    let mut hasher = DefaultHasher::new();
+                    message.hash(&mut hasher);
+                    hasher.finish()
+                };
+                let same_msg = LAST_MSG_HASH.swap(msg_hash, Ordering::Relaxed) == msg_hash;
 
                 let err_string = if same_msg {
                     // same message
-                    IDENTICAL_MSG_COUNT += 1;
+                    let count = IDENTICAL_MSG_COUNT.fetch_add(1, Ordering::Relaxed) + 1;
+                    if count > 4 {
+                        return vk::FALSE;
+                    }
                     format!("\n{vk_message_type} ({count})\n\t{vulkan_id}\n{context_objects}\n_______________\n",
                             vk_message_type = "[IDENTICAL]",
-                            count = IDENTICAL_MSG_COUNT,
+                            count = count,
                             vulkan_id = val_err.vulkan_id,
                             context_objects = context_objs,
                     )
                 } else {
                     // new message
-                    LAST_MSG = &msg;
-                    IDENTICAL_MSG_COUNT = 0;
+                    IDENTICAL_MSG_COUNT.store(0, Ordering::Relaxed);
 
                     format!("\n{vk_message_type}\n\t{vulkan_id}\n{context_objects}\nMSG: {message}\n\nISSUE: {spec_info}\n\nSPEC: {spec_link}\n_______________\n",
                             vk_message_type = vk_message_type,
@@ -148,15 +204,14 @@ mod init {
 
                 log::error!("{}", err_string);
             }
-            vk::DebugUtilsMessageSeverityFlagsEXT::WARNING => {
+            (vk::DebugUtilsMessageSeverityFlagsEXT::WARNING, _) => {
                 log::warn!("{} [{:?}]", vk_message_type, message)
             }
-            vk::DebugUtilsMessageSeverityFlagsEXT::INFO => {
-                let msg = message.to_str().expect("Failed to convert &Cstr to &str.");
-                let msg = parser::parse_vk_general_message(msg);
+            (vk::DebugUtilsMessageSeverityFlagsEXT::INFO, _) => {
+                let msg = parse_vk_general_message(&message);
                 log::info!("{} {}", vk_message_type, msg);
             }
-            vk::DebugUtilsMessageSeverityFlagsEXT::VERBOSE => {
+            (vk::DebugUtilsMessageSeverityFlagsEXT::VERBOSE, _) => {
                 if crate::config::VK_VERBOSE_LOGGING_ENABLE {
                     log::trace!("{} [{:?}]", vk_message_type, message);
                 }
@@ -166,166 +221,4 @@ mod init {
 
         ash::vk::FALSE
     }
-
-    mod parser {
-        pub struct ContextObj {
-            pub index: String,
-            pub vk_type: String,
-            pub handle: String,
-        }
-
-        pub struct ValidationError {
-            pub vulkan_id: String,
-            pub context_objects: Vec<ContextObj>,
-            pub message: String,
-            pub spec_info: String, // If there is a "The Vulkan spec states": -> issue, otherwise empty
-            pub spec_link: String,
-        }
-        impl ValidationError {
-            fn simple_msg(msg: &str) -> Self {
-                Self {
-                    vulkan_id: "".to_owned(),
-                    context_objects: vec![ContextObj {
-                        index: "".to_owned(),
-                        vk_type: "".to_owned(),
-                        handle: "".to_owned(),
-                    }],
-                    message: msg.to_owned(),
-                    spec_info: "".to_owned(),
-                    spec_link: "".to_owned(),
-                }
-            }
-        }
-
-        pub fn parse_vk_general_message(input: &str) -> &str {
-            &input
-        }
-
-        pub fn parse_vk_validation_error_message(input: &str) -> ValidationError {
-            let sections: Vec<String> = input
-                .clone()
-                .split("|")
-                .map(|str| str.to_string())
-                .collect();
-
-            let context_section = sections.iter().find_map(|s| {
-                if s.contains("[ ") && s.contains(" ]") {
-                    Some(s)
-                } else {
-                    None
-                }
-            });
-
-            if let Some(context) = context_section {
-                // find vulkan id (name of vk object/function that failed)
-                let (vuid, end_index) = find_vulkan_id(&context);
-
-                let context_objs: Vec<ContextObj> = context[end_index..]
-                    .split("Object")
-                    .filter_map(|object| {
-                        if let Some((index, rest)) = object.split_once(":") {
-                            let index = index.replace(" ", "");
-
-                            if let Some((handle, vk_type)) = rest.split_once(",") {
-                                let handle = handle
-                                    .split_once("= ")
-                                    .unwrap_or(("", "Unknown handle"))
-                                    .1
-                                    .replace(" ", "");
-                                let vk_type = vk_type
-                                    .split_once("= ")
-                                    .unwrap_or(("", "Unknown type"))
-                                    .1
-                                    .replace(" ", "")
-                                    .replace(";", "");
-
-                                let err = ContextObj {
-                                    index,
-                                    vk_type,
-                                    handle,
-                                };
-                                Some(err)
-                            } else {
-                                None
-                            }
-                        } else {
-                            None
-                        }
-                    })
-                    .collect();
-
-                let vuid = vuid.split_once("VUID-").unwrap_or(("", vuid)).1;
-                let vuid = vuid.rsplit_once("-").unwrap_or((vuid, "")).0;
-
-                let message = sections.iter().find_map(|s| {
-                    if s.contains("(") {
-                        Some(s.to_owned())
-                    } else {
-                        None
-                    }
-                });
-
-                if let Some(msg) = message {
-                    // let adresses: Vec<String> = context_objs.iter().map(|cxt_obj| {
-                    //     println!("MEMORY ADRESS INDEX {}", cxt_obj.handle);
-                    // }).collect();
-                    let mut msg = msg.to_owned();
-
-                    for (i, obj) in context_objs.iter().enumerate() {
-                        let index: String = "|".to_owned() + &i.to_string() + "|"; // "|i|"
-                        msg = msg.replace(&(obj.handle.to_owned() + "[]"), &index);
-                        msg = msg.replace(&obj.handle, &index);
-                    }
-
-                    let (msg, spec_info) = msg
-                        .split_once("The Vulkan spec states: ")
-                        .unwrap_or((&msg, ""));
-
-                    // get link part
-                    let link_pattern = "(https://".to_owned();
-                    let (spec_info, spec_link) = spec_info
-                        .split_once(&link_pattern)
-                        .unwrap_or((spec_info, ""));
-
-                    // trim trailing whitespace and add .
-                    let mut spec_info = spec_info.to_owned().trim_end().to_string();
-                    spec_info += ".";
-
-                    // add back split off link pattern
-                    let mut spec_link = spec_link.to_owned();
-                    if !spec_link.is_empty() {
-                        spec_link = link_pattern + &spec_link;
-                    }
-
-                    ValidationError {
-                        vulkan_id: vuid.to_owned(),
-                        context_objects: context_objs,
-                        message: msg.to_owned(),
-                        spec_info,
-                        spec_link,
-                    }
-                } else {
-                    // failed to parse
-                    ValidationError::simple_msg(input)
-                }
-            } else {
-                // failed to parse
-                ValidationError::simple_msg(input)
-            }
-        }
-
-        // Helper functions --------------------------
-
-        /// Finds the VUID section of the given context section of a vulkan validation error
-        /// string. Returns a readable version of the VUID along with the index in the context
-        /// string at which the VUID section ends.
-        fn find_vulkan_id(context_section: &str) -> (&str, usize) {
-            let (vuid, end_index) = {
-                let from = context_section.find(|c| c == '[').unwrap();
-                let to = context_section.find(|c| c == ']').unwrap();
-                (&context_section[from + 2..to], to)
-            };
-            (vuid, end_index)
-        }
-    }
 }
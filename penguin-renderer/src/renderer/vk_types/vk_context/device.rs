@@ -1,4 +1,6 @@
-use crate::renderer::vk_types::{Instance, PhysicalDevice};
+use crate::renderer::vk_types::{
+    renderer_feature_requests, EnabledFeatures, Instance, PhysicalDevice, SupportedFeatures,
+};
 use ash::vk;
 
 use crate::impl_deref;
@@ -6,6 +8,8 @@ use crate::impl_deref;
 pub struct Device {
     pub handle: ash::Device,
     pub graphics_queue_handle: vk::Queue,
+    /// The extensions and features the device was created with.
+    pub enabled_features: EnabledFeatures,
 }
 impl_deref!(Device, handle, ash::Device);
 
@@ -13,11 +17,25 @@ impl super::Device {
     pub(crate) fn init(instance: &Instance, physical_device: &PhysicalDevice) -> Self {
         log::trace!("Queue index: {}", physical_device.graphics_queue_index);
 
+        log::trace!("Negotiating device features");
+        let supported = SupportedFeatures::query(&instance.handle, physical_device.handle);
+        // physical device selection rejects devices without the required features
+        let enabled_features = EnabledFeatures::negotiate(&renderer_feature_requests(), &supported)
+            .unwrap_or_else(|missing| {
+                panic!("Device doesn't support {}", missing.join(", "));
+            });
+        log::debug!(
+            "Enabled device extensions: {:?}",
+            enabled_features.extensions
+        );
+        log::debug!("Enabled device features: {:?}", enabled_features.features);
+
         log::trace!("Creating logical device");
-        let device: ash::Device = create_logical_device(
+        let device: ash::Device = init::create_logical_device(
             &instance.handle,
             physical_device.handle,
             physical_device.graphics_queue_index,
+            &enabled_features,
         );
 
         log::trace!("Getting graphics queue handle");
@@ -27,18 +45,11 @@ impl super::Device {
         Self {
             handle: device,
             graphics_queue_handle: queue_handle,
+            enabled_features,
         }
     }
 }
 
-pub fn create_logical_device(
-    instance: &ash::Instance,
-    physical_device: vk::PhysicalDevice,
-    graphics_queue_index: u32,
-) -> ash::Device {
-    init::create_logical_device(&instance, physical_device, graphics_queue_index)
-}
-
 pub fn get_graphics_queue_handle(
     logical_device: &ash::Device,
     graphics_queue_index: u32,
@@ -49,49 +60,49 @@ pub fn get_graphics_queue_handle(
 }
 
 mod init {
+    use crate::renderer::vk_types::EnabledFeatures;
     use ash::vk;
     use std::ffi::CString;
-    use std::ptr;
 
     // ------------------- LOGICAL DEVICE ---------------------------------
-    fn required_device_features() -> vk::PhysicalDeviceFeatures {
-        // TODO: Support separate depth stencil layouts if feature is available. This allows for optimal tiling rather than linear (render pass create info -> pAttachemnts[1].finalLayout
-
-        vk::PhysicalDeviceFeatures {
-            fill_mode_non_solid: 1, // for wireframe mode
-            geometry_shader: 1,
-            tessellation_shader: 1,
-            shader_float64: 1,
-            ..Default::default()
-        }
-    }
-
     pub(crate) fn create_logical_device(
         instance: &ash::Instance,
         physical_device: vk::PhysicalDevice,
         graphics_queue_index: u32,
+        enabled_features: &EnabledFeatures,
     ) -> ash::Device {
         let priorities = [1.0_f32];
 
-        let graphics_queue_create_info = vk::DeviceQueueCreateInfo {
-            queue_family_index: graphics_queue_index,
-            queue_count: 1,
-            p_queue_priorities: priorities.as_ptr(),
-            ..Default::default()
-        };
+        let queue_create_infos = [vk::DeviceQueueCreateInfo::builder()
+            .queue_family_index(graphics_queue_index)
+            .queue_priorities(&priorities)
+            .build()];
 
         // Specify device features to use
-        let physical_device_features = required_device_features();
-
-        let enable_extension_names = [ash::extensions::khr::Swapchain::name().as_ptr()];
+        let mut feature_chain = enabled_features.feature_chain();
 
-        // validation layers
-        let enabled_validation_layers_raw: Vec<CString> = crate::config::VK_VALIDATION
-            .required_validation_layers
+        let enabled_extensions_raw: Vec<CString> = enabled_features
+            .extensions
+            .iter()
+            .map(|name| CString::new(name.as_str()).expect("Couldn't unwrap extension name"))
+            .collect();
+        let enabled_extensions: Vec<*const std::os::raw::c_char> = enabled_extensions_raw
             .iter()
-            .map(|name| CString::new(*name).expect("Couldn't unwrap layer name ptr"))
+            .map(|name| name.as_ptr())
             .collect();
 
+        // validation layers
+        let enabled_validation_layers_raw: Vec<CString> = if crate::config::VK_VALIDATION.is_enabled
+        {
+            crate::config::VK_VALIDATION
+                .required_validation_layers
+                .iter()
+                .map(|name| CString::new(*name).expect("Couldn't unwrap layer name ptr"))
+                .collect()
+        } else {
+            Vec::new()
+        };
+
         let enabled_validation_layers: Vec<*const std::os::raw::c_char> =
             enabled_validation_layers_raw
                 .iter()
@@ -99,24 +110,16 @@ mod init {
                 .collect();
 
         // Create logical device info
-        let create_info = vk::DeviceCreateInfo {
-            queue_create_info_count: 1,
-            p_queue_create_infos: &graphics_queue_create_info,
-            p_enabled_features: &physical_device_features,
-            enabled_extension_count: enable_extension_names.len() as u32,
-            pp_enabled_extension_names: enable_extension_names.as_ptr(),
-            enabled_layer_count: if crate::config::VK_VALIDATION.is_enabled {
-                enabled_validation_layers_raw.len() as u32
-            } else {
-                0 as u32
-            },
-            pp_enabled_layer_names: if crate::config::VK_VALIDATION.is_enabled {
-                enabled_validation_layers.as_ptr()
-            } else {
-                ptr::null()
-            },
-            ..Default::default()
-        };
+        let mut create_info = vk::DeviceCreateInfo::builder()
+            .queue_create_infos(&queue_create_infos)
+            .enabled_features(&feature_chain.core)
+            .enabled_extension_names(&enabled_extensions)
+            .enabled_layer_names(&enabled_validation_layers);
+        if enabled_features.has_vulkan12_features() {
+            create_info = create_info
+                .push_next(&mut feature_chain.vulkan11)
+                .push_next(&mut feature_chain.vulkan12);
+        }
 
         unsafe {
             instance
@@ -0,0 +1,374 @@
+use ash::vk;
+use std::collections::BTreeSet;
+
+/// The Vulkan 1.0, 1.1 and 1.2 feature structs, queried and enabled together.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct FeatureChain {
+    pub core: vk::PhysicalDeviceFeatures,
+    pub vulkan11: vk::PhysicalDeviceVulkan11Features,
+    pub vulkan12: vk::PhysicalDeviceVulkan12Features,
+}
+
+macro_rules! device_features {
+    ($($(#[$attribute:meta])* $variant:ident => $chain_member:ident.$field:ident,)*) => {
+        /// A device feature the renderer knows how to enable, named after its member in the
+        /// Vulkan feature structs.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub enum DeviceFeature {
+            $($(#[$attribute])* $variant,)*
+        }
+        impl DeviceFeature {
+            pub const ALL: &'static [DeviceFeature] = &[$(DeviceFeature::$variant,)*];
+
+            pub fn name(self) -> &'static str {
+                match self {
+                    $(DeviceFeature::$variant => stringify!($field),)*
+                }
+            }
+
+            fn flag(self, chain: &mut FeatureChain) -> &mut vk::Bool32 {
+                match self {
+                    $(DeviceFeature::$variant => &mut chain.$chain_member.$field,)*
+                }
+            }
+        }
+    };
+}
+
+device_features! {
+    GeometryShader => core.geometry_shader,
+    TessellationShader => core.tessellation_shader,
+    ShaderFloat64 => core.shader_float64,
+    ShaderInt64 => core.shader_int64,
+    /// wireframe rendering
+    FillModeNonSolid => core.fill_mode_non_solid,
+    SamplerAnisotropy => core.sampler_anisotropy,
+    DepthClamp => core.depth_clamp,
+    TextureCompressionBc => core.texture_compression_bc,
+    MultiDrawIndirect => core.multi_draw_indirect,
+    DrawIndirectFirstInstance => core.draw_indirect_first_instance,
+    PipelineStatisticsQuery => core.pipeline_statistics_query,
+    // Vulkan 1.1
+    ShaderDrawParameters => vulkan11.shader_draw_parameters,
+    Multiview => vulkan11.multiview,
+    // Vulkan 1.2
+    ShaderSampledImageArrayNonUniformIndexing => vulkan12.shader_sampled_image_array_non_uniform_indexing,
+    DescriptorBindingSampledImageUpdateAfterBind => vulkan12.descriptor_binding_sampled_image_update_after_bind,
+    DescriptorBindingPartiallyBound => vulkan12.descriptor_binding_partially_bound,
+    DescriptorBindingVariableDescriptorCount => vulkan12.descriptor_binding_variable_descriptor_count,
+    RuntimeDescriptorArray => vulkan12.runtime_descriptor_array,
+    TimelineSemaphore => vulkan12.timeline_semaphore,
+    HostQueryReset => vulkan12.host_query_reset,
+    BufferDeviceAddress => vulkan12.buffer_device_address,
+    ScalarBlockLayout => vulkan12.scalar_block_layout,
+    SeparateDepthStencilLayouts => vulkan12.separate_depth_stencil_layouts,
+    ImagelessFramebuffer => vulkan12.imageless_framebuffer,
+}
+
+/// Extensions and features one subsystem of the renderer needs. Optional requests are enabled
+/// as a whole or not at all.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeatureRequest {
+    /// what code paths check with `EnabledFeatures::is_enabled`
+    pub subsystem: &'static str,
+    /// devices that can't fulfill required requests are rejected
+    pub required: bool,
+    pub extensions: &'static [&'static str],
+    pub features: &'static [DeviceFeature],
+}
+
+impl FeatureRequest {
+    /// The extensions and features of the request the device doesn't support.
+    pub fn missing(&self, supported: &SupportedFeatures) -> Vec<&'static str> {
+        self.extensions
+            .iter()
+            .filter(|&&extension| !supported.extensions.contains(extension))
+            .copied()
+            .chain(
+                self.features
+                    .iter()
+                    .filter(|feature| !supported.features.contains(feature))
+                    .map(|feature| feature.name()),
+            )
+            .collect()
+    }
+}
+
+/// What the renderer as a whole asks of the device, gathered from its subsystems.
+pub fn renderer_feature_requests() -> Vec<FeatureRequest> {
+    let mut requests = vec![FeatureRequest {
+        subsystem: "renderer",
+        required: true,
+        extensions: &crate::config::REQUIRED_DEVICE_EXTENSIONS,
+        features: &crate::config::REQUIRED_DEVICE_FEATURES,
+    }];
+    requests.push(crate::renderer::sync::GpuTimeline::FEATURE_REQUEST);
+    requests.push(crate::renderer::profiling::GpuProfilerResource::FEATURE_REQUEST);
+    if crate::config::BINDLESS_TEXTURES_ENABLE {
+        requests.push(crate::renderer::vk_types::BindlessTexturesResource::FEATURE_REQUEST);
+    }
+    requests
+}
+
+/// What a physical device supports, as far as the renderer can enable it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SupportedFeatures {
+    /// the 1.1 and 1.2 features can only be queried and enabled on Vulkan 1.2 devices
+    pub api_version: u32,
+    pub extensions: BTreeSet<String>,
+    pub features: BTreeSet<DeviceFeature>,
+}
+
+impl SupportedFeatures {
+    pub fn query(instance: &ash::Instance, physical_device: vk::PhysicalDevice) -> Self {
+        let extensions = unsafe { instance.enumerate_device_extension_properties(physical_device) }
+            .expect("Couldn't enumerate device extension properties")
+            .iter()
+            .map(|extension| crate::util::raw_c_string_to_string(&extension.extension_name))
+            .collect();
+
+        let api_version =
+            unsafe { instance.get_physical_device_properties(physical_device) }.api_version;
+
+        let mut chain = query_feature_chain(instance, physical_device, api_version);
+        let features = DeviceFeature::ALL
+            .iter()
+            .copied()
+            .filter(|feature| *feature.flag(&mut chain) == vk::TRUE)
+            .collect();
+
+        Self {
+            api_version,
+            extensions,
+            features,
+        }
+    }
+}
+
+/// Devices older than Vulkan 1.2 only report the 1.0 features, the others stay unsupported.
+fn query_feature_chain(
+    instance: &ash::Instance,
+    physical_device: vk::PhysicalDevice,
+    api_version: u32,
+) -> FeatureChain {
+    let mut chain = FeatureChain::default();
+
+    if api_version < vk::API_VERSION_1_2 {
+        chain.core = unsafe { instance.get_physical_device_features(physical_device) };
+        return chain;
+    }
+
+    chain.core = {
+        let mut features2 = vk::PhysicalDeviceFeatures2::builder()
+            .push_next(&mut chain.vulkan11)
+            .push_next(&mut chain.vulkan12);
+        unsafe { instance.get_physical_device_features2(physical_device, &mut features2) };
+        features2.features
+    };
+    chain
+}
+
+/// What the device was created with.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EnabledFeatures {
+    pub api_version: u32,
+    pub extensions: BTreeSet<String>,
+    pub features: BTreeSet<DeviceFeature>,
+    /// subsystems whose requests were all fulfilled
+    pub subsystems: BTreeSet<&'static str>,
+}
+
+impl EnabledFeatures {
+    /// Enables every request the device supports. Fails with what's missing if a required
+    /// request can't be fulfilled.
+    ///
+    ///     Optional requests that can't be fulfilled are left out entirely, so a subsystem never
+    ///     ends up with half of what it asked for.
+    pub fn negotiate(
+        requests: &[FeatureRequest],
+        supported: &SupportedFeatures,
+    ) -> Result<Self, Vec<String>> {
+        let mut enabled = Self {
+            api_version: supported.api_version,
+            ..Default::default()
+        };
+        let mut missing_required = Vec::new();
+
+        for request in requests {
+            let missing = request
+                .missing(supported)
+                .into_iter()
+                .map(|name| format!("{}: {}", request.subsystem, name))
+                .collect::<Vec<_>>();
+
+            if missing.is_empty() {
+                enabled.extensions.extend(
+                    request
+                        .extensions
+                        .iter()
+                        .map(|&extension| extension.to_owned()),
+                );
+                enabled.features.extend(request.features.iter().copied());
+                enabled.subsystems.insert(request.subsystem);
+            } else if request.required {
+                missing_required.extend(missing);
+            } else {
+                log::info!(
+                    "{} disabled, the device doesn't support {}",
+                    request.subsystem,
+                    missing.join(", ")
+                );
+            }
+        }
+
+        if missing_required.is_empty() {
+            Ok(enabled)
+        } else {
+            Err(missing_required)
+        }
+    }
+
+    /// Whether all of the subsystem's request was enabled.
+    pub fn is_enabled(&self, subsystem: &str) -> bool {
+        self.subsystems.contains(subsystem)
+    }
+
+    pub fn has_feature(&self, feature: DeviceFeature) -> bool {
+        self.features.contains(&feature)
+    }
+
+    pub fn has_extension(&self, extension: &str) -> bool {
+        self.extensions.contains(extension)
+    }
+
+    pub(crate) fn feature_chain(&self) -> FeatureChain {
+        let mut chain = FeatureChain::default();
+        for feature in self.features.iter() {
+            *feature.flag(&mut chain) = vk::TRUE;
+        }
+        chain
+    }
+
+    /// Whether the 1.1 and 1.2 feature structs can be chained when creating the device.
+    pub(crate) fn has_vulkan12_features(&self) -> bool {
+        self.api_version >= vk::API_VERSION_1_2
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CORE: FeatureRequest = FeatureRequest {
+        subsystem: "core",
+        required: true,
+        extensions: &["VK_KHR_swapchain"],
+        features: &[DeviceFeature::GeometryShader],
+    };
+    const BINDLESS: FeatureRequest = FeatureRequest {
+        subsystem: "bindless",
+        required: false,
+        extensions: &[],
+        features: &[
+            DeviceFeature::RuntimeDescriptorArray,
+            DeviceFeature::DescriptorBindingPartiallyBound,
+        ],
+    };
+
+    fn supported(extensions: &[&str], features: &[DeviceFeature]) -> SupportedFeatures {
+        SupportedFeatures {
+            api_version: vk::API_VERSION_1_2,
+            extensions: extensions.iter().map(|&name| name.to_owned()).collect(),
+            features: features.iter().copied().collect(),
+        }
+    }
+
+    #[test]
+    fn supported_requests_are_enabled() {
+        let device = supported(
+            &["VK_KHR_swapchain"],
+            &[
+                DeviceFeature::GeometryShader,
+                DeviceFeature::RuntimeDescriptorArray,
+                DeviceFeature::DescriptorBindingPartiallyBound,
+                DeviceFeature::TimelineSemaphore,
+            ],
+        );
+
+        let enabled = EnabledFeatures::negotiate(&[CORE, BINDLESS], &device).unwrap();
+
+        assert!(enabled.is_enabled("core") && enabled.is_enabled("bindless"));
+        assert!(enabled.has_extension("VK_KHR_swapchain"));
+        assert!(enabled.has_feature(DeviceFeature::RuntimeDescriptorArray));
+        // only requested features are enabled
+        assert!(!enabled.has_feature(DeviceFeature::TimelineSemaphore));
+    }
+
+    #[test]
+    fn partially_supported_optional_requests_are_left_out() {
+        let device = supported(
+            &["VK_KHR_swapchain"],
+            &[
+                DeviceFeature::GeometryShader,
+                DeviceFeature::RuntimeDescriptorArray,
+            ],
+        );
+
+        let enabled = EnabledFeatures::negotiate(&[CORE, BINDLESS], &device).unwrap();
+
+        assert!(enabled.is_enabled("core"));
+        assert!(!enabled.is_enabled("bindless"));
+        assert!(!enabled.has_feature(DeviceFeature::RuntimeDescriptorArray));
+    }
+
+    #[test]
+    fn missing_required_features_are_reported() {
+        let device = supported(&[], &[]);
+
+        let missing = EnabledFeatures::negotiate(&[CORE, BINDLESS], &device).unwrap_err();
+
+        assert_eq!(missing, ["core: VK_KHR_swapchain", "core: geometry_shader"]);
+    }
+
+    #[test]
+    fn missing_lists_extensions_before_features() {
+        let device = supported(&[], &[DeviceFeature::RuntimeDescriptorArray]);
+
+        assert_eq!(
+            CORE.missing(&device),
+            ["VK_KHR_swapchain", "geometry_shader"]
+        );
+        assert_eq!(
+            BINDLESS.missing(&device),
+            ["descriptor_binding_partially_bound"]
+        );
+    }
+
+    #[test]
+    fn feature_chain_sets_the_enabled_flags() {
+        let enabled = EnabledFeatures {
+            features: [
+                DeviceFeature::FillModeNonSolid,
+                DeviceFeature::TimelineSemaphore,
+            ]
+            .into_iter()
+            .collect(),
+            ..Default::default()
+        };
+
+        let chain = enabled.feature_chain();
+
+        assert_eq!(chain.core.fill_mode_non_solid, vk::TRUE);
+        assert_eq!(chain.core.geometry_shader, vk::FALSE);
+        assert_eq!(chain.vulkan12.timeline_semaphore, vk::TRUE);
+    }
+
+    #[test]
+    fn feature_names_match_the_vulkan_members() {
+        assert_eq!(DeviceFeature::ShaderFloat64.name(), "shader_float64");
+        assert_eq!(
+            DeviceFeature::TimelineSemaphore.name(),
+            "timeline_semaphore"
+        );
+    }
+}
@@ -24,6 +24,28 @@ impl Instance {
             handle: instance,
         })
     }
+
+    /// Instance without surface extensions, for querying devices without a window.
+    pub fn init_headless() -> Result<Self> {
+        let entry = unsafe { ash::Entry::new() }?;
+
+        log::trace!("Creating headless Vulkan instance.");
+        let instance: ash::Instance = create_ash_instance(&entry, &Vec::new())?;
+
+        Ok(Self {
+            entry,
+            handle: instance,
+        })
+    }
+
+    /// Highest Vulkan version the loader supports.
+    pub fn loader_version(&self) -> Result<u32> {
+        // 1.0 loaders can't report their version
+        Ok(self
+            .entry
+            .try_enumerate_instance_version()?
+            .unwrap_or(vk::API_VERSION_1_0))
+    }
 }
 
 fn create_ash_instance(
@@ -43,7 +65,7 @@ fn create_ash_instance(
         .map(|extension| extension.as_ptr())
         .collect::<Vec<_>>();
 
-    if crate::config::VK_VALIDATION.is_enabled {
+    if crate::renderer::debug::debug_utils_enabled() {
         extension_names.push(ash::extensions::ext::DebugUtils::name().as_ptr());
     }
 
@@ -4,8 +4,8 @@ pub use debug_messenger::*;
 mod device;
 pub use device::*;
 
-mod frame_buffers;
-pub use frame_buffers::*;
+mod features;
+pub use features::*;
 
 mod instance;
 pub use instance::*;
@@ -1,8 +1,8 @@
+use crate::impl_deref;
 use crate::renderer::vk_types::vk_context::instance::Instance;
 use crate::renderer::vk_types::Surface;
 use anyhow::*;
 use ash::vk;
-use crate::impl_deref;
 
 pub struct PhysicalDevice {
     pub handle: vk::PhysicalDevice,
@@ -11,8 +11,13 @@ pub struct PhysicalDevice {
 impl_deref!(PhysicalDevice, handle, vk::PhysicalDevice);
 
 impl PhysicalDevice {
-    pub(crate) fn init(instance: &Instance, surface: &Surface) -> Result<Self> {
-        let (handle, queue_index) = init::select_physical_device(instance, surface)?;
+    /// Picks the best scoring suitable device, or the one the selector names if it's suitable.
+    pub(crate) fn init(
+        instance: &Instance,
+        surface: &Surface,
+        selector: Option<&DeviceSelector>,
+    ) -> Result<Self> {
+        let (handle, queue_index) = init::select_physical_device(instance, surface, selector)?;
 
         Ok(Self {
             handle,
@@ -21,6 +26,119 @@ impl PhysicalDevice {
     }
 }
 
+/// A user's choice of physical device, overriding the scoring.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceSelector {
+    /// position in the order the instance enumerates devices
+    Index(usize),
+    /// case insensitive part of the device name
+    Name(String),
+}
+impl DeviceSelector {
+    /// A number selects by index, anything else by name. Blank values select nothing.
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        if value.is_empty() {
+            return None;
+        }
+        Some(match value.parse() {
+            Ok(index) => Self::Index(index),
+            Err(_) => Self::Name(value.to_owned()),
+        })
+    }
+
+    /// The `PENGUIN_PHYSICAL_DEVICE` environment variable takes precedence over the config.
+    pub fn from_env_or(config_value: Option<&str>) -> Option<Self> {
+        std::env::var(crate::config::PHYSICAL_DEVICE_ENV_VAR)
+            .ok()
+            .and_then(|value| Self::parse(&value))
+            .or_else(|| config_value.and_then(Self::parse))
+    }
+
+    pub fn matches(&self, candidate: &DeviceCandidate) -> bool {
+        match self {
+            Self::Index(index) => candidate.index == *index,
+            Self::Name(name) => candidate.name.to_lowercase().contains(&name.to_lowercase()),
+        }
+    }
+}
+
+/// What device selection knows about a physical device.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceCandidate {
+    pub index: usize,
+    pub name: String,
+    pub device_type: vk::PhysicalDeviceType,
+    /// size of the largest device local memory heap
+    pub device_local_memory: u64,
+    /// how many of the optional feature requests the device can fulfill
+    pub optional_feature_count: u32,
+    pub graphics_queue_index: Option<u32>,
+    /// why the device can't be used, empty if it can
+    pub rejections: Vec<String>,
+}
+
+/// Devices with a higher score are preferred. The type outweighs the memory, which outweighs the
+/// optional features.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DeviceScore {
+    type_rank: u32,
+    device_local_memory: u64,
+    optional_feature_count: u32,
+}
+
+impl DeviceCandidate {
+    pub fn is_suitable(&self) -> bool {
+        self.rejections.is_empty()
+    }
+
+    pub fn score(&self) -> DeviceScore {
+        let type_rank = match self.device_type {
+            vk::PhysicalDeviceType::DISCRETE_GPU => 4,
+            vk::PhysicalDeviceType::INTEGRATED_GPU => 3,
+            vk::PhysicalDeviceType::VIRTUAL_GPU => 2,
+            vk::PhysicalDeviceType::CPU => 1,
+            _ => 0,
+        };
+        DeviceScore {
+            type_rank,
+            device_local_memory: self.device_local_memory,
+            optional_feature_count: self.optional_feature_count,
+        }
+    }
+}
+
+/// Index into `candidates` of the device to use. A selected device is only used if it's
+/// suitable, otherwise the best scoring suitable one is. Ties go to the first enumerated device.
+pub fn pick_physical_device(
+    candidates: &[DeviceCandidate],
+    selector: Option<&DeviceSelector>,
+) -> Option<usize> {
+    if let Some(selector) = selector {
+        match candidates
+            .iter()
+            .position(|candidate| selector.matches(candidate))
+        {
+            Some(selected) if candidates[selected].is_suitable() => return Some(selected),
+            Some(selected) => log::warn!(
+                "Selected physical device {} isn't suitable: {}",
+                candidates[selected].name,
+                candidates[selected].rejections.join(", ")
+            ),
+            None => log::warn!("No physical device matches {:?}", selector),
+        }
+    }
+
+    candidates
+        .iter()
+        .enumerate()
+        .filter(|(_, candidate)| candidate.is_suitable())
+        // max_by_key returns the last maximum, reversing keeps the first
+        .rev()
+        .max_by_key(|(_, candidate)| candidate.score())
+        .map(|(position, _)| position)
+}
+
 pub struct SwapchainSupportDetails {
     pub surface_capabilities: vk::SurfaceCapabilitiesKHR,
     pub surface_color_formats: Vec<vk::SurfaceFormatKHR>,
@@ -71,6 +189,7 @@ mod init {
     pub fn select_physical_device(
         instance: &Instance,
         surface: &Surface,
+        selector: Option<&DeviceSelector>,
     ) -> Result<(vk::PhysicalDevice, PhysicalDeviceQueueIndex)> {
         // Find devices with vulkan support
 
@@ -86,124 +205,121 @@ mod init {
             physical_devices.len()
         );
 
-        // Select suitable physical device
-        let mut suitable_device = None;
-
-        for &physical_device in physical_devices.iter() {
-            let device_info = check_device_suitablity_info(&instance, physical_device, &surface);
-
-            if device_info.is_suitable() {
-                if suitable_device.is_none() {
-                    suitable_device = Some((physical_device, device_info));
-                    break;
-                }
+        let candidates = physical_devices
+            .iter()
+            .enumerate()
+            .map(|(index, &physical_device)| {
+                device_candidate(&instance, index, physical_device, &surface)
+            })
+            .collect::<Vec<_>>();
+
+        for candidate in candidates.iter() {
+            if candidate.is_suitable() {
+                log::info!(
+                    "Physical device {}: {} ({:?}, {} MiB, {} optional features)",
+                    candidate.index,
+                    candidate.name,
+                    candidate.device_type,
+                    candidate.device_local_memory / (1024 * 1024),
+                    candidate.optional_feature_count
+                );
+            } else {
+                log::info!(
+                    "Physical device {}: {} ({:?}) rejected: {}",
+                    candidate.index,
+                    candidate.name,
+                    candidate.device_type,
+                    candidate.rejections.join(", ")
+                );
             }
         }
 
-        match suitable_device {
+        let selected = match pick_physical_device(&candidates, selector) {
             None => {
                 log::error!("Couldn't find a suitable physical device.");
                 panic!();
             }
-            Some((physical_device, info)) => {
-                let queue_index = info.graphics_queue_index.expect("No graphics queue index");
-
-                Ok((physical_device, queue_index))
-            }
-        }
-    }
+            Some(selected) => &candidates[selected],
+        };
+        log::info!(
+            "Using physical device {}: {}",
+            selected.index,
+            selected.name
+        );
 
-    struct PhysicalDeviceInfo {
-        graphics_queue_index: Option<PhysicalDeviceQueueIndex>,
-        required_extensions_supported: bool,
-        swapchain_supported: bool,
-        geometry_shader_support: bool,
-    }
-    impl PhysicalDeviceInfo {
-        fn is_suitable(&self) -> bool {
-            self.graphics_queue_index.is_some()
-                && self.required_extensions_supported
-                && self.swapchain_supported
-                && self.geometry_shader_support
-        }
+        let queue_index = selected
+            .graphics_queue_index
+            .expect("No graphics queue index");
+        Ok((physical_devices[selected.index], queue_index))
     }
 
-    /// Checks if the given physical device supports the required device extensions.
-    fn check_device_suitablity_info(
+    /// Checks if the given physical device supports what the renderer needs and what it would
+    /// like to have.
+    fn device_candidate(
         instance: &Instance,
+        index: usize,
         physical_device: vk::PhysicalDevice,
         surface: &Surface,
-    ) -> PhysicalDeviceInfo {
+    ) -> DeviceCandidate {
         let properties = unsafe { instance.get_physical_device_properties(physical_device) };
-
-        let features = unsafe { instance.get_physical_device_features(physical_device) };
-
-        let geometry_shader_support = if features.geometry_shader == vk::FALSE {
-            false
-        } else {
-            true
-        };
-
-        let device_type = match properties.device_type {
-            vk::PhysicalDeviceType::CPU => "Cpu",
-            vk::PhysicalDeviceType::INTEGRATED_GPU => "Integrated GPU",
-            vk::PhysicalDeviceType::DISCRETE_GPU => "Discrete GPU",
-            vk::PhysicalDeviceType::VIRTUAL_GPU => "Virtual GPU",
-            vk::PhysicalDeviceType::OTHER => "Unknown",
-            _ => panic!("Couldn't find physical device type"),
+        let memory_properties =
+            unsafe { instance.get_physical_device_memory_properties(physical_device) };
+
+        let mut rejections = Vec::new();
+
+        let requests = renderer_feature_requests();
+        let supported = SupportedFeatures::query(&instance.handle, physical_device);
+        let optional_feature_count = match EnabledFeatures::negotiate(&requests, &supported) {
+            Ok(enabled) => requests
+                .iter()
+                .filter(|request| !request.required && enabled.is_enabled(request.subsystem))
+                .count() as _,
+            Err(missing) => {
+                rejections.extend(missing.into_iter().map(|name| format!("missing {}", name)));
+                0
+            }
         };
-        log::info!("Physical device: {}", device_type);
 
         let graphics_queue_index = find_graphics_queue_family(&instance, physical_device, surface);
+        if graphics_queue_index.is_none() {
+            rejections.push("no queue family with graphics and present support".to_owned());
+        }
 
-        let are_required_extensions_supported =
-            check_required_extensions_supported(&instance, physical_device);
-
-        let is_swapchain_supported = if are_required_extensions_supported {
+        if supported.extensions.contains("VK_KHR_swapchain") {
             let swapchain_support = super::query_swapchain_support(physical_device, surface);
 
-            !swapchain_support.surface_color_formats.is_empty()
-                && !swapchain_support.surface_present_modes.is_empty()
-        } else {
-            false
-        };
+            if swapchain_support.surface_color_formats.is_empty()
+                || swapchain_support.surface_present_modes.is_empty()
+            {
+                rejections.push("no surface formats or present modes".to_owned());
+            }
+        }
 
-        PhysicalDeviceInfo {
+        let device_local_memory = memory_properties.memory_heaps
+            [..memory_properties.memory_heap_count as usize]
+            .iter()
+            .filter(|heap| heap.flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL))
+            .map(|heap| heap.size)
+            .max()
+            .unwrap_or(0);
+
+        DeviceCandidate {
+            index,
+            name: crate::util::raw_c_string_to_string(&properties.device_name),
+            device_type: properties.device_type,
+            device_local_memory,
+            optional_feature_count,
             graphics_queue_index,
-            required_extensions_supported: are_required_extensions_supported,
-            swapchain_supported: is_swapchain_supported,
-            geometry_shader_support,
+            rejections,
         }
     }
 
-    use crate::renderer::vk_types::{Instance, Surface};
+    use super::{pick_physical_device, DeviceCandidate, DeviceSelector};
+    use crate::renderer::vk_types::{
+        renderer_feature_requests, EnabledFeatures, Instance, SupportedFeatures, Surface,
+    };
     use ash::vk;
 
-    /// Checks if the listed device extensions are supported on the given physical device.
-    fn check_required_extensions_supported(
-        instance: &Instance,
-        physical_device: vk::PhysicalDevice,
-    ) -> bool {
-        log::trace!("Checking extensions supported:");
-        let supported_extensions =
-            unsafe { instance.enumerate_device_extension_properties(physical_device) }
-                .expect("Physical device: Couldn't get device extensions");
-
-        let supported_extensions_found: Vec<String> = supported_extensions
-            .into_iter()
-            .map(|extension| crate::util::raw_c_string_to_string(&extension.extension_name)) // converts each raw string to strings
-            .filter(|extension_name| {
-                crate::config::REQUIRED_DEVICE_EXTENSIONS.contains(&extension_name.as_str())
-            }) // filters out any extensions that aren't also in the DEVICE_EXTENSIONS array
-            .collect();
-
-        supported_extensions_found
-            .iter()
-            .for_each(|name| log::debug!("Required extension: {} is supported.", name));
-
-        supported_extensions_found.len() == crate::config::REQUIRED_DEVICE_EXTENSIONS.len()
-    }
-
     pub(crate) fn find_graphics_queue_family(
         instance: &Instance,
         physical_device: vk::PhysicalDevice,
@@ -271,3 +387,115 @@ mod init {
         queue_family_index
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(index: usize, name: &str, device_type: vk::PhysicalDeviceType) -> DeviceCandidate {
+        DeviceCandidate {
+            index,
+            name: name.to_owned(),
+            device_type,
+            device_local_memory: 4 << 30,
+            optional_feature_count: 3,
+            graphics_queue_index: Some(0),
+            rejections: Vec::new(),
+        }
+    }
+
+    fn laptop() -> Vec<DeviceCandidate> {
+        vec![
+            candidate(
+                0,
+                "Intel(R) UHD Graphics 620",
+                vk::PhysicalDeviceType::INTEGRATED_GPU,
+            ),
+            candidate(
+                1,
+                "NVIDIA GeForce GTX 1050",
+                vk::PhysicalDeviceType::DISCRETE_GPU,
+            ),
+            candidate(
+                2,
+                "llvmpipe (LLVM 12.0.0, 256 bits)",
+                vk::PhysicalDeviceType::CPU,
+            ),
+        ]
+    }
+
+    #[test]
+    fn discrete_gpus_are_preferred() {
+        assert_eq!(pick_physical_device(&laptop(), None), Some(1));
+    }
+
+    #[test]
+    fn memory_breaks_ties_between_types() {
+        let mut devices = laptop();
+        devices[0].device_type = vk::PhysicalDeviceType::DISCRETE_GPU;
+        devices[0].device_local_memory = 8 << 30;
+        assert_eq!(pick_physical_device(&devices, None), Some(0));
+    }
+
+    #[test]
+    fn type_outweighs_features_and_memory() {
+        let mut devices = laptop();
+        devices[1].device_local_memory = 2 << 30;
+        devices[1].optional_feature_count = 0;
+        assert_eq!(pick_physical_device(&devices, None), Some(1));
+    }
+
+    #[test]
+    fn equal_devices_keep_the_enumeration_order() {
+        let devices = vec![
+            candidate(0, "first", vk::PhysicalDeviceType::DISCRETE_GPU),
+            candidate(1, "second", vk::PhysicalDeviceType::DISCRETE_GPU),
+        ];
+        assert_eq!(pick_physical_device(&devices, None), Some(0));
+    }
+
+    #[test]
+    fn rejected_devices_are_never_picked() {
+        let mut devices = laptop();
+        devices[1]
+            .rejections
+            .push("missing renderer: geometry_shader".to_owned());
+        assert_eq!(pick_physical_device(&devices, None), Some(0));
+
+        for device in devices.iter_mut() {
+            device
+                .rejections
+                .push("missing renderer: VK_KHR_swapchain".to_owned());
+        }
+        assert_eq!(pick_physical_device(&devices, None), None);
+    }
+
+    #[test]
+    fn selector_overrides_the_score() {
+        let devices = laptop();
+        let by_name = DeviceSelector::parse("intel").unwrap();
+        assert_eq!(pick_physical_device(&devices, Some(&by_name)), Some(0));
+
+        let by_index = DeviceSelector::parse(" 2 ").unwrap();
+        assert_eq!(by_index, DeviceSelector::Index(2));
+        assert_eq!(pick_physical_device(&devices, Some(&by_index)), Some(2));
+    }
+
+    #[test]
+    fn unusable_selections_fall_back_to_the_score() {
+        let mut devices = laptop();
+        devices[0]
+            .rejections
+            .push("missing renderer: geometry_shader".to_owned());
+        let intel = DeviceSelector::parse("UHD").unwrap();
+        assert_eq!(pick_physical_device(&devices, Some(&intel)), Some(1));
+
+        let missing = DeviceSelector::parse("radeon").unwrap();
+        assert_eq!(pick_physical_device(&devices, Some(&missing)), Some(1));
+    }
+
+    #[test]
+    fn blank_selectors_select_nothing() {
+        assert_eq!(DeviceSelector::parse("  "), None);
+    }
+}
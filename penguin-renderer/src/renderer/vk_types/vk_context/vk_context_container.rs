@@ -1,4 +1,7 @@
-use crate::renderer::vk_types::{DebugMessenger, Device, Instance, PhysicalDevice, Surface};
+use crate::renderer::sync::GpuTimeline;
+use crate::renderer::vk_types::{
+    DebugMessenger, Device, DeviceSelector, EnabledFeatures, Instance, PhysicalDevice, Surface,
+};
 /// ------------------------- VK CONTEXT ----------------------------------
 use ash::vk;
 
@@ -8,6 +11,8 @@ pub struct VkContext {
     pub surface: Surface,
     pub physical_device: PhysicalDevice,
     pub device: Device,
+    /// orders the submissions to the graphics queue
+    pub timeline: GpuTimeline,
 }
 
 impl VkContext {
@@ -45,13 +50,34 @@ impl VkContext {
         });
         supported_format
     }
+
+    /// The highest sample count up to `requested` that both color and depth framebuffer
+    /// attachments support.
+    pub fn supported_sample_count(&self, requested: u32) -> vk::SampleCountFlags {
+        let limits = self.pd_device_properties().limits;
+        let supported = limits.framebuffer_color_sample_counts
+            & limits.framebuffer_depth_sample_counts;
+
+        [64, 32, 16, 8, 4, 2]
+            .into_iter()
+            .filter(|&count| count <= requested)
+            .map(vk::SampleCountFlags::from_raw)
+            .find(|&samples| supported.contains(samples))
+            .unwrap_or(vk::SampleCountFlags::TYPE_1)
+    }
 }
 
 impl VkContext {
+    /// What the device was created with, for code paths that depend on optional features.
+    pub fn enabled_features(&self) -> &EnabledFeatures {
+        &self.device.enabled_features
+    }
+
     pub fn destroy(&mut self) {
         unsafe {
             log::trace!("Dropping vk context!");
 
+            self.timeline.destroy(&self.device);
             self.device.destroy_device(None);
 
             log::trace!("Destroying surface..");
@@ -74,7 +100,11 @@ impl VkContext {
         }
     }
 
-    pub fn init(window: &penguin_app::window::Window, log_level_filter: log::LevelFilter) -> Self {
+    pub fn init(
+        window: &penguin_app::window::Window,
+        log_level_filter: log::LevelFilter,
+        device_selector: Option<&DeviceSelector>,
+    ) -> Self {
         log::trace!("Constructing VkContext...");
 
         log::trace!("Creating instance.");
@@ -86,10 +116,13 @@ impl VkContext {
         let surface = Surface::init(&instance, window).expect("couldn't init vk surface");
         log::trace!("Selecting physical device and caching it's properties.");
         let physical_device =
-            PhysicalDevice::init(&instance, &surface).expect("couldn't init vk physical device");
+            PhysicalDevice::init(&instance, &surface, device_selector)
+                .expect("couldn't init vk physical device");
 
         log::trace!("Creating logical device.");
         let device = Device::init(&instance, &physical_device);
+        log::trace!("Creating GPU timeline.");
+        let timeline = GpuTimeline::init(&device, &device.enabled_features);
 
         Self {
             instance,
@@ -97,6 +130,7 @@ impl VkContext {
             surface,
             physical_device,
             device,
+            timeline,
         }
     }
 }
@@ -0,0 +1,17 @@
+#version 460
+
+layout (location = 0) in vec2 uv;
+
+layout (location = 0) out vec4 out_color;
+
+layout (set = 0, binding = 0) uniform sampler2D scene_color;
+layout (set = 0, binding = 1) uniform sampler2D bloom;
+
+layout (push_constant) uniform CompositeParams {
+    float intensity;
+} params;
+
+void main() {
+    vec3 color = texture(scene_color, uv).rgb + texture(bloom, uv).rgb * params.intensity;
+    out_color = vec4(color, 1.0);
+}
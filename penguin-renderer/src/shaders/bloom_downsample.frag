@@ -0,0 +1,54 @@
+#version 460
+
+layout (location = 0) in vec2 uv;
+
+layout (location = 0) out vec4 out_color;
+
+layout (set = 0, binding = 0) uniform sampler2D source;
+
+layout (push_constant) uniform DownsampleParams {
+    float threshold;
+    float knee;
+    uint apply_threshold;
+} params;
+
+// keeps the part of the color above the threshold, with a quadratic curve around it
+vec3 bright_part(vec3 color) {
+    float brightness = max(color.r, max(color.g, color.b));
+    float soft_knee = params.threshold * params.knee + 0.00001;
+    float soft = clamp(brightness - params.threshold + soft_knee, 0.0, 2.0 * soft_knee);
+    soft = soft * soft / (4.0 * soft_knee);
+    float contribution = max(soft, brightness - params.threshold) / max(brightness, 0.00001);
+    return color * contribution;
+}
+
+// 13 taps in overlapping 2x2 boxes, as in Jimenez' "Next Generation Post Processing in Call of
+// Duty: Advanced Warfare"
+void main() {
+    vec2 texel = 1.0 / vec2(textureSize(source, 0));
+
+    vec3 a = texture(source, uv + texel * vec2(-2.0, -2.0)).rgb;
+    vec3 b = texture(source, uv + texel * vec2( 0.0, -2.0)).rgb;
+    vec3 c = texture(source, uv + texel * vec2( 2.0, -2.0)).rgb;
+    vec3 d = texture(source, uv + texel * vec2(-2.0,  0.0)).rgb;
+    vec3 e = texture(source, uv).rgb;
+    vec3 f = texture(source, uv + texel * vec2( 2.0,  0.0)).rgb;
+    vec3 g = texture(source, uv + texel * vec2(-2.0,  2.0)).rgb;
+    vec3 h = texture(source, uv + texel * vec2( 0.0,  2.0)).rgb;
+    vec3 i = texture(source, uv + texel * vec2( 2.0,  2.0)).rgb;
+    vec3 j = texture(source, uv + texel * vec2(-1.0, -1.0)).rgb;
+    vec3 k = texture(source, uv + texel * vec2( 1.0, -1.0)).rgb;
+    vec3 l = texture(source, uv + texel * vec2(-1.0,  1.0)).rgb;
+    vec3 m = texture(source, uv + texel * vec2( 1.0,  1.0)).rgb;
+
+    vec3 color = e * 0.125
+        + (a + c + g + i) * 0.03125
+        + (b + d + f + h) * 0.0625
+        + (j + k + l + m) * 0.125;
+
+    if (params.apply_threshold != 0u) {
+        color = bright_part(color);
+    }
+
+    out_color = vec4(color, 1.0);
+}
@@ -0,0 +1,28 @@
+#version 460
+
+layout (location = 0) in vec2 uv;
+
+layout (location = 0) out vec4 out_color;
+
+// downsampled image of this level
+layout (set = 0, binding = 0) uniform sampler2D current;
+// blurred image of the level below, half the size
+layout (set = 0, binding = 1) uniform sampler2D blurred;
+
+// 3x3 tent filter
+void main() {
+    vec2 texel = 1.0 / vec2(textureSize(blurred, 0));
+
+    vec3 color = texture(blurred, uv).rgb * 4.0;
+    color += (texture(blurred, uv + texel * vec2(-1.0,  0.0)).rgb
+        + texture(blurred, uv + texel * vec2( 1.0,  0.0)).rgb
+        + texture(blurred, uv + texel * vec2( 0.0, -1.0)).rgb
+        + texture(blurred, uv + texel * vec2( 0.0,  1.0)).rgb) * 2.0;
+    color += texture(blurred, uv + texel * vec2(-1.0, -1.0)).rgb
+        + texture(blurred, uv + texel * vec2( 1.0, -1.0)).rgb
+        + texture(blurred, uv + texel * vec2(-1.0,  1.0)).rgb
+        + texture(blurred, uv + texel * vec2( 1.0,  1.0)).rgb;
+    color /= 16.0;
+
+    out_color = vec4(texture(current, uv).rgb + color, 1.0);
+}
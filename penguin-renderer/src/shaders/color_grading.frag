@@ -0,0 +1,42 @@
+#version 460
+
+layout (location = 0) in vec2 uv;
+
+layout (location = 0) out vec4 out_color;
+
+layout (set = 0, binding = 0) uniform sampler2D source;
+layout (set = 0, binding = 1) uniform sampler3D lut;
+
+layout (push_constant) uniform ColorGradingParams {
+    float strength;
+    // the source decodes sRGB on read, but the table is indexed with encoded colors
+    uint encode_input;
+} params;
+
+vec3 linear_to_srgb(vec3 color) {
+    vec3 low = color * 12.92;
+    vec3 high = 1.055 * pow(color, vec3(1.0 / 2.4)) - 0.055;
+    return mix(high, low, lessThanEqual(color, vec3(0.0031308)));
+}
+
+vec3 srgb_to_linear(vec3 color) {
+    vec3 low = color / 12.92;
+    vec3 high = pow((color + 0.055) / 1.055, vec3(2.4));
+    return mix(high, low, lessThanEqual(color, vec3(0.04045)));
+}
+
+void main() {
+    vec4 color = texture(source, uv);
+    vec3 encoded = params.encode_input != 0u ? linear_to_srgb(color.rgb) : color.rgb;
+
+    // sample the centers of the first and last texels for 0 and 1
+    float size = float(textureSize(lut, 0).x);
+    vec3 lut_coordinates = clamp(encoded, 0.0, 1.0) * ((size - 1.0) / size) + 0.5 / size;
+    vec3 graded = texture(lut, lut_coordinates).rgb;
+
+    if (params.encode_input != 0u) {
+        graded = srgb_to_linear(graded);
+    }
+
+    out_color = vec4(mix(color.rgb, graded, params.strength), color.a);
+}
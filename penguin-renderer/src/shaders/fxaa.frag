@@ -0,0 +1,69 @@
+#version 460
+
+layout (location = 0) in vec2 uv;
+
+layout (location = 0) out vec4 out_color;
+
+layout (set = 0, binding = 0) uniform sampler2D source;
+
+layout (push_constant) uniform FxaaParams {
+    float edge_threshold;
+    float edge_threshold_min;
+    float span_max;
+} params;
+
+const float REDUCE_MUL = 1.0 / 8.0;
+const float REDUCE_MIN = 1.0 / 128.0;
+
+float luma(vec3 color) {
+    return dot(color, vec3(0.299, 0.587, 0.114));
+}
+
+// based on Timothy Lottes' FXAA, blurs along the edge direction found from the corner lumas
+void main() {
+    vec2 texel = 1.0 / vec2(textureSize(source, 0));
+
+    vec4 center = texture(source, uv);
+    float luma_m = luma(center.rgb);
+    float luma_nw = luma(texture(source, uv + texel * vec2(-1.0, -1.0)).rgb);
+    float luma_ne = luma(texture(source, uv + texel * vec2( 1.0, -1.0)).rgb);
+    float luma_sw = luma(texture(source, uv + texel * vec2(-1.0,  1.0)).rgb);
+    float luma_se = luma(texture(source, uv + texel * vec2( 1.0,  1.0)).rgb);
+
+    float luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    float luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+    // not an edge
+    if (luma_max - luma_min < max(params.edge_threshold_min, luma_max * params.edge_threshold)) {
+        out_color = center;
+        return;
+    }
+
+    vec2 direction = vec2(
+        -((luma_nw + luma_ne) - (luma_sw + luma_se)),
+        (luma_nw + luma_sw) - (luma_ne + luma_se)
+    );
+
+    float direction_reduce = max(
+        (luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * REDUCE_MUL,
+        REDUCE_MIN
+    );
+    float inverse_direction_min = 1.0 / (min(abs(direction.x), abs(direction.y)) + direction_reduce);
+    direction = clamp(direction * inverse_direction_min, vec2(-params.span_max), vec2(params.span_max))
+        * texel;
+
+    vec3 color_a = 0.5 * (
+        texture(source, uv + direction * (1.0 / 3.0 - 0.5)).rgb +
+        texture(source, uv + direction * (2.0 / 3.0 - 0.5)).rgb
+    );
+    vec3 color_b = color_a * 0.5 + 0.25 * (
+        texture(source, uv + direction * -0.5).rgb +
+        texture(source, uv + direction * 0.5).rgb
+    );
+
+    // the wider blur went past the edge
+    float luma_b = luma(color_b);
+    vec3 color = (luma_b < luma_min || luma_b > luma_max) ? color_a : color_b;
+
+    out_color = vec4(color, center.a);
+}
@@ -0,0 +1,256 @@
+#version 460
+
+layout (location = 0) in vec3 color;
+layout (location = 1) in vec2 uv;
+layout (location = 3) in vec3 world_position;
+layout (location = 4) in vec3 world_normal;
+layout (location = 5) in vec4 world_tangent;
+
+layout (location = 0) out vec4 out_color;
+
+layout (set = 0, binding = 0) uniform GPUCameraData {
+    // xyz is the camera's world position
+    vec4 data;
+    mat4 proj_view;
+} u_camera;
+
+layout (set = 0, binding = 1) uniform UniformFrameData {
+    vec4 ambient_color;
+    uint light_count;
+} ufd;
+
+const uint LIGHT_DIRECTIONAL = 0u;
+const uint LIGHT_POINT = 1u;
+const uint LIGHT_SPOT = 2u;
+
+struct Light {
+    vec3 position;
+    float range;
+    vec3 direction;
+    uint kind;
+    vec3 color;
+    float intensity;
+    float spot_cos_inner;
+    float spot_cos_outer;
+    uint casts_shadows;
+};
+
+layout (std430, set = 0, binding = 2) readonly buffer LightBuffer {
+    Light lights[];
+} light_buffer;
+
+// GPUMaterialData
+layout (push_constant) uniform MaterialParams {
+    vec4 base_color;
+    vec3 emissive;
+    float metallic;
+    float roughness;
+    float occlusion_strength;
+    float normal_scale;
+    // indices into the bindless texture array, in the order of the MAP_ constants
+    uint texture_indices[5];
+    // 0 unless the material is alpha tested
+    float alpha_cutoff;
+} material;
+
+// PbrMap
+const uint MAP_ALBEDO = 0u;
+const uint MAP_NORMAL = 1u;
+const uint MAP_METALLIC_ROUGHNESS = 2u;
+const uint MAP_EMISSIVE = 3u;
+const uint MAP_OCCLUSION = 4u;
+
+layout (set = 2, binding = 0) uniform sampler2D albedo_map;
+layout (set = 2, binding = 1) uniform sampler2D normal_map;
+layout (set = 2, binding = 2) uniform sampler2D metallic_roughness_map;
+layout (set = 2, binding = 3) uniform sampler2D emissive_map;
+layout (set = 2, binding = 4) uniform sampler2D occlusion_map;
+
+vec4 sample_map(uint map, vec2 uv) {
+    switch (map) {
+        case MAP_ALBEDO: return texture(albedo_map, uv);
+        case MAP_NORMAL: return texture(normal_map, uv);
+        case MAP_METALLIC_ROUGHNESS: return texture(metallic_roughness_map, uv);
+        case MAP_EMISSIVE: return texture(emissive_map, uv);
+        default: return texture(occlusion_map, uv);
+    }
+}
+
+layout (set = 3, binding = 0) uniform GPUShadowData {
+    // MAX_SHADOW_CASCADES
+    mat4 cascade_view_proj[4];
+    uint cascade_count;
+    uint pcf_radius;
+} shadow_data;
+
+layout (set = 3, binding = 1) uniform sampler2DShadow shadow_map;
+
+// 1 where the light reaches the position, 0 in full shadow
+float shadow_visibility(vec3 position) {
+    for (uint cascade = 0u; cascade < shadow_data.cascade_count; cascade++) {
+        vec4 clip = shadow_data.cascade_view_proj[cascade] * vec4(position, 1.0);
+        vec3 ndc = clip.xyz / clip.w;
+        if (any(greaterThan(abs(ndc.xy), vec2(1.0))) || ndc.z < 0.0 || ndc.z > 1.0) {
+            continue;
+        }
+
+        // the cascades are tiles next to each other, the filter stays inside of this one
+        vec2 tile_uv = ndc.xy * 0.5 + 0.5;
+        vec2 tile_texel = vec2(float(shadow_data.cascade_count), 1.0) / vec2(textureSize(shadow_map, 0));
+        float tile_width = 1.0 / float(shadow_data.cascade_count);
+
+        int radius = int(shadow_data.pcf_radius);
+        float visibility = 0.0;
+        for (int x = -radius; x <= radius; x++) {
+            for (int y = -radius; y <= radius; y++) {
+                vec2 uv = clamp(tile_uv + vec2(x, y) * tile_texel, tile_texel * 0.5, 1.0 - tile_texel * 0.5);
+                vec2 atlas_uv = vec2((float(cascade) + uv.x) * tile_width, uv.y);
+                visibility += texture(shadow_map, vec3(atlas_uv, ndc.z));
+            }
+        }
+        float sample_count = float((2 * radius + 1) * (2 * radius + 1));
+        return visibility / sample_count;
+    }
+
+    // outside of every cascade
+    return 1.0;
+}
+
+// smooth falloff that reaches 0 at the light's range
+float distance_attenuation(float distance, float range) {
+    float ratio = distance / range;
+    float window = clamp(1.0 - ratio * ratio * ratio * ratio, 0.0, 1.0);
+    return window * window / (distance * distance + 1.0);
+}
+
+const float PI = 3.14159265359;
+// below this the specular highlight of point lights gets smaller than a pixel and flickers
+const float MIN_ROUGHNESS = 0.045;
+
+struct Surface {
+    vec3 position;
+    vec3 normal;
+    // towards the camera
+    vec3 view;
+    vec3 albedo;
+    float metallic;
+    float roughness;
+};
+
+// GGX / Trowbridge-Reitz normal distribution
+float distribution_ggx(float n_dot_h, float roughness) {
+    float alpha = roughness * roughness;
+    float alpha2 = alpha * alpha;
+    float denominator = n_dot_h * n_dot_h * (alpha2 - 1.0) + 1.0;
+    return alpha2 / (PI * denominator * denominator);
+}
+
+// height correlated Smith masking-shadowing, already divided by 4 n.l n.v
+float visibility_smith_ggx(float n_dot_v, float n_dot_l, float roughness) {
+    float alpha = roughness * roughness;
+    float alpha2 = alpha * alpha;
+    float ggx_v = n_dot_l * sqrt(n_dot_v * n_dot_v * (1.0 - alpha2) + alpha2);
+    float ggx_l = n_dot_v * sqrt(n_dot_l * n_dot_l * (1.0 - alpha2) + alpha2);
+    return 0.5 / max(ggx_v + ggx_l, 1e-5);
+}
+
+vec3 fresnel_schlick(float v_dot_h, vec3 f0) {
+    return f0 + (1.0 - f0) * pow(1.0 - v_dot_h, 5.0);
+}
+
+// reflectance at normal incidence, dielectrics reflect about 4%
+vec3 surface_f0(Surface surface) {
+    return mix(vec3(0.04), surface.albedo, surface.metallic);
+}
+
+// Cook-Torrance specular plus Lambert diffuse, times n.l
+vec3 brdf(Surface surface, vec3 to_light) {
+    vec3 half_vector = normalize(surface.view + to_light);
+    float n_dot_l = max(dot(surface.normal, to_light), 0.0);
+    float n_dot_v = max(dot(surface.normal, surface.view), 1e-4);
+    float n_dot_h = max(dot(surface.normal, half_vector), 0.0);
+    float v_dot_h = max(dot(surface.view, half_vector), 0.0);
+
+    vec3 fresnel = fresnel_schlick(v_dot_h, surface_f0(surface));
+    vec3 specular = fresnel
+        * distribution_ggx(n_dot_h, surface.roughness)
+        * visibility_smith_ggx(n_dot_v, n_dot_l, surface.roughness);
+    // metals don't have diffuse reflection, and what's reflected specularly isn't diffused
+    vec3 diffuse = (1.0 - fresnel) * (1.0 - surface.metallic) * surface.albedo / PI;
+
+    return (diffuse + specular) * n_dot_l;
+}
+
+// light reflected towards the camera by all lights
+vec3 direct_lighting(Surface surface) {
+    vec3 result = vec3(0.0);
+
+    for (uint index = 0u; index < ufd.light_count; index++) {
+        Light light = light_buffer.lights[index];
+
+        vec3 to_light;
+        float attenuation = 1.0;
+        if (light.kind == LIGHT_DIRECTIONAL) {
+            to_light = -light.direction;
+        } else {
+            vec3 offset = light.position - surface.position;
+            float distance = length(offset);
+            to_light = offset / distance;
+            attenuation = distance_attenuation(distance, light.range);
+
+            if (light.kind == LIGHT_SPOT) {
+                float cos_angle = dot(-to_light, light.direction);
+                attenuation *= smoothstep(light.spot_cos_outer, light.spot_cos_inner, cos_angle);
+            }
+        }
+
+        if (light.casts_shadows != 0u) {
+            attenuation *= shadow_visibility(surface.position);
+        }
+
+        result += light.color * light.intensity * attenuation * brdf(surface, to_light);
+    }
+
+    return result;
+}
+
+// the vertex normal, perturbed by the normal map
+vec3 surface_normal() {
+    vec3 normal = normalize(world_normal);
+    // re-orthogonalized, interpolation bends the tangent away from the normal
+    vec3 tangent = world_tangent.xyz - normal * dot(normal, world_tangent.xyz);
+    if (dot(tangent, tangent) < 1e-8) {
+        return normal;
+    }
+    tangent = normalize(tangent);
+    vec3 bitangent = cross(normal, tangent) * world_tangent.w;
+
+    vec3 tangent_normal = sample_map(MAP_NORMAL, uv).xyz * 2.0 - 1.0;
+    tangent_normal.xy *= material.normal_scale;
+    return normalize(mat3(tangent, bitangent, normal) * tangent_normal);
+}
+
+void main() {
+    vec4 base_color = material.base_color * sample_map(MAP_ALBEDO, uv);
+    if (base_color.a < material.alpha_cutoff) {
+        discard;
+    }
+    vec4 metallic_roughness = sample_map(MAP_METALLIC_ROUGHNESS, uv);
+
+    Surface surface;
+    surface.position = world_position;
+    surface.normal = surface_normal();
+    surface.view = normalize(u_camera.data.xyz - world_position);
+    surface.albedo = base_color.rgb;
+    surface.metallic = clamp(material.metallic * metallic_roughness.b, 0.0, 1.0);
+    surface.roughness = clamp(material.roughness * metallic_roughness.g, MIN_ROUGHNESS, 1.0);
+
+    float occlusion = mix(1.0, sample_map(MAP_OCCLUSION, uv).r, material.occlusion_strength);
+    vec3 ambient_reflectance = surface.albedo * (1.0 - surface.metallic) + surface_f0(surface);
+    vec3 ambient = ufd.ambient_color.rgb * ambient_reflectance * occlusion;
+
+    vec3 emissive = material.emissive * sample_map(MAP_EMISSIVE, uv).rgb;
+
+    // the alpha only matters to transparent materials, whose pipelines blend with it
+    out_color = vec4(ambient + direct_lighting(surface) + emissive, base_color.a);
+}
@@ -0,0 +1,21 @@
+#version 460
+
+struct RenderObjectData {
+    mat4 model_transform;
+};
+
+layout (std140, set = 0, binding = 0) readonly buffer GPUObjectDataNew {
+    RenderObjectData objects[];
+} object_buffer;
+
+// world space to the clip space of the cascade's shadow map tile
+layout (push_constant) uniform ShadowCascade {
+    mat4 view_proj;
+} cascade;
+
+layout (location = 0) in vec3 v_position;
+
+void main() {
+    mat4 model_matrix = object_buffer.objects[gl_BaseInstance].model_transform;
+    gl_Position = cascade.view_proj * model_matrix * vec4(v_position, 1.0);
+}
@@ -6,6 +6,7 @@ layout (location = 0) out vec4 out_color;
 
 layout(set = 0, binding = 1) uniform UniformFrameData {
     vec4 ambient_color;
+    uint light_count;
 } ufd;
 
 
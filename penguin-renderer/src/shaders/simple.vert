@@ -27,9 +27,13 @@ layout (location = 0) in vec3 v_position;
 layout (location = 1) in vec3 v_normal;
 layout (location = 2) in vec3 v_color;
 layout (location = 3) in vec2 v_uv;
+layout (location = 4) in vec4 v_tangent;
 
 layout (location = 0) out vec3 frag_color;
 layout (location = 1) out vec2 uv;
+layout (location = 3) out vec3 world_position;
+layout (location = 4) out vec3 world_normal;
+layout (location = 5) out vec4 world_tangent;
 
 void main() {
     mat4 model_matrix = object_buffer.objects[gl_BaseInstance].model_transform;
@@ -37,6 +41,10 @@ void main() {
     mat4 transform_matrix = (u_camera.proj_view * model_matrix);
 
     gl_Position = transform_matrix * vec4(v_position, 1.0);
+    world_position = (model_matrix * vec4(v_position, 1.0)).xyz;
+    // only correct for uniform scaling, which is all the model transforms do so far
+    world_normal = mat3(model_matrix) * v_normal;
+    world_tangent = vec4(mat3(model_matrix) * v_tangent.xyz, v_tangent.w);
 
     frag_color = v_color;
     uv = v_uv;
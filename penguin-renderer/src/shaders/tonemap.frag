@@ -0,0 +1,77 @@
+#version 460
+
+layout (location = 0) in vec2 uv;
+
+layout (location = 0) out vec4 out_color;
+
+layout (set = 0, binding = 0) uniform sampler2D hdr_color;
+
+layout (push_constant) uniform TonemapParams {
+    float exposure;
+    uint tonemap_operator;
+    // set when the output image isn't sRGB, so the hardware doesn't encode it on write
+    uint encode_srgb;
+} params;
+
+const uint OPERATOR_ACES = 0u;
+const uint OPERATOR_REINHARD = 1u;
+const uint OPERATOR_FILMIC = 2u;
+
+// Krzysztof Narkowicz' fit of the ACES curve
+vec3 aces(vec3 x) {
+    const float a = 2.51;
+    const float b = 0.03;
+    const float c = 2.43;
+    const float d = 0.59;
+    const float e = 0.14;
+    return clamp((x * (a * x + b)) / (x * (c * x + d) + e), 0.0, 1.0);
+}
+
+vec3 reinhard(vec3 x) {
+    return x / (1.0 + x);
+}
+
+// John Hable's Uncharted 2 curve
+vec3 hable(vec3 x) {
+    const float a = 0.15;
+    const float b = 0.50;
+    const float c = 0.10;
+    const float d = 0.20;
+    const float e = 0.02;
+    const float f = 0.30;
+    return ((x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f)) - e / f;
+}
+
+vec3 filmic(vec3 x) {
+    const float exposure_bias = 2.0;
+    const vec3 white_point = vec3(11.2);
+    return hable(x * exposure_bias) / hable(white_point);
+}
+
+vec3 linear_to_srgb(vec3 color) {
+    vec3 low = color * 12.92;
+    vec3 high = 1.055 * pow(color, vec3(1.0 / 2.4)) - 0.055;
+    return mix(high, low, lessThanEqual(color, vec3(0.0031308)));
+}
+
+void main() {
+    vec3 color = texture(hdr_color, uv).rgb * params.exposure;
+
+    switch (params.tonemap_operator) {
+        case OPERATOR_REINHARD:
+            color = reinhard(color);
+            break;
+        case OPERATOR_FILMIC:
+            color = filmic(color);
+            break;
+        default:
+            color = aces(color);
+            break;
+    }
+
+    if (params.encode_srgb != 0u) {
+        color = linear_to_srgb(color);
+    }
+
+    out_color = vec4(color, 1.0);
+}
@@ -0,0 +1,23 @@
+#version 460
+
+layout (location = 0) in vec2 uv;
+
+layout (location = 0) out vec4 out_color;
+
+layout (set = 0, binding = 0) uniform sampler2D source;
+
+layout (push_constant) uniform VignetteParams {
+    float intensity;
+    float radius;
+    float smoothness;
+} params;
+
+void main() {
+    vec4 color = texture(source, uv);
+
+    // 0 in the center, 1 in the corners
+    float distance_to_center = length(uv - 0.5) * sqrt(2.0);
+    float darkening = smoothstep(params.radius - params.smoothness, params.radius, distance_to_center);
+
+    out_color = vec4(color.rgb * (1.0 - darkening * params.intensity), color.a);
+}